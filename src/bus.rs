@@ -10,6 +10,7 @@ use crate::{
         exi::ExternalInterface,
         gp_fifo::GpFifo,
         memory::{Memory, MEMORY_SIZE},
+        mi::MemoryInterface,
         mmio::Mmio,
         pe::PixelEngine,
         pi::ProcessorInterface,
@@ -35,6 +36,7 @@ pub struct Bus {
     pub(crate) dsp: DspInterface,
     pub(crate) exi: ExternalInterface,
     pub(crate) gp_fifo: GpFifo,
+    pub(crate) mi: MemoryInterface,
     pub(crate) pi: ProcessorInterface,
     pub(crate) pe: PixelEngine,
     pub(crate) si: SerialInterface,
@@ -57,6 +59,7 @@ impl Default for Bus {
         mmio.register_device::<ProcessorInterface>();
         mmio.register_device::<ExternalInterface>();
         mmio.register_device::<GpFifo>();
+        mmio.register_device::<MemoryInterface>();
         mmio.register_device::<VideoInterface>();
         mmio.register_device::<SerialInterface>();
 
@@ -71,6 +74,7 @@ impl Default for Bus {
             dsp: Default::default(),
             exi,
             gp_fifo: Default::default(),
+            mi: Default::default(),
             pe: Default::default(),
             pi: Default::default(),
             si: Default::default(),
@@ -80,6 +84,23 @@ impl Default for Bus {
 }
 
 impl Bus {
+    /// Reset the hardware to its power on state. The bootrom, main memory, SRAM, the disc and
+    /// inserted devices are kept.
+    pub fn reset(&mut self) {
+        self.l1_cache = Default::default();
+        self.ai = Default::default();
+        self.cp = Default::default();
+        self.di.reset();
+        self.dsp.reset();
+        self.exi.reset();
+        self.gp_fifo = Default::default();
+        self.mi = Default::default();
+        self.pi.reset();
+        self.pe = Default::default();
+        self.si.reset();
+        self.vi.reset();
+    }
+
     pub fn read<T>(&mut self, cpu_state: &mut CpuState, addr: u32) -> T
     where
         Mmio: ReadWrite<T>,
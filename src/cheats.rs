@@ -0,0 +1,350 @@
+mod action_replay;
+mod gecko;
+
+use std::{
+    cell::Cell,
+    collections::HashSet,
+    fmt, fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use byteorder::{BigEndian, ByteOrder};
+
+use self::{action_replay::ActionReplayCode, gecko::GeckoCode};
+use crate::{
+    bus::{Bus, ReadWrite},
+    cpu::{l1_cache::L1Cache, Cpu},
+    hw::{
+        bootrom::Bootrom,
+        memory::{Memory, MEMORY_SIZE},
+        mmio::Mmio,
+    },
+};
+
+/// Gecko code list (.gct) header and terminator
+const GCT_MAGIC: u32 = 0x00D0_C0DE;
+const GCT_END: u32 = 0xF000_0000;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Gecko,
+    GeckoEnabled,
+    ActionReplay,
+    ActionReplayEnabled,
+    Other,
+}
+
+/// Gecko and unencrypted Action Replay codes applied once per frame
+#[derive(Default)]
+pub struct Cheats {
+    gecko: Vec<GeckoCode>,
+    action_replay: Vec<ActionReplayCode>,
+    /// Gecko registers, these persist between frames
+    gecko_registers: [u32; 16],
+}
+
+impl Cheats {
+    /// Load a binary `.gct` code list or a Dolphin style text/ini file
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let data = fs::read(path)?;
+
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("gct") => {
+                let name = path.file_stem().unwrap_or_default().to_string_lossy();
+
+                self.load_gct(&name, &data)
+            }
+            _ => self.load_text(&String::from_utf8_lossy(&data)),
+        }
+    }
+
+    pub fn load_gct(&mut self, name: &str, data: &[u8]) -> Result<(), Error> {
+        if data.len() < 8
+            || BigEndian::read_u32(&data[0..]) != GCT_MAGIC
+            || BigEndian::read_u32(&data[4..]) != GCT_MAGIC
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Not a valid gecko code list",
+            ));
+        }
+
+        let mut lines = Vec::new();
+
+        for line in data[8..].chunks_exact(8) {
+            let line = (BigEndian::read_u32(line), BigEndian::read_u32(&line[4..]));
+
+            if line == (GCT_END, 0) {
+                break;
+            }
+
+            lines.push(line);
+        }
+
+        info!("Loaded gecko code list {} ({} lines)", name, lines.len());
+
+        self.gecko.push(GeckoCode {
+            name: name.to_string(),
+            lines,
+            warning: WarnOnce::default(),
+        });
+
+        Ok(())
+    }
+
+    /// Parse codes from `[Gecko]` and `[ActionReplay]` sections. Codes start with a `$Name`
+    /// line, `*` lines are notes. When a `_Enabled` section exists only the codes listed there
+    /// are kept. Lines before any section header are treated as Gecko codes.
+    pub fn load_text(&mut self, text: &str) -> Result<(), Error> {
+        let mut section = Section::Gecko;
+        let mut gecko: Vec<(String, Vec<(u32, u32)>)> = Vec::new();
+        let mut action_replay: Vec<(String, Vec<(u32, u32)>)> = Vec::new();
+        let mut gecko_enabled: Option<HashSet<String>> = None;
+        let mut action_replay_enabled: Option<HashSet<String>> = None;
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('*') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = match &line[1..line.len() - 1] {
+                    "Gecko" => Section::Gecko,
+                    "Gecko_Enabled" => Section::GeckoEnabled,
+                    "ActionReplay" => Section::ActionReplay,
+                    "ActionReplay_Enabled" => Section::ActionReplayEnabled,
+                    _ => Section::Other,
+                };
+                continue;
+            }
+
+            let codes = match section {
+                Section::Gecko => &mut gecko,
+                Section::ActionReplay => &mut action_replay,
+                Section::GeckoEnabled | Section::ActionReplayEnabled => {
+                    let enabled = if section == Section::GeckoEnabled {
+                        &mut gecko_enabled
+                    } else {
+                        &mut action_replay_enabled
+                    };
+
+                    if let Some(name) = line.strip_prefix('$') {
+                        enabled
+                            .get_or_insert_with(HashSet::new)
+                            .insert(name.trim().to_string());
+                    }
+                    continue;
+                }
+                Section::Other => continue,
+            };
+
+            if let Some(name) = line.strip_prefix('$') {
+                codes.push((name.trim().to_string(), Vec::new()));
+                continue;
+            }
+
+            let code = parse_line(line)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, format!("line {}: {e}", n + 1)))?;
+
+            if codes.is_empty() {
+                codes.push((String::new(), Vec::new()));
+            }
+
+            codes.last_mut().unwrap().1.push(code);
+        }
+
+        let is_enabled = |enabled: &Option<HashSet<String>>, name: &String| {
+            enabled
+                .as_ref()
+                .is_none_or(|enabled| enabled.contains(name))
+        };
+
+        for (name, lines) in gecko {
+            if is_enabled(&gecko_enabled, &name) {
+                info!("Gecko code: {}", name);
+                self.gecko.push(GeckoCode {
+                    name,
+                    lines,
+                    warning: WarnOnce::default(),
+                });
+            }
+        }
+
+        for (name, lines) in action_replay {
+            if is_enabled(&action_replay_enabled, &name) {
+                info!("Action Replay code: {}", name);
+                self.action_replay.push(ActionReplayCode {
+                    name,
+                    lines,
+                    warning: WarnOnce::default(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.gecko.is_empty() && self.action_replay.is_empty()
+    }
+
+    /// Run all codes, called on every VI frame
+    pub fn apply(&mut self, cpu: &mut Cpu, bus: &mut Bus) {
+        let mut hook_addr = gecko::HOOK_BASE;
+
+        for code in self.action_replay.iter() {
+            code.run(cpu, bus);
+        }
+
+        for code in self.gecko.iter() {
+            code.run(&mut self.gecko_registers, &mut hook_addr, cpu, bus);
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Result<(u32, u32), String> {
+    let mut words = line.split_whitespace();
+
+    match (words.next(), words.next(), words.next()) {
+        (Some(a), Some(b), None) if a.len() == 8 && b.len() == 8 => {
+            match (u32::from_str_radix(a, 16), u32::from_str_radix(b, 16)) {
+                (Ok(a), Ok(b)) => Ok((a, b)),
+                _ => Err(format!("invalid code `{line}`")),
+            }
+        }
+        (Some(code), None, None) if code.len() == 15 && code.contains('-') => {
+            Err("encrypted Action Replay codes are not supported".to_string())
+        }
+        _ => Err(format!("invalid code `{line}`")),
+    }
+}
+
+/// Codes and patches may only touch main memory, anything else would raise exceptions on the
+/// emulated cpu
+pub(crate) fn is_ram(addr: u32) -> bool {
+    matches!(addr >> 28, 0x8 | 0xC) && (addr & 0x0FFF_FFFF) < MEMORY_SIZE
+}
+
+/// Main memory as seen by a running code. Addresses are computed while running, the first
+/// invalid one is kept so the code can report it once instead of on every frame.
+pub(crate) struct CodeMemory<'a> {
+    cpu: &'a mut Cpu,
+    bus: &'a mut Bus,
+    invalid: Option<u32>,
+}
+
+impl<'a> CodeMemory<'a> {
+    pub fn new(cpu: &'a mut Cpu, bus: &'a mut Bus) -> Self {
+        CodeMemory {
+            cpu,
+            bus,
+            invalid: None,
+        }
+    }
+
+    pub fn read<T>(&mut self, addr: u32) -> Option<T>
+    where
+        Mmio: ReadWrite<T>,
+        Memory: ReadWrite<T>,
+        L1Cache: ReadWrite<T>,
+        Bootrom: ReadWrite<T>,
+    {
+        if is_ram(addr) {
+            self.cpu.read(self.bus, addr)
+        } else {
+            self.invalid.get_or_insert(addr);
+            None
+        }
+    }
+
+    pub fn write<T>(&mut self, addr: u32, val: T)
+    where
+        Mmio: ReadWrite<T>,
+        Memory: ReadWrite<T>,
+        L1Cache: ReadWrite<T>,
+    {
+        if is_ram(addr) {
+            self.cpu.write(self.bus, addr, val);
+        } else {
+            self.invalid.get_or_insert(addr);
+        }
+    }
+
+    /// First address outside main memory the code tried to access
+    pub fn invalid(&self) -> Option<u32> {
+        self.invalid
+    }
+}
+
+/// Codes run on every frame, a problem is only logged the first time so a broken code does not
+/// flood the log
+#[derive(Default)]
+pub(crate) struct WarnOnce(Cell<bool>);
+
+impl WarnOnce {
+    pub fn warn(&self, message: fmt::Arguments) {
+        if !self.0.replace(true) {
+            warn!("{}, further warnings for this code are not shown", message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_text() {
+        let mut cheats = Cheats::default();
+
+        let text = "
+            [Gecko]
+            $Infinite Lives [someone]
+            04123456 00000063
+            *Keeps lives at 99
+            $Moon Jump
+            28123456 00000010
+            04200000 3F800000
+            E0000000 80008000
+            [Gecko_Enabled]
+            $Moon Jump
+            [ActionReplay]
+            $Max Coins
+            02123456 000003E7
+        ";
+
+        cheats.load_text(text).unwrap();
+
+        assert_eq!(cheats.gecko.len(), 1);
+        assert_eq!(cheats.gecko[0].name, "Moon Jump");
+        assert_eq!(cheats.gecko[0].lines.len(), 3);
+        assert_eq!(cheats.action_replay.len(), 1);
+        assert_eq!(
+            cheats.action_replay[0].lines,
+            vec![(0x0212_3456, 0x0000_03E7)]
+        );
+
+        assert!(cheats.load_text("[ActionReplay]\nABCD-EFGH-IJKLM").is_err());
+        assert!(cheats.load_text("0412345 00000063").is_err());
+    }
+
+    #[test]
+    fn load_gct() {
+        let mut cheats = Cheats::default();
+
+        let data = [
+            0x00, 0xD0, 0xC0, 0xDE, 0x00, 0xD0, 0xC0, 0xDE, // header
+            0x04, 0x12, 0x34, 0x56, 0x00, 0x00, 0x00, 0x63, // 32-bit write
+            0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // end of codes
+        ];
+
+        cheats.load_gct("test", &data).unwrap();
+
+        assert_eq!(cheats.gecko[0].lines, vec![(0x0412_3456, 0x0000_0063)]);
+        assert!(cheats.load_gct("test", &data[8..]).is_err());
+    }
+}
@@ -0,0 +1,346 @@
+use super::{CodeMemory, WarnOnce};
+use crate::{bus::Bus, cpu::Cpu};
+
+const SIZE_8: u32 = 0;
+const SIZE_16: u32 = 1;
+const SIZE_32: u32 = 2;
+const SIZE_FLOAT: u32 = 3;
+
+const ZERO_END: u32 = 0;
+const ZERO_NORMAL: u32 = 2;
+const ZERO_FILL: u32 = 4;
+
+/// Terminator of the "skip until" conditional block
+const SKIP_END: (u32, u32) = (0, 0x4000_0000);
+
+pub struct ActionReplayCode {
+    pub name: String,
+    pub lines: Vec<(u32, u32)>,
+    pub warning: WarnOnce,
+}
+
+/// Decoded address word: `TTTSSXXX XXXXXXXX ...` subtype, type, size and address
+struct Address {
+    addr: u32,
+    size: u32,
+    kind: u32,
+    subtype: u32,
+}
+
+impl From<u32> for Address {
+    fn from(a: u32) -> Self {
+        Address {
+            addr: (a & 0x01FF_FFFF) | 0x8000_0000,
+            size: (a >> 25) & 3,
+            kind: (a >> 27) & 7,
+            subtype: (a >> 30) & 3,
+        }
+    }
+}
+
+impl ActionReplayCode {
+    pub fn run(&self, cpu: &mut Cpu, bus: &mut Bus) {
+        let memory = &mut CodeMemory::new(cpu, bus);
+        let mut i = 0;
+
+        while i < self.lines.len() {
+            let (a, b) = self.lines[i];
+            i += 1;
+
+            if a == 0 {
+                match b >> 29 {
+                    ZERO_END => break,
+                    ZERO_NORMAL => (),
+                    ZERO_FILL => {
+                        let Some(&next) = self.lines.get(i) else {
+                            break;
+                        };
+                        i += 1;
+
+                        let addr = Address::from(b);
+
+                        if addr.size == SIZE_FLOAT {
+                            memory_copy(memory, b, next);
+                        } else {
+                            fill_and_slide(memory, &addr, next);
+                        }
+                    }
+                    _ => {
+                        self.warning.warn(format_args!(
+                            "action replay code `{}`: unsupported zero code",
+                            self.name
+                        ));
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            let addr = Address::from(a);
+
+            if addr.kind == 0 {
+                match addr.subtype {
+                    0 => write_fill(memory, &addr, b),
+                    1 => {
+                        let Some(pointer) = memory.read::<u32>(addr.addr) else {
+                            continue;
+                        };
+
+                        match addr.size {
+                            SIZE_8 => {
+                                let pointer = pointer.wrapping_add(b >> 8);
+                                memory.write(pointer, b as u8);
+                            }
+                            SIZE_16 => {
+                                let pointer = pointer.wrapping_add((b >> 16) * 2);
+                                memory.write(pointer, b as u16);
+                            }
+                            _ => memory.write(pointer, b),
+                        }
+                    }
+                    2 => add(memory, &addr, b),
+                    _ => (),
+                }
+                continue;
+            }
+
+            if self.condition(memory, &addr, b) {
+                continue;
+            }
+
+            match addr.subtype {
+                0 => i += 1,
+                1 => i += 2,
+                2 => {
+                    while i < self.lines.len() && self.lines[i] != SKIP_END {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                _ => break,
+            }
+        }
+
+        if let Some(addr) = memory.invalid() {
+            self.warning.warn(format_args!(
+                "action replay code `{}` accessed invalid address {:#010x}",
+                self.name, addr
+            ));
+        }
+    }
+
+    fn condition(&self, memory: &mut CodeMemory, addr: &Address, b: u32) -> bool {
+        // bits to shift out when sign extending for the signed comparisons
+        let (value, b, shift) = match addr.size {
+            SIZE_8 => {
+                let Some(value) = memory.read::<u8>(addr.addr) else {
+                    return false;
+                };
+                (value as u32, b & 0xFF, 24)
+            }
+            SIZE_16 => {
+                let Some(value) = memory.read::<u16>(addr.addr) else {
+                    return false;
+                };
+                (value as u32, b & 0xFFFF, 16)
+            }
+            _ => {
+                let Some(value) = memory.read::<u32>(addr.addr) else {
+                    return false;
+                };
+                (value, b, 0)
+            }
+        };
+
+        if addr.size == SIZE_FLOAT {
+            let (value, b) = (f32::from_bits(value), f32::from_bits(b));
+
+            return match addr.kind {
+                1 => value == b,
+                2 => value != b,
+                3 | 5 => value < b,
+                4 | 6 => value > b,
+                _ => value as u32 & b as u32 != 0,
+            };
+        }
+
+        let signed = |v: u32| ((v << shift) as i32) >> shift;
+
+        match addr.kind {
+            1 => value == b,
+            2 => value != b,
+            3 => signed(value) < signed(b),
+            4 => signed(value) > signed(b),
+            5 => value < b,
+            6 => value > b,
+            _ => value & b != 0,
+        }
+    }
+}
+
+fn write_fill(memory: &mut CodeMemory, addr: &Address, b: u32) {
+    match addr.size {
+        SIZE_8 => {
+            for n in 0..=(b >> 8) {
+                memory.write(addr.addr.wrapping_add(n), b as u8);
+            }
+        }
+        SIZE_16 => {
+            for n in 0..=(b >> 16) {
+                memory.write(addr.addr.wrapping_add(n * 2), b as u16);
+            }
+        }
+        _ => memory.write(addr.addr, b),
+    }
+}
+
+fn add(memory: &mut CodeMemory, addr: &Address, b: u32) {
+    match addr.size {
+        SIZE_8 => {
+            if let Some(value) = memory.read::<u8>(addr.addr) {
+                memory.write(addr.addr, value.wrapping_add(b as u8));
+            }
+        }
+        SIZE_16 => {
+            if let Some(value) = memory.read::<u16>(addr.addr) {
+                memory.write(addr.addr, value.wrapping_add(b as u16));
+            }
+        }
+        SIZE_32 => {
+            if let Some(value) = memory.read::<u32>(addr.addr) {
+                memory.write(addr.addr, value.wrapping_add(b));
+            }
+        }
+        _ => {
+            if let Some(value) = memory.read::<u32>(addr.addr) {
+                let value = f32::from_bits(value) + b as f32;
+                memory.write(addr.addr, value.to_bits());
+            }
+        }
+    }
+}
+
+/// `00000000 8XXXXXXX` `YYYYYYYY SSNNIIII`: write Y at X, N+1 times, incrementing the value by S
+/// and the address by I (in units of the write size)
+fn fill_and_slide(memory: &mut CodeMemory, addr: &Address, (value, b): (u32, u32)) {
+    let value_inc = (b >> 24) as i8 as u32;
+    let count = (b >> 16) & 0xFF;
+    let addr_inc = b as u16 as i16 as u32;
+
+    let mut target = addr.addr;
+    let mut value = value;
+
+    for _ in 0..=count {
+        match addr.size {
+            SIZE_8 => memory.write(target, value as u8),
+            SIZE_16 => memory.write(target, value as u16),
+            _ => memory.write(target, value),
+        }
+
+        let scale = match addr.size {
+            SIZE_8 => 1,
+            SIZE_16 => 2,
+            _ => 4,
+        };
+
+        target = target.wrapping_add(addr_inc.wrapping_mul(scale));
+        value = value.wrapping_add(value_inc);
+    }
+}
+
+/// `00000000 86XXXXXX` `8YYYYYYY 0000NNNN`: copy N bytes from X to Y
+fn memory_copy(memory: &mut CodeMemory, src: u32, (dst, b): (u32, u32)) {
+    let src = (src & 0x01FF_FFFF) | 0x8000_0000;
+    let dst = (dst & 0x01FF_FFFF) | 0x8000_0000;
+
+    for n in 0..(b & 0xFFFF) {
+        if let Some(byte) = memory.read::<u8>(src.wrapping_add(n)) {
+            memory.write(dst.wrapping_add(n), byte);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(lines: &[(u32, u32)], cpu: &mut Cpu, bus: &mut Bus) {
+        let code = ActionReplayCode {
+            name: String::new(),
+            lines: lines.to_vec(),
+            warning: WarnOnce::default(),
+        };
+
+        code.run(cpu, bus);
+    }
+
+    #[test]
+    fn writes_and_conditionals() {
+        let mut cpu = Cpu::default();
+        let mut bus = Bus::default();
+        cpu.emulate_bs2(&mut bus);
+
+        cpu.write::<u32>(&mut bus, 0x8010_0000, 7);
+
+        run(
+            &[
+                (0x0010_0010, 0x0000_02AB), // 8-bit fill, 3 bytes
+                (0x0210_0020, 0x0001_1234), // 16-bit fill, 2 halfwords
+                (0x0410_0030, 0xDEAD_BEEF),
+                (0x0C10_0000, 0x0000_0007), // if [0x80100000] == 7
+                (0x0410_0040, 0x0000_0001),
+                (0x0C10_0000, 0x0000_0008), // if [0x80100000] == 8
+                (0x0410_0044, 0x0000_0001),
+                (0x8410_0000, 0x0000_0003), // [0x80100000] += 3
+            ],
+            &mut cpu,
+            &mut bus,
+        );
+
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0010), Some(0xABAB_AB00));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0020), Some(0x1234_1234));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0030), Some(0xDEAD_BEEF));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0040), Some(1));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0044), Some(0));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0000), Some(10));
+    }
+
+    #[test]
+    fn fill_and_slide() {
+        let mut cpu = Cpu::default();
+        let mut bus = Bus::default();
+        cpu.emulate_bs2(&mut bus);
+
+        run(
+            &[
+                (0x0000_0000, 0x8410_0000),
+                (0x0000_0001, 0x0102_0001), // 3 words, value += 1, addr += 4
+            ],
+            &mut cpu,
+            &mut bus,
+        );
+
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0000), Some(1));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0004), Some(2));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0008), Some(3));
+    }
+
+    #[test]
+    fn invalid_address() {
+        let mut cpu = Cpu::default();
+        let mut bus = Bus::default();
+        cpu.emulate_bs2(&mut bus);
+
+        cpu.write::<u32>(&mut bus, 0x8010_0000, 0xCC00_0000);
+
+        let code = ActionReplayCode {
+            name: String::new(),
+            lines: vec![(0x4410_0000, 0x0000_0001)], // [[0x80100000]] = 1
+            warning: WarnOnce::default(),
+        };
+
+        code.run(&mut cpu, &mut bus);
+
+        assert!(code.warning.0.get());
+    }
+}
@@ -0,0 +1,638 @@
+use super::{CodeMemory, WarnOnce};
+use crate::{bus::Bus, cpu::Cpu};
+
+/// Area used for injected code of insert asm (C2) codes, this is where the code handler lives
+/// on hardware
+pub(super) const HOOK_BASE: u32 = 0x8000_1800;
+const HOOK_END: u32 = 0x8000_3000;
+
+const BASE_ADDRESS: u32 = 0x8000_0000;
+
+/// Upper bound on executed lines per code, guards against runaway goto/repeat loops
+const MAX_STEPS: usize = 0x10000;
+
+const CODE_WRITE_8: u8 = 0x00;
+const CODE_WRITE_16: u8 = 0x02;
+const CODE_WRITE_32: u8 = 0x04;
+const CODE_WRITE_STRING: u8 = 0x06;
+const CODE_WRITE_SERIAL: u8 = 0x08;
+const CODE_IF_EQ_32: u8 = 0x20;
+const CODE_IF_NE_32: u8 = 0x22;
+const CODE_IF_GT_32: u8 = 0x24;
+const CODE_IF_LT_32: u8 = 0x26;
+const CODE_IF_EQ_16: u8 = 0x28;
+const CODE_IF_NE_16: u8 = 0x2A;
+const CODE_IF_GT_16: u8 = 0x2C;
+const CODE_IF_LT_16: u8 = 0x2E;
+const CODE_LOAD_BA: u8 = 0x40;
+const CODE_SET_BA: u8 = 0x42;
+const CODE_STORE_BA: u8 = 0x44;
+const CODE_LOAD_PO: u8 = 0x48;
+const CODE_SET_PO: u8 = 0x4A;
+const CODE_STORE_PO: u8 = 0x4C;
+const CODE_SET_REPEAT: u8 = 0x60;
+const CODE_EXECUTE_REPEAT: u8 = 0x62;
+const CODE_RETURN: u8 = 0x64;
+const CODE_GOTO: u8 = 0x66;
+const CODE_GOSUB: u8 = 0x68;
+const CODE_SET_REGISTER: u8 = 0x80;
+const CODE_LOAD_REGISTER: u8 = 0x82;
+const CODE_STORE_REGISTER: u8 = 0x84;
+const CODE_REGISTER_OP_VALUE: u8 = 0x86;
+const CODE_REGISTER_OP_REGISTER: u8 = 0x88;
+const CODE_MEMORY_COPY_1: u8 = 0x8A;
+const CODE_MEMORY_COPY_2: u8 = 0x8C;
+const CODE_EXECUTE_ASM: u8 = 0xC0;
+const CODE_INSERT_ASM: u8 = 0xC2;
+const CODE_BRANCH: u8 = 0xC6;
+const CODE_FULL_TERMINATOR: u8 = 0xE0;
+const CODE_ENDIF: u8 = 0xE2;
+const CODE_END: u8 = 0xF0;
+
+pub struct GeckoCode {
+    pub name: String,
+    pub lines: Vec<(u32, u32)>,
+    pub warning: WarnOnce,
+}
+
+impl GeckoCode {
+    pub fn run(
+        &self,
+        registers: &mut [u32; 16],
+        hook_addr: &mut u32,
+        cpu: &mut Cpu,
+        bus: &mut Bus,
+    ) {
+        let mut ctx = Context {
+            memory: CodeMemory::new(cpu, bus),
+            registers,
+            ba: BASE_ADDRESS,
+            po: BASE_ADDRESS,
+            conditions: Vec::new(),
+            blocks: [(0, 0); 16],
+            name: &self.name,
+            warning: &self.warning,
+        };
+
+        let mut i = 0;
+        let mut steps = 0;
+
+        while i < self.lines.len() {
+            steps += 1;
+            if steps > MAX_STEPS {
+                self.warning.warn(format_args!(
+                    "gecko code `{}` exceeded {} steps",
+                    self.name, MAX_STEPS
+                ));
+                break;
+            }
+
+            let (a, b) = self.lines[i];
+            let next = i + 1 + extra_lines(a, b);
+            let executing = ctx.conditions.iter().all(|c| *c);
+
+            let byte = (a >> 24) as u8;
+
+            // bit 4 of the codetype selects the pointer address instead of the base address,
+            // except for the terminator codes which are matched exactly
+            let (codetype, pointer) = if byte >= CODE_FULL_TERMINATOR {
+                (byte, false)
+            } else {
+                (byte & 0xEE, byte & 0x10 != 0)
+            };
+
+            let base = if pointer { ctx.po } else { ctx.ba };
+            let addr = base.wrapping_add(a & 0x01FF_FFFF);
+
+            let flow = |t: u32| match t {
+                0 => executing,
+                1 => !executing,
+                _ => true,
+            };
+
+            match codetype {
+                CODE_IF_EQ_32..=CODE_IF_LT_16 => {
+                    // lowest address bit applies an endif before the check
+                    if a & 1 != 0 {
+                        ctx.conditions.pop();
+                    }
+
+                    let executing = ctx.conditions.iter().all(|c| *c);
+                    let addr = addr & !1;
+
+                    let result = executing && ctx.condition(codetype, addr, b);
+
+                    ctx.conditions.push(result);
+                }
+                CODE_FULL_TERMINATOR => {
+                    ctx.conditions.clear();
+                    ctx.set_high_addresses(b);
+                }
+                CODE_ENDIF => {
+                    let count = a & 0xFF;
+
+                    for _ in 0..count {
+                        ctx.conditions.pop();
+                    }
+
+                    // else
+                    if (a >> 20) & 0xF == 1 {
+                        if let Some(top) = ctx.conditions.last_mut() {
+                            *top = !*top;
+                        }
+                    }
+
+                    ctx.set_high_addresses(b);
+                }
+                CODE_END => break,
+                CODE_RETURN if flow((a >> 20) & 0xF) => {
+                    i = ctx.blocks[b as usize & 0xF].0;
+                    continue;
+                }
+                CODE_GOTO if flow((a >> 20) & 0xF) => {
+                    i = next.wrapping_add_signed(a as i16 as isize);
+                    continue;
+                }
+                CODE_GOSUB if flow((a >> 20) & 0xF) => {
+                    ctx.blocks[b as usize & 0xF].0 = next;
+                    i = next.wrapping_add_signed(a as i16 as isize);
+                    continue;
+                }
+                _ if !executing => (),
+                CODE_WRITE_8 => {
+                    for n in 0..=(b >> 16) {
+                        ctx.memory.write(addr.wrapping_add(n), b as u8);
+                    }
+                }
+                CODE_WRITE_16 => {
+                    for n in 0..=(b >> 16) {
+                        ctx.memory.write(addr.wrapping_add(n * 2), b as u16);
+                    }
+                }
+                CODE_WRITE_32 => ctx.memory.write(addr, b),
+                CODE_WRITE_STRING => {
+                    let data = self.words(i + 1, next);
+
+                    for n in 0..b {
+                        let byte = (data[n as usize / 4] >> (24 - (n % 4) * 8)) as u8;
+                        ctx.memory.write(addr.wrapping_add(n), byte);
+                    }
+                }
+                CODE_WRITE_SERIAL => {
+                    let Some(&(c, d)) = self.lines.get(i + 1) else {
+                        break;
+                    };
+
+                    let size = c >> 28;
+                    let count = (c >> 16) & 0xFFF;
+                    let addr_inc = c & 0xFFFF;
+
+                    let mut addr = addr;
+                    let mut value = b;
+
+                    for _ in 0..=count {
+                        match size {
+                            0 => ctx.memory.write(addr, value as u8),
+                            1 => ctx.memory.write(addr, value as u16),
+                            _ => ctx.memory.write(addr, value),
+                        }
+                        addr = addr.wrapping_add(addr_inc);
+                        value = value.wrapping_add(d);
+                    }
+                }
+                CODE_LOAD_BA | CODE_SET_BA | CODE_STORE_BA | CODE_LOAD_PO | CODE_SET_PO
+                | CODE_STORE_PO => ctx.address_code(codetype, a, b),
+                CODE_SET_REPEAT => ctx.blocks[b as usize & 0xF] = (next, a & 0xFFFF),
+                CODE_EXECUTE_REPEAT => {
+                    let block = &mut ctx.blocks[b as usize & 0xF];
+
+                    if block.1 != 0 {
+                        block.1 -= 1;
+                        i = block.0;
+                        continue;
+                    }
+                }
+                CODE_SET_REGISTER
+                | CODE_LOAD_REGISTER
+                | CODE_STORE_REGISTER
+                | CODE_REGISTER_OP_VALUE
+                | CODE_REGISTER_OP_REGISTER
+                | CODE_MEMORY_COPY_1
+                | CODE_MEMORY_COPY_2 => ctx.register_code(codetype, base, a, b),
+                CODE_INSERT_ASM => {
+                    let mut code = self.words(i + 1, next);
+                    let size = code.len() as u32 * 4;
+
+                    if code.is_empty() || hook_addr.wrapping_add(size) > HOOK_END {
+                        self.warning.warn(format_args!(
+                            "gecko code `{}` insert asm does not fit",
+                            self.name
+                        ));
+                    } else {
+                        // the last word of an insert asm code is a placeholder for the branch
+                        // back to the instruction following the hook
+                        let last = code.len() - 1;
+                        code[last] = branch(*hook_addr + last as u32 * 4, addr.wrapping_add(4));
+
+                        for (n, word) in code.iter().enumerate() {
+                            ctx.memory.write(*hook_addr + n as u32 * 4, *word);
+                        }
+
+                        ctx.memory.write(addr, branch(addr, *hook_addr));
+
+                        *hook_addr += size;
+                    }
+                }
+                CODE_BRANCH => ctx.memory.write(addr, branch(addr, b)),
+                CODE_EXECUTE_ASM => self.warning.warn(format_args!(
+                    "gecko code `{}`: execute asm is not supported",
+                    self.name
+                )),
+                _ => self.warning.warn(format_args!(
+                    "gecko code `{}`: unsupported codetype {:#04x}",
+                    self.name, codetype
+                )),
+            }
+
+            i = next;
+        }
+
+        if let Some(addr) = ctx.memory.invalid() {
+            self.warning.warn(format_args!(
+                "gecko code `{}` accessed invalid address {:#010x}",
+                self.name, addr
+            ));
+        }
+    }
+
+    /// Flatten code lines into words
+    fn words(&self, start: usize, end: usize) -> Vec<u32> {
+        self.lines[start.min(self.lines.len())..end.min(self.lines.len())]
+            .iter()
+            .flat_map(|&(a, b)| [a, b])
+            .collect()
+    }
+}
+
+/// Number of data lines that follow a code line
+fn extra_lines(a: u32, b: u32) -> usize {
+    match ((a >> 24) as u8) & 0xEE {
+        CODE_WRITE_STRING => (b as usize).div_ceil(8),
+        CODE_WRITE_SERIAL => 1,
+        CODE_EXECUTE_ASM | CODE_INSERT_ASM => b as usize,
+        _ => 0,
+    }
+}
+
+fn branch(from: u32, to: u32) -> u32 {
+    0x4800_0000 | (to.wrapping_sub(from) & 0x03FF_FFFC)
+}
+
+struct Context<'a> {
+    memory: CodeMemory<'a>,
+    registers: &'a mut [u32; 16],
+    /// Base address
+    ba: u32,
+    /// Pointer address
+    po: u32,
+    /// Results of nested conditionals, codes execute while all are true
+    conditions: Vec<bool>,
+    /// Repeat/gosub blocks: (line, repeat count)
+    blocks: [(usize, u32); 16],
+    name: &'a str,
+    warning: &'a WarnOnce,
+}
+
+impl Context<'_> {
+    fn condition(&mut self, codetype: u8, addr: u32, b: u32) -> bool {
+        match codetype {
+            CODE_IF_EQ_32 | CODE_IF_NE_32 | CODE_IF_GT_32 | CODE_IF_LT_32 => {
+                let Some(value) = self.memory.read::<u32>(addr) else {
+                    return false;
+                };
+
+                match codetype {
+                    CODE_IF_EQ_32 => value == b,
+                    CODE_IF_NE_32 => value != b,
+                    CODE_IF_GT_32 => value > b,
+                    _ => value < b,
+                }
+            }
+            _ => {
+                let Some(value) = self.memory.read::<u16>(addr) else {
+                    return false;
+                };
+
+                let value = value & !(b >> 16) as u16;
+                let b = b as u16;
+
+                match codetype {
+                    CODE_IF_EQ_16 => value == b,
+                    CODE_IF_NE_16 => value != b,
+                    CODE_IF_GT_16 => value > b,
+                    _ => value < b,
+                }
+            }
+        }
+    }
+
+    fn set_high_addresses(&mut self, b: u32) {
+        if b & 0xFFFF_0000 != 0 {
+            self.ba = b & 0xFFFF_0000;
+        }
+
+        if b & 0xFFFF != 0 {
+            self.po = b << 16;
+        }
+    }
+
+    /// `40TYZ00N XXXXXXXX` base address and `48TYZ00N XXXXXXXX` pointer codes
+    fn address_code(&mut self, codetype: u8, a: u32, b: u32) {
+        let (t, y, z, n) = ((a >> 20) & 0xF, (a >> 16) & 0xF, (a >> 12) & 0xF, a & 0xF);
+
+        let mut addr = b;
+
+        match y {
+            1 => addr = addr.wrapping_add(self.ba),
+            2 => addr = addr.wrapping_add(self.po),
+            _ => (),
+        }
+
+        if z == 1 {
+            addr = addr.wrapping_add(self.registers[n as usize]);
+        }
+
+        let target = if codetype < CODE_LOAD_PO {
+            &mut self.ba
+        } else {
+            &mut self.po
+        };
+
+        let value = match codetype {
+            CODE_LOAD_BA | CODE_LOAD_PO => {
+                let Some(value) = self.memory.read::<u32>(addr) else {
+                    return;
+                };
+                value
+            }
+            CODE_SET_BA | CODE_SET_PO => addr,
+            _ => {
+                let value = *target;
+                self.memory.write(addr, value);
+                return;
+            }
+        };
+
+        let target = if codetype < CODE_LOAD_PO {
+            &mut self.ba
+        } else {
+            &mut self.po
+        };
+
+        *target = if t == 1 {
+            target.wrapping_add(value)
+        } else {
+            value
+        };
+    }
+
+    /// Gecko register codes, `base` is the base or pointer address selected by the codetype
+    fn register_code(&mut self, codetype: u8, base: u32, a: u32, b: u32) {
+        let (t, y, n) = ((a >> 20) & 0xF, (a >> 16) & 0xF, (a & 0xF) as usize);
+
+        let offset = |y: u32| if y == 1 { base } else { 0 };
+
+        match codetype {
+            // 80SY000N XXXXXXXX
+            CODE_SET_REGISTER => {
+                let value = b.wrapping_add(offset(y));
+
+                self.registers[n] = if t == 1 {
+                    self.registers[n].wrapping_add(value)
+                } else {
+                    value
+                };
+            }
+            // 82UY000N XXXXXXXX
+            CODE_LOAD_REGISTER => {
+                let addr = b.wrapping_add(offset(y));
+
+                let value = match t {
+                    0 => self.memory.read::<u8>(addr).map(u32::from),
+                    1 => self.memory.read::<u16>(addr).map(u32::from),
+                    _ => self.memory.read::<u32>(addr),
+                };
+
+                if let Some(value) = value {
+                    self.registers[n] = value;
+                }
+            }
+            // 84UYZZZN XXXXXXXX
+            CODE_STORE_REGISTER => {
+                let y = (a >> 16) & 0xF;
+                let count = (a >> 4) & 0xFFF;
+                let addr = b.wrapping_add(offset(y));
+                let value = self.registers[n];
+
+                for i in 0..=count {
+                    match t {
+                        0 => self.memory.write(addr.wrapping_add(i), value as u8),
+                        1 => self.memory.write(addr.wrapping_add(i * 2), value as u16),
+                        _ => self.memory.write(addr.wrapping_add(i * 4), value),
+                    }
+                }
+            }
+            // 86TY000N XXXXXXXX and 88TY000N 0000000M
+            CODE_REGISTER_OP_VALUE | CODE_REGISTER_OP_REGISTER => {
+                let lhs = self.registers[n];
+                let rhs = if codetype == CODE_REGISTER_OP_VALUE {
+                    b
+                } else {
+                    self.registers[b as usize & 0xF]
+                };
+
+                let lhs = if y & 1 != 0 {
+                    self.memory.read::<u32>(lhs).unwrap_or(0)
+                } else {
+                    lhs
+                };
+                let rhs = if y & 2 != 0 {
+                    self.memory.read::<u32>(rhs).unwrap_or(0)
+                } else {
+                    rhs
+                };
+
+                self.registers[n] = match t {
+                    0 => lhs.wrapping_add(rhs),
+                    1 => lhs.wrapping_mul(rhs),
+                    2 => lhs | rhs,
+                    3 => lhs & rhs,
+                    4 => lhs ^ rhs,
+                    5 => lhs.wrapping_shl(rhs),
+                    6 => lhs.wrapping_shr(rhs),
+                    7 => lhs.rotate_left(rhs),
+                    8 => (lhs as i32).wrapping_shr(rhs) as u32,
+                    9 => (f32::from_bits(lhs) + f32::from_bits(rhs)).to_bits(),
+                    0xA => (f32::from_bits(lhs) * f32::from_bits(rhs)).to_bits(),
+                    _ => {
+                        self.warning.warn(format_args!(
+                            "gecko code `{}`: register operation {:#x} is not supported",
+                            self.name, t
+                        ));
+                        lhs
+                    }
+                };
+            }
+            // 8AYYYYNM XXXXXXXX and 8CYYYYNM XXXXXXXX, register 0xF selects the base address
+            _ => {
+                let size = (a >> 8) & 0xFFFF;
+                let (src, dst) = ((a >> 4) & 0xF, a & 0xF);
+
+                let register = |r: u32| {
+                    if r == 0xF {
+                        base
+                    } else {
+                        self.registers[r as usize]
+                    }
+                };
+
+                let (src, dst) = if codetype == CODE_MEMORY_COPY_1 {
+                    (register(src), register(dst).wrapping_add(b))
+                } else {
+                    (register(src).wrapping_add(b), register(dst))
+                };
+
+                for i in 0..size {
+                    if let Some(byte) = self.memory.read::<u8>(src.wrapping_add(i)) {
+                        self.memory.write(dst.wrapping_add(i), byte);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(lines: &[(u32, u32)], cpu: &mut Cpu, bus: &mut Bus) -> [u32; 16] {
+        let code = GeckoCode {
+            name: String::new(),
+            lines: lines.to_vec(),
+            warning: WarnOnce::default(),
+        };
+
+        let mut registers = [0; 16];
+        let mut hook_addr = HOOK_BASE;
+
+        code.run(&mut registers, &mut hook_addr, cpu, bus);
+
+        registers
+    }
+
+    #[test]
+    fn writes() {
+        let mut cpu = Cpu::default();
+        let mut bus = Bus::default();
+        cpu.emulate_bs2(&mut bus);
+
+        run(
+            &[
+                (0x0010_0000, 0x0003_00AB),
+                (0x0210_0010, 0x0001_1234),
+                (0x0510_0020, 0xDEAD_BEEF),
+                (0x0610_0030, 0x0000_0005),
+                (0x4865_6C6C, 0x6F00_0000),
+                (0x0810_0040, 0x0000_0001),
+                (0x2002_0004, 0x0000_0001),
+            ],
+            &mut cpu,
+            &mut bus,
+        );
+
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0000), Some(0xABAB_ABAB));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0010), Some(0x1234_1234));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8110_0020), Some(0xDEAD_BEEF));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0030), Some(0x4865_6C6C));
+        assert_eq!(cpu.read::<u8>(&mut bus, 0x8010_0034), Some(0x6F));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0040), Some(1));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0044), Some(2));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0048), Some(3));
+    }
+
+    #[test]
+    fn conditionals() {
+        let mut cpu = Cpu::default();
+        let mut bus = Bus::default();
+        cpu.emulate_bs2(&mut bus);
+
+        cpu.write::<u32>(&mut bus, 0x8010_0000, 5);
+
+        run(
+            &[
+                (0x2010_0000, 0x0000_0005), // if [0x80100000] == 5
+                (0x0410_0010, 0x0000_0001),
+                (0x2010_0001, 0x0000_0006), // endif, if [0x80100000] == 6
+                (0x0410_0014, 0x0000_0001),
+                (0xE210_0001, 0x0000_0000), // else
+                (0x0410_0018, 0x0000_0001),
+                (0xE000_0000, 0x8000_8000),
+                (0x0410_001C, 0x0000_0001),
+            ],
+            &mut cpu,
+            &mut bus,
+        );
+
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0010), Some(1));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0014), Some(0));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0018), Some(1));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_001C), Some(1));
+    }
+
+    #[test]
+    fn pointers_and_registers() {
+        let mut cpu = Cpu::default();
+        let mut bus = Bus::default();
+        cpu.emulate_bs2(&mut bus);
+
+        cpu.write::<u32>(&mut bus, 0x8010_0000, 0x8020_0000);
+        cpu.write::<u32>(&mut bus, 0x8020_0008, 40);
+
+        let registers = run(
+            &[
+                (0x4800_0000, 0x8010_0000), // po = [0x80100000]
+                (0x1400_0004, 0x0000_0063), // [po + 4] = 99
+                (0x8220_0003, 0x8020_0008), // gr3 = [0x80200008]
+                (0x8600_0003, 0x0000_0002), // gr3 += 2
+                (0x8420_0003, 0x8020_000C), // [0x8020000C] = gr3
+            ],
+            &mut cpu,
+            &mut bus,
+        );
+
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8020_0004), Some(99));
+        assert_eq!(registers[3], 42);
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8020_000C), Some(42));
+    }
+
+    #[test]
+    fn insert_asm() {
+        let mut cpu = Cpu::default();
+        let mut bus = Bus::default();
+        cpu.emulate_bs2(&mut bus);
+
+        run(
+            &[
+                (0xC210_0000, 0x0000_0001),
+                (0x3860_0001, 0x0000_0000), // li r3,1
+            ],
+            &mut cpu,
+            &mut bus,
+        );
+
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8010_0000), Some(0x4BF0_1800));
+        assert_eq!(cpu.read::<u32>(&mut bus, HOOK_BASE), Some(0x3860_0001));
+        assert_eq!(cpu.read::<u32>(&mut bus, HOOK_BASE + 4), Some(0x480F_E800));
+    }
+}
@@ -0,0 +1,3 @@
+//! Subcommands of the `rustcube` binary
+pub mod disc;
+pub mod memcard;
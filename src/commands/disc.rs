@@ -0,0 +1,221 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use getopts::Options;
+use rustcube::disc::{Disc, FstEntry, APPLOADER_OFFSET, BI2_OFFSET, BI2_SIZE, HEADER_SIZE};
+
+use crate::DynResult;
+
+const USAGE: &str = "Usage: {program} disc COMMAND [options] DISC [PATH]
+
+Commands:
+    info DISC           print the disc header, apploader and bi2 details
+    ls DISC [PATH]      list the files on a disc, all files if no path is given
+    extract DISC [PATH] extract a file or directory, the whole disc if no path
+                        is given (system files in sys/, the filesystem in files/)
+    dump-dol DISC       write the main DOL";
+
+fn print_usage(program: &str, opts: &Options) {
+    print!("{}", opts.usage(&USAGE.replace("{program}", program)));
+}
+
+pub fn run(program: &str, args: &[String]) -> DynResult<()> {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "print this help menu");
+    opts.optopt(
+        "o",
+        "output",
+        "directory to extract to, or file for dump-dol",
+        "PATH",
+    );
+
+    let matches = opts.parse(args)?;
+
+    let (command, disc_path, path) = match matches.free.as_slice() {
+        [command, disc] if !matches.opt_present("h") => (command, disc, None),
+        [command, disc, path] if !matches.opt_present("h") => (command, disc, Some(path)),
+        _ => {
+            print_usage(program, &opts);
+            return Ok(());
+        }
+    };
+
+    let mut disc = Disc::open(disc_path).map_err(|e| format!("{disc_path}: {e}"))?;
+
+    match command.as_str() {
+        "info" => info(&disc),
+        "ls" => {
+            let path = path.map(String::as_str).unwrap_or("/");
+            list(disc.list(path)?, &normalize(path));
+        }
+        "extract" => {
+            let output = PathBuf::from(matches.opt_str("o").unwrap_or_else(|| ".".to_string()));
+
+            match path.map(|path| normalize(path)) {
+                Some(path) if !path.is_empty() => extract_path(&mut disc, &path, &output)?,
+                Some(_) => {
+                    let entries = disc.fst()?.root().to_vec();
+                    extract_entries(&mut disc, &entries, "", &output)?;
+                }
+                None => {
+                    extract_system(&mut disc, &output.join("sys"))?;
+                    let entries = disc.fst()?.root().to_vec();
+                    extract_entries(&mut disc, &entries, "", &output.join("files"))?;
+                }
+            }
+        }
+        "dump-dol" => {
+            let output = matches
+                .opt_str("o")
+                .unwrap_or_else(|| "main.dol".to_string());
+            let dol = disc.read_dol()?;
+
+            fs::write(&output, &dol)?;
+
+            println!("Wrote {:#x} byte DOL to {output}", dol.len());
+        }
+        _ => {
+            print_usage(program, &opts);
+        }
+    }
+
+    Ok(())
+}
+
+fn info(disc: &Disc) {
+    let header = disc.header();
+    let apploader = disc.apploader();
+    let bi2 = disc.bi2();
+
+    println!("Game ID:            {}", disc.game_id());
+    println!("Name:               {}", header.game_name);
+    println!("Disc number:        {}", header.disc_number);
+    println!("Version:            {}", header.version);
+    println!("Audio streaming:    {}", header.audio_streaming);
+    println!("DOL offset:         {:#010x}", header.dol_offset);
+    println!("FST offset:         {:#010x}", header.fst_offset);
+    println!("FST size:           {:#x}", header.fst_size);
+    println!("FST max size:       {:#x}", header.fst_max_size);
+    println!("Debug monitor:      {:#010x}", header.debug_monitor_offset);
+    println!("Debug load address: {:#010x}", header.debug_monitor_address);
+    println!();
+    println!("Apploader date:     {}", apploader.date);
+    println!("Apploader entry:    {:#010x}", apploader.entry_point);
+    println!("Apploader size:     {:#x}", apploader.size);
+    println!("Trailer size:       {:#x}", apploader.trailer_size);
+    println!();
+    println!(
+        "Country code:       {}{}",
+        bi2.country_code,
+        bi2.region()
+            .map(|region| format!(" ({region:?})"))
+            .unwrap_or_default()
+    );
+    println!("Debug monitor size: {:#x}", bi2.debug_monitor_size);
+    println!("Simulated memory:   {:#x}", bi2.simulated_memory_size);
+    println!("Argument offset:    {:#x}", bi2.argument_offset);
+    println!("Debug flag:         {}", bi2.debug_flag);
+    println!("Track location:     {:#x}", bi2.track_location);
+    println!("Track size:         {:#x}", bi2.track_size);
+}
+
+/// Print the files under a directory with their offsets and sizes
+fn list(entries: &[FstEntry], dir: &str) {
+    for entry in entries {
+        let path = format!("{dir}/{}", entry.name());
+
+        match entry {
+            FstEntry::File { offset, size, .. } => {
+                println!("{offset:#010x}  {size:>10}  {path}");
+            }
+            FstEntry::Directory { entries, .. } => {
+                println!("{:10}  {:>10}  {path}/", "", "");
+                list(entries, &path);
+            }
+        }
+    }
+}
+
+/// Path with a leading `/` and no trailing `/`, empty for the root directory
+fn normalize(path: &str) -> String {
+    match path.trim_matches('/') {
+        "" => String::new(),
+        path => format!("/{path}"),
+    }
+}
+
+/// Extract the file or directory at `path` into `output`
+fn extract_path(disc: &mut Disc, path: &str, output: &Path) -> DynResult<()> {
+    let entry = disc
+        .fst()?
+        .find(path)
+        .cloned()
+        .ok_or_else(|| format!("{path}: no such file or directory"))?;
+
+    let dir = &path[..path.rfind('/').unwrap_or(0)];
+
+    extract_entries(disc, &[entry], dir, output)
+}
+
+fn extract_entries(
+    disc: &mut Disc,
+    entries: &[FstEntry],
+    dir: &str,
+    output: &Path,
+) -> DynResult<()> {
+    fs::create_dir_all(output)?;
+
+    for entry in entries {
+        let name = entry.name();
+        // names come from the disc, keep them inside the output directory
+        if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+            return Err(format!("{dir}: invalid file name `{name}`").into());
+        }
+
+        let path = format!("{dir}/{name}");
+
+        match entry {
+            FstEntry::File { .. } => {
+                let mut file = disc.open_file(&path)?;
+                let mut out = fs::File::create(output.join(name))?;
+                io::copy(&mut file, &mut out)?;
+
+                println!("Extracted {path}");
+            }
+            FstEntry::Directory { entries, .. } => {
+                extract_entries(disc, entries, &path, &output.join(name))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the system files: disc header, bi2, apploader, main DOL and FST
+fn extract_system(disc: &mut Disc, output: &Path) -> DynResult<()> {
+    fs::create_dir_all(output)?;
+
+    let header = disc.header();
+    let (fst_offset, fst_size) = (header.fst_offset as u64, header.fst_size as usize);
+    let apploader_size = disc.apploader().image_size();
+
+    let files = [
+        ("boot.bin", 0, HEADER_SIZE),
+        ("bi2.bin", BI2_OFFSET, BI2_SIZE),
+        ("apploader.img", APPLOADER_OFFSET, apploader_size),
+        ("fst.bin", fst_offset, fst_size),
+    ];
+
+    for (name, offset, size) in files {
+        fs::write(output.join(name), disc.read_vec(offset, size)?)?;
+
+        println!("Extracted sys/{name}");
+    }
+
+    fs::write(output.join("main.dol"), disc.read_dol()?)?;
+    println!("Extracted sys/main.dol");
+
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use std::{fs, path::Path};
+
+use getopts::Options;
+use rustcube::{
+    config::Language,
+    memcard::{self, Card, Gci, CARD_SIZES, DEFAULT_FLASH_IDS},
+    Sram,
+};
+
+use crate::DynResult;
+
+const USAGE: &str = "Usage: {program} memcard COMMAND [options] CARD [FILE...]
+
+Commands:
+    create CARD         create a formatted card image
+    format CARD         erase all saves on a card image
+    list CARD           list the saves on a card image
+    import CARD GCI...  copy .gci saves onto a card image
+    export CARD [SAVE...]
+                        write saves as .gci files, all saves if none are given
+                        (by directory index or file name)
+    verify CARD         check directory and block allocation map
+    repair CARD         rebuild directory and block allocation map";
+
+fn print_usage(program: &str, opts: &Options) {
+    print!("{}", opts.usage(&USAGE.replace("{program}", program)));
+}
+
+pub fn run(program: &str, args: &[String]) -> DynResult<()> {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "print this help menu");
+    opts.optopt(
+        "s",
+        "size",
+        "blocks of created cards: 59, 123, 251, 507, 1019 or 2043",
+        "BLOCKS",
+    );
+    opts.optopt("", "slot", "slot the card is formatted for: a or b", "SLOT");
+    opts.optopt(
+        "",
+        "sram",
+        "SRAM of the console cards are formatted for, as used by the emulator's --sram",
+        "FILE",
+    );
+    opts.optopt("o", "output", "directory for exported saves", "DIR");
+
+    let matches = opts.parse(args)?;
+
+    let (command, card_path, files) = match matches.free.as_slice() {
+        [command, card, files @ ..] if !matches.opt_present("h") => (command, card, files),
+        _ => {
+            print_usage(program, &opts);
+            return Ok(());
+        }
+    };
+
+    let slot = match matches.opt_str("slot").as_deref() {
+        None | Some("a") | Some("A") => 0,
+        Some("b") | Some("B") => 1,
+        Some(slot) => return Err(format!("--slot: invalid slot `{slot}`").into()),
+    };
+
+    let sram = match matches.opt_str("sram") {
+        Some(path) => Some(Sram::load(&path).map_err(|e| format!("--sram: {path}: {e}"))?),
+        None => None,
+    };
+
+    match command.as_str() {
+        "create" => {
+            let blocks = match matches.opt_str("s") {
+                Some(size) => size
+                    .parse()
+                    .ok()
+                    .filter(|blocks| CARD_SIZES.contains(blocks))
+                    .ok_or_else(|| format!("--size: invalid card size `{size}`"))?,
+                None => CARD_SIZES[0],
+            };
+
+            if Path::new(card_path).exists() {
+                return Err(format!("{card_path} already exists").into());
+            }
+
+            format(card_path, blocks, slot, sram.as_ref())?;
+        }
+        "format" => {
+            let blocks = Card::open(card_path)?.blocks();
+            format(card_path, blocks, slot, sram.as_ref())?;
+        }
+        "list" => {
+            let card = Card::open(card_path)?;
+
+            println!("{:>5}  {:<6}  {:>6}  File name", "Index", "Game", "Blocks");
+
+            for (index, entry) in card.entries() {
+                println!(
+                    "{:>5}  {}{}  {:>6}  {}",
+                    index,
+                    entry.game_code(),
+                    entry.maker_code(),
+                    entry.block_count(),
+                    entry.file_name()
+                );
+            }
+
+            println!("{} of {} blocks free", card.free_blocks(), card.blocks());
+        }
+        "import" => {
+            let mut card = Card::open(card_path)?;
+
+            for path in files {
+                let gci = Gci::from_bytes(&fs::read(path)?).map_err(|e| format!("{path}: {e}"))?;
+                card.import(&gci).map_err(|e| format!("{path}: {e}"))?;
+
+                println!("Imported {}", gci.entry);
+            }
+
+            card.save(card_path)?;
+        }
+        "export" => {
+            let card = Card::open(card_path)?;
+            let output = matches.opt_str("o").unwrap_or_else(|| ".".to_string());
+
+            let saves: Vec<_> = card
+                .entries()
+                .into_iter()
+                .filter(|(index, entry)| {
+                    files.is_empty()
+                        || files
+                            .iter()
+                            .any(|save| *save == index.to_string() || *save == entry.file_name())
+                })
+                .collect();
+
+            if saves.is_empty() {
+                return Err("no matching saves".into());
+            }
+
+            for (index, entry) in saves {
+                let path = Path::new(&output).join(entry.gci_file_name());
+
+                fs::write(&path, card.export(index)?.to_bytes())?;
+
+                println!("Exported {} to {}", entry, path.display());
+            }
+        }
+        "verify" => {
+            let problems = Card::open(card_path)?.verify();
+
+            for problem in problems.iter() {
+                println!("{problem}");
+            }
+
+            if !problems.is_empty() {
+                return Err(format!("{card_path}: {} problems found", problems.len()).into());
+            }
+
+            println!("{card_path}: ok");
+        }
+        "repair" => {
+            let mut card = Card::open(card_path)?;
+            let repairs = card.repair();
+
+            for repair in repairs.iter() {
+                println!("{repair}");
+            }
+
+            if repairs.is_empty() {
+                println!("{card_path}: ok");
+            } else {
+                card.save(card_path)?;
+                println!("{card_path}: repaired");
+            }
+        }
+        _ => {
+            print_usage(program, &opts);
+        }
+    }
+
+    Ok(())
+}
+
+/// Format with the flash id and language of the console's SRAM, as the IPL does, or the
+/// emulator's defaults without one
+fn format(path: &str, blocks: u16, slot: usize, sram: Option<&Sram>) -> DynResult<()> {
+    let (flash_id, language) = match sram {
+        Some(sram) => (sram.flash_id(slot), sram.language()),
+        None => (*DEFAULT_FLASH_IDS[slot], Language::English),
+    };
+
+    let card = Card::format(blocks, &flash_id, memcard::format_time_now(), language);
+
+    card.save(path)?;
+
+    println!("Formatted {blocks} block card {path}");
+
+    Ok(())
+}
@@ -0,0 +1,884 @@
+use crate::{
+    hw::exi::USB_GECKO_PORT,
+    input::{key_from_name, KeyMap, PadInput},
+    memcard::CARD_SIZES,
+};
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{Error, ErrorKind},
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CpuTiming {
+    /// Per instruction cycle counts
+    #[default]
+    Accurate,
+    /// Double the cycles counted per instruction, devices and timers advance faster relative to
+    /// the code being executed
+    Fast,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DspMode {
+    /// Run the DSP ROM/ucode on the emulated DSP core
+    #[default]
+    Lle,
+    /// Never step the DSP core
+    Off,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Region {
+    NtscJ,
+    NtscU,
+    Pal,
+}
+
+impl Region {
+    /// Region from the last character of a game code, e.g. `GALE`
+    pub fn from_game_code(game_code: u32) -> Option<Region> {
+        match game_code as u8 {
+            b'J' | b'K' | b'W' => Some(Region::NtscJ),
+            b'E' => Some(Region::NtscU),
+            b'P' | b'D' | b'F' | b'H' | b'I' | b'S' | b'U' | b'X' | b'Y' => Some(Region::Pal),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+    Italian,
+    Dutch,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SoundMode {
+    Mono,
+    Stereo,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VideoMode {
+    Ntsc,
+    Pal,
+    Mpal,
+}
+
+/// Device in memory card slot A or B
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SlotDevice {
+    None,
+    /// Card image or `.gci` folder from the `[MemoryCard]` settings
+    #[default]
+    MemoryCard,
+    /// USB Gecko bridged to a TCP port on localhost
+    UsbGecko,
+    /// SD Gecko with the disk image from the `[SDCard]` settings
+    SdGecko,
+}
+
+/// Device in the serial port 1 (EXI channel 0, device 2)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SerialPortDevice {
+    #[default]
+    None,
+    /// Broadband Adapter using the `[BBA]` settings
+    Bba,
+}
+
+/// Device in one of the controller ports
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PortDevice {
+    #[default]
+    None,
+    /// Standard controller
+    Controller,
+    /// Game Boy Advance link to an emulator connected over TCP
+    Gba,
+    /// ASCII keyboard typed on with the host keyboard
+    Keyboard,
+    SteeringWheel,
+    /// DK Bongos
+    Bongos,
+}
+
+/// Where the Broadband Adapter sends and receives frames
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BbaBackend {
+    /// UDP tunnel to a peer, e.g. another instance on the same host
+    #[default]
+    Udp,
+    /// Linux TAP interface
+    Tap,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BbaSettings {
+    pub backend: BbaBackend,
+    pub mac: [u8; 6],
+    pub tap_device: String,
+    pub local_addr: SocketAddr,
+    pub peer_addr: SocketAddr,
+}
+
+impl Default for BbaSettings {
+    fn default() -> Self {
+        BbaSettings {
+            backend: Default::default(),
+            mac: [0x00, 0x09, 0xBF, 0x00, 0x00, 0x01],
+            tap_device: String::from("tap0"),
+            local_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 55030)),
+            peer_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 55031)),
+        }
+    }
+}
+
+/// SRAM settings written at startup, unset settings keep their SRAM value
+#[derive(Clone, Debug, Default)]
+pub struct SramSettings {
+    pub display_offset: Option<i8>,
+    pub sound_mode: Option<SoundMode>,
+    pub video_mode: Option<VideoMode>,
+    pub progressive_scan: Option<bool>,
+    pub pal60: Option<bool>,
+    pub wireless_pad_ids: [Option<u16>; 4],
+    pub last_dvd_error: Option<u8>,
+}
+
+/// What to do once the requested number of frames has run
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ExitBehavior {
+    /// Stop emulation and return
+    #[default]
+    Quit,
+    /// Stop emulation but keep presenting the last frame until the window is closed
+    Wait,
+}
+
+/// Emulator settings, read from ini style files. Later files override values of earlier ones,
+/// per-game files are applied over the global config.
+///
+/// ```ini
+/// [Core]
+/// Ipl = ipl.bin
+/// CpuTiming = accurate | fast
+/// DspMode = lle | off
+/// Region = ntsc-j | ntsc-u | pal
+/// Language = english | german | french | spanish | italian | dutch
+/// Sram = sram.raw
+/// RtcTime = 946684800
+/// Headless = false
+/// FrameLimit = 60
+/// Frames = 0
+/// OnExit = quit | wait
+/// [DSP]
+/// Rom = dsp_rom.bin
+/// Coef = dsp_coef.bin
+/// [Log]
+/// Filter = info,rustcube::dsp=warn
+/// [MemoryCard]
+/// SlotA = card_a.raw | gci_folder/
+/// SlotB = card_b.raw
+/// SizeA = 59 | 123 | 251 | 507 | 1019 | 2043
+/// SizeB = 59
+/// [EXI]
+/// SlotA = memcard | usbgecko | sdgecko | none
+/// SlotB = memcard
+/// UsbGeckoPort = 55020
+/// SerialPort1 = none | bba
+/// [BBA]
+/// Backend = udp | tap
+/// MacAddress = 00:09:bf:00:00:01
+/// TapDevice = tap0
+/// LocalAddr = 127.0.0.1:55030
+/// PeerAddr = 127.0.0.1:55031
+/// [SI]
+/// Port1 = controller | gba | keyboard | wheel | bongos | none
+/// Port2 = none
+/// [SDCard]
+/// SlotA = sd.img
+/// SlotB = sd.img
+/// [SRAM]
+/// DisplayOffset = 0
+/// SoundMode = mono | stereo
+/// VideoMode = ntsc | pal | mpal
+/// ProgressiveScan = false
+/// Pal60 = false
+/// WirelessPad1 = 0
+/// LastDvdError = 0
+/// [Pad1]
+/// A = X
+/// StickUp = Up
+/// [Hacks]
+/// SomeHack = true
+/// ```
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// IPL image, booted when no game is given
+    pub ipl: Option<PathBuf>,
+    pub cpu_timing: CpuTiming,
+    pub dsp_mode: DspMode,
+    /// Region override, detected from the disc when not set
+    pub region: Option<Region>,
+    /// Language override, the SRAM setting is kept when not set
+    pub language: Option<Language>,
+    /// File SRAM is loaded from and saved to, SRAM is reset on each run when not set
+    pub sram: Option<PathBuf>,
+    /// Fixed RTC start time in seconds since the unix epoch, the host clock when not set
+    pub rtc_time: Option<u64>,
+    /// Don't open a window
+    pub headless: bool,
+    /// Frames per second, unlimited when 0
+    pub frame_limit: u32,
+    /// Number of frames to run, unlimited when 0
+    pub frames: u64,
+    pub on_exit: ExitBehavior,
+    pub dsp_rom: PathBuf,
+    pub dsp_coef: PathBuf,
+    /// `env_logger` filter, e.g. `info,rustcube::dsp=warn`
+    pub log_filter: Option<String>,
+    pub slot_a: SlotDevice,
+    pub slot_b: SlotDevice,
+    /// TCP port of a USB Gecko in slot A, slot B uses the next port
+    pub usb_gecko_port: u16,
+    pub serial_port_1: SerialPortDevice,
+    pub bba: BbaSettings,
+    pub memcard_a: Option<PathBuf>,
+    pub memcard_b: Option<PathBuf>,
+    /// Size in blocks of newly created memory cards
+    pub memcard_a_size: u16,
+    pub memcard_b_size: u16,
+    /// Disk images of SD Geckos in slot A and B
+    pub sd_card_a: Option<PathBuf>,
+    pub sd_card_b: Option<PathBuf>,
+    /// Devices in the controller ports 1-4
+    pub ports: [PortDevice; 4],
+    pub sram_settings: SramSettings,
+    /// Keys of the controllers in ports 1-4, read from the global config only
+    pub key_maps: [KeyMap; 4],
+    /// Names of the enabled `[Hacks]`, usually set per game
+    pub hacks: BTreeSet<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            ipl: None,
+            cpu_timing: Default::default(),
+            dsp_mode: Default::default(),
+            region: None,
+            language: None,
+            sram: None,
+            rtc_time: None,
+            headless: false,
+            frame_limit: 60,
+            frames: 0,
+            on_exit: Default::default(),
+            dsp_rom: PathBuf::from("dsp_rom.bin"),
+            dsp_coef: PathBuf::from("dsp_coef.bin"),
+            log_filter: None,
+            slot_a: Default::default(),
+            slot_b: Default::default(),
+            usb_gecko_port: USB_GECKO_PORT,
+            serial_port_1: Default::default(),
+            bba: Default::default(),
+            memcard_a: None,
+            memcard_b: None,
+            memcard_a_size: 59,
+            memcard_b_size: 59,
+            sd_card_a: None,
+            sd_card_b: None,
+            ports: [
+                PortDevice::Controller,
+                PortDevice::None,
+                PortDevice::None,
+                PortDevice::None,
+            ],
+            sram_settings: Default::default(),
+            key_maps: [
+                KeyMap::default_port_1(),
+                KeyMap::default(),
+                KeyMap::default(),
+                KeyMap::default(),
+            ],
+            hacks: BTreeSet::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, Error> {
+        let mut config = Config::default();
+        config.merge_file(path)?;
+        Ok(config)
+    }
+
+    pub fn merge_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let text = fs::read_to_string(path.as_ref())?;
+
+        self.merge_text(&text).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: {e}", path.as_ref().display()),
+            )
+        })
+    }
+
+    /// Settings for a game: this config with `<game code>.ini` and `<game id>.ini` from `dir`
+    /// merged over it
+    pub fn for_game<P: AsRef<Path>>(&self, dir: P, game_id: &str) -> Result<Config, Error> {
+        let mut config = self.clone();
+
+        for path in game_files(dir, game_id) {
+            info!("Loading game settings {}", path.display());
+            config.merge_file(path)?;
+        }
+
+        Ok(config)
+    }
+
+    pub fn merge_text(&mut self, text: &str) -> Result<(), String> {
+        let mut section = String::new();
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len() - 1].to_string();
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                // patch and cheat sections share per-game files
+                continue;
+            };
+
+            let (key, value) = (key.trim(), value.trim());
+
+            self.set(&section, key, value)
+                .map_err(|e| format!("line {}: {e}", n + 1))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn hack(&self, name: &str) -> bool {
+        self.hacks.contains(name)
+    }
+
+    /// Settings that affect emulation results as `(section, key, value)`, stored in movies so
+    /// playback runs with the settings the movie was recorded with
+    pub fn movie_settings(&self) -> Vec<(String, String, String)> {
+        let slot = |device| match device {
+            SlotDevice::None => "none",
+            SlotDevice::MemoryCard => "memcard",
+            SlotDevice::UsbGecko => "usbgecko",
+            SlotDevice::SdGecko => "sdgecko",
+        };
+        let port = |device| match device {
+            PortDevice::None => "none",
+            PortDevice::Controller => "controller",
+            PortDevice::Gba => "gba",
+            PortDevice::Keyboard => "keyboard",
+            PortDevice::SteeringWheel => "wheel",
+            PortDevice::Bongos => "bongos",
+        };
+
+        let settings = [
+            (
+                "Core",
+                "CpuTiming",
+                match self.cpu_timing {
+                    CpuTiming::Accurate => "accurate",
+                    CpuTiming::Fast => "fast",
+                }
+                .to_string(),
+            ),
+            (
+                "Core",
+                "DspMode",
+                match self.dsp_mode {
+                    DspMode::Lle => "lle",
+                    DspMode::Off => "off",
+                }
+                .to_string(),
+            ),
+            (
+                "Core",
+                "Region",
+                match self.region {
+                    None => "auto",
+                    Some(Region::NtscJ) => "ntsc-j",
+                    Some(Region::NtscU) => "ntsc-u",
+                    Some(Region::Pal) => "pal",
+                }
+                .to_string(),
+            ),
+            (
+                "Core",
+                "Language",
+                match self.language {
+                    None => "sram",
+                    Some(Language::English) => "english",
+                    Some(Language::German) => "german",
+                    Some(Language::French) => "french",
+                    Some(Language::Spanish) => "spanish",
+                    Some(Language::Italian) => "italian",
+                    Some(Language::Dutch) => "dutch",
+                }
+                .to_string(),
+            ),
+            (
+                "Core",
+                "RtcTime",
+                self.rtc_time
+                    .map_or_else(|| "host".to_string(), |time| time.to_string()),
+            ),
+            (
+                "SRAM",
+                "DisplayOffset",
+                optional_string(self.sram_settings.display_offset),
+            ),
+            (
+                "SRAM",
+                "SoundMode",
+                match self.sram_settings.sound_mode {
+                    None => "",
+                    Some(SoundMode::Mono) => "mono",
+                    Some(SoundMode::Stereo) => "stereo",
+                }
+                .to_string(),
+            ),
+            (
+                "SRAM",
+                "VideoMode",
+                match self.sram_settings.video_mode {
+                    None => "",
+                    Some(VideoMode::Ntsc) => "ntsc",
+                    Some(VideoMode::Pal) => "pal",
+                    Some(VideoMode::Mpal) => "mpal",
+                }
+                .to_string(),
+            ),
+            (
+                "SRAM",
+                "ProgressiveScan",
+                optional_string(self.sram_settings.progressive_scan),
+            ),
+            ("SRAM", "Pal60", optional_string(self.sram_settings.pal60)),
+            (
+                "SRAM",
+                "WirelessPad1",
+                optional_string(self.sram_settings.wireless_pad_ids[0]),
+            ),
+            (
+                "SRAM",
+                "WirelessPad2",
+                optional_string(self.sram_settings.wireless_pad_ids[1]),
+            ),
+            (
+                "SRAM",
+                "WirelessPad3",
+                optional_string(self.sram_settings.wireless_pad_ids[2]),
+            ),
+            (
+                "SRAM",
+                "WirelessPad4",
+                optional_string(self.sram_settings.wireless_pad_ids[3]),
+            ),
+            (
+                "SRAM",
+                "LastDvdError",
+                optional_string(self.sram_settings.last_dvd_error),
+            ),
+            ("SI", "Port1", port(self.ports[0]).to_string()),
+            ("SI", "Port2", port(self.ports[1]).to_string()),
+            ("SI", "Port3", port(self.ports[2]).to_string()),
+            ("SI", "Port4", port(self.ports[3]).to_string()),
+            ("EXI", "SlotA", slot(self.slot_a).to_string()),
+            ("EXI", "SlotB", slot(self.slot_b).to_string()),
+        ];
+
+        settings
+            .into_iter()
+            .map(|(section, key, value)| (section.to_string(), key.to_string(), value))
+            .collect()
+    }
+
+    /// Change a single setting, as it would appear in `section` of a config file
+    pub fn set(&mut self, section: &str, key: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("invalid value `{value}` for {key}");
+
+        match (section, key) {
+            ("Core", "Ipl") => self.ipl = path(value),
+            ("Core", "CpuTiming") => {
+                self.cpu_timing = match value.to_ascii_lowercase().as_str() {
+                    "accurate" => CpuTiming::Accurate,
+                    "fast" => CpuTiming::Fast,
+                    _ => return Err(invalid()),
+                }
+            }
+            ("Core", "DspMode") => {
+                self.dsp_mode = match value.to_ascii_lowercase().as_str() {
+                    "lle" => DspMode::Lle,
+                    "off" => DspMode::Off,
+                    _ => return Err(invalid()),
+                }
+            }
+            ("Core", "Region") => {
+                self.region = match value.to_ascii_lowercase().as_str() {
+                    "" | "auto" => None,
+                    "ntsc-j" => Some(Region::NtscJ),
+                    "ntsc-u" => Some(Region::NtscU),
+                    "pal" => Some(Region::Pal),
+                    _ => return Err(invalid()),
+                }
+            }
+            ("Core", "Language") => {
+                self.language = match value.to_ascii_lowercase().as_str() {
+                    "" | "sram" => None,
+                    "english" => Some(Language::English),
+                    "german" => Some(Language::German),
+                    "french" => Some(Language::French),
+                    "spanish" => Some(Language::Spanish),
+                    "italian" => Some(Language::Italian),
+                    "dutch" => Some(Language::Dutch),
+                    _ => return Err(invalid()),
+                }
+            }
+            ("Core", "Sram") => self.sram = path(value),
+            ("Core", "RtcTime") => {
+                self.rtc_time = match value.to_ascii_lowercase().as_str() {
+                    "" | "host" => None,
+                    _ => Some(value.parse().map_err(|_| invalid())?),
+                }
+            }
+            ("Core", "Headless") => self.headless = parse_bool(value).ok_or_else(invalid)?,
+            ("Core", "FrameLimit") => self.frame_limit = value.parse().map_err(|_| invalid())?,
+            ("Core", "Frames") => self.frames = value.parse().map_err(|_| invalid())?,
+            ("Core", "OnExit") => {
+                self.on_exit = match value.to_ascii_lowercase().as_str() {
+                    "quit" => ExitBehavior::Quit,
+                    "wait" => ExitBehavior::Wait,
+                    _ => return Err(invalid()),
+                }
+            }
+            ("DSP", "Rom") => self.dsp_rom = PathBuf::from(value),
+            ("DSP", "Coef") => self.dsp_coef = PathBuf::from(value),
+            ("Log", "Filter") => self.log_filter = Some(value.to_string()),
+            ("SI", "Port1") => self.ports[0] = port_device(value).ok_or_else(invalid)?,
+            ("SI", "Port2") => self.ports[1] = port_device(value).ok_or_else(invalid)?,
+            ("SI", "Port3") => self.ports[2] = port_device(value).ok_or_else(invalid)?,
+            ("SI", "Port4") => self.ports[3] = port_device(value).ok_or_else(invalid)?,
+            ("EXI", "SlotA") => self.slot_a = slot_device(value).ok_or_else(invalid)?,
+            ("EXI", "SlotB") => self.slot_b = slot_device(value).ok_or_else(invalid)?,
+            ("EXI", "UsbGeckoPort") => {
+                self.usb_gecko_port = value.parse().map_err(|_| invalid())?
+            }
+            ("EXI", "SerialPort1") => {
+                self.serial_port_1 = match value.to_ascii_lowercase().as_str() {
+                    "none" => SerialPortDevice::None,
+                    "bba" => SerialPortDevice::Bba,
+                    _ => return Err(invalid()),
+                }
+            }
+            ("BBA", "Backend") => {
+                self.bba.backend = match value.to_ascii_lowercase().as_str() {
+                    "udp" => BbaBackend::Udp,
+                    "tap" => BbaBackend::Tap,
+                    _ => return Err(invalid()),
+                }
+            }
+            ("BBA", "MacAddress") => self.bba.mac = mac_address(value).ok_or_else(invalid)?,
+            ("BBA", "TapDevice") => self.bba.tap_device = value.to_string(),
+            ("BBA", "LocalAddr") => self.bba.local_addr = value.parse().map_err(|_| invalid())?,
+            ("BBA", "PeerAddr") => self.bba.peer_addr = value.parse().map_err(|_| invalid())?,
+            ("MemoryCard", "SlotA") => self.memcard_a = path(value),
+            ("MemoryCard", "SlotB") => self.memcard_b = path(value),
+            ("MemoryCard", "SizeA") => {
+                self.memcard_a_size = card_size(value).ok_or_else(invalid)?
+            }
+            ("MemoryCard", "SizeB") => {
+                self.memcard_b_size = card_size(value).ok_or_else(invalid)?
+            }
+            ("SDCard", "SlotA") => self.sd_card_a = path(value),
+            ("SDCard", "SlotB") => self.sd_card_b = path(value),
+            ("SRAM", "DisplayOffset") => {
+                self.sram_settings.display_offset = optional(value, invalid)?
+            }
+            ("SRAM", "SoundMode") => {
+                self.sram_settings.sound_mode = match value.to_ascii_lowercase().as_str() {
+                    "" => None,
+                    "mono" => Some(SoundMode::Mono),
+                    "stereo" => Some(SoundMode::Stereo),
+                    _ => return Err(invalid()),
+                }
+            }
+            ("SRAM", "VideoMode") => {
+                self.sram_settings.video_mode = match value.to_ascii_lowercase().as_str() {
+                    "" => None,
+                    "ntsc" => Some(VideoMode::Ntsc),
+                    "pal" => Some(VideoMode::Pal),
+                    "mpal" => Some(VideoMode::Mpal),
+                    _ => return Err(invalid()),
+                }
+            }
+            ("SRAM", "ProgressiveScan") => {
+                self.sram_settings.progressive_scan = optional_bool(value, invalid)?
+            }
+            ("SRAM", "Pal60") => self.sram_settings.pal60 = optional_bool(value, invalid)?,
+            ("SRAM", "WirelessPad1" | "WirelessPad2" | "WirelessPad3" | "WirelessPad4") => {
+                let pad = (key.as_bytes()[11] - b'1') as usize;
+                self.sram_settings.wireless_pad_ids[pad] = optional(value, invalid)?
+            }
+            ("SRAM", "LastDvdError") => {
+                self.sram_settings.last_dvd_error = optional(value, invalid)?
+            }
+            ("Pad1" | "Pad2" | "Pad3" | "Pad4", _) => {
+                let port = (section.as_bytes()[3] - b'1') as usize;
+                let input = PadInput::from_name(key).ok_or_else(invalid)?;
+                let key = if value.is_empty() {
+                    None
+                } else {
+                    Some(key_from_name(value).ok_or_else(invalid)?)
+                };
+
+                self.key_maps[port].bind(input, key);
+            }
+            ("Hacks", _) => {
+                if parse_bool(value).ok_or_else(invalid)? {
+                    self.hacks.insert(key.to_string());
+                } else {
+                    self.hacks.remove(key);
+                }
+            }
+            // Settings for other emulators or newer versions
+            _ => debug!("Ignoring setting [{}] {}", section, key),
+        }
+
+        Ok(())
+    }
+}
+
+/// Per-game files in `dir` that exist, the game code file followed by the game id file
+pub fn game_files<P: AsRef<Path>>(dir: P, game_id: &str) -> Vec<PathBuf> {
+    let mut names = vec![&game_id[..game_id.len().min(4)]];
+    if game_id.len() > 4 {
+        names.push(game_id);
+    }
+
+    names
+        .into_iter()
+        .map(|name| dir.as_ref().join(format!("{name}.ini")))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+fn path(value: &str) -> Option<PathBuf> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(value))
+    }
+}
+
+fn card_size(value: &str) -> Option<u16> {
+    value
+        .parse()
+        .ok()
+        .filter(|blocks| CARD_SIZES.contains(blocks))
+}
+
+fn slot_device(value: &str) -> Option<SlotDevice> {
+    match value.to_ascii_lowercase().as_str() {
+        "none" => Some(SlotDevice::None),
+        "memcard" => Some(SlotDevice::MemoryCard),
+        "usbgecko" => Some(SlotDevice::UsbGecko),
+        "sdgecko" => Some(SlotDevice::SdGecko),
+        _ => None,
+    }
+}
+
+fn port_device(value: &str) -> Option<PortDevice> {
+    match value.to_ascii_lowercase().as_str() {
+        "none" => Some(PortDevice::None),
+        "controller" => Some(PortDevice::Controller),
+        "gba" => Some(PortDevice::Gba),
+        "keyboard" => Some(PortDevice::Keyboard),
+        "wheel" => Some(PortDevice::SteeringWheel),
+        "bongos" => Some(PortDevice::Bongos),
+        _ => None,
+    }
+}
+
+/// `xx:xx:xx:xx:xx:xx`
+fn mac_address(value: &str) -> Option<[u8; 6]> {
+    let mut mac = [0; 6];
+    let mut parts = value.split([':', '-']);
+
+    for byte in mac.iter_mut() {
+        *byte = u8::from_str_radix(parts.next()?, 16).ok()?;
+    }
+
+    parts.next().is_none().then_some(mac)
+}
+
+/// Unset when empty
+fn optional<T: std::str::FromStr>(
+    value: &str,
+    invalid: impl Fn() -> String,
+) -> Result<Option<T>, String> {
+    if value.is_empty() {
+        Ok(None)
+    } else {
+        value.parse().map(Some).map_err(|_| invalid())
+    }
+}
+
+/// Value of an optional setting as `optional` and `optional_bool` parse it, empty when unset
+fn optional_string<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn optional_bool(value: &str, invalid: impl Fn() -> String) -> Result<Option<bool>, String> {
+    if value.is_empty() {
+        Ok(None)
+    } else {
+        parse_bool(value).map(Some).ok_or_else(invalid)
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use minifb::Key;
+
+    use super::*;
+
+    #[test]
+    fn merge() {
+        let mut config = Config::default();
+
+        config
+            .merge_text(
+                "
+                [Core]
+                CpuTiming = fast
+                Region = pal
+                [SI]
+                Port2 = gba
+                Port4 = bongos
+                [EXI]
+                SlotB = sdgecko
+                SerialPort1 = bba
+                [BBA]
+                MacAddress = 00:09:bf:12:34:56
+                PeerAddr = 127.0.0.1:6000
+                [SDCard]
+                SlotB = sd.img
+                [MemoryCard]
+                SlotA = card_a.raw
+                SizeA = 251
+                [Pad1]
+                A = Space
+                Start =
+                [Pad2]
+                StickLeft = NumPad4
+                [Hacks]
+                SkipIntro = true
+                ",
+            )
+            .unwrap();
+
+        // game settings with patches
+        config
+            .merge_text(
+                "
+                [Core]
+                Region = ntsc-u
+                DspMode = off
+                Language = german
+                Frames = 120
+                [SRAM]
+                VideoMode = pal
+                Pal60 = true
+                WirelessPad2 = 4660
+                [Hacks]
+                SkipIntro = false
+                [OnFrame]
+                $Patch
+                0x80003100:dword:0x60000000
+                ",
+            )
+            .unwrap();
+
+        assert_eq!(config.key_maps[0].key(PadInput::A), Some(Key::Space));
+        assert_eq!(config.key_maps[0].key(PadInput::Start), None);
+        assert_eq!(config.key_maps[0].key(PadInput::B), Some(Key::Z));
+        assert_eq!(
+            config.key_maps[1].key(PadInput::StickLeft),
+            Some(Key::NumPad4)
+        );
+        assert_eq!(config.cpu_timing, CpuTiming::Fast);
+        assert_eq!(config.dsp_mode, DspMode::Off);
+        assert_eq!(config.region, Some(Region::NtscU));
+        assert_eq!(config.memcard_a, Some(PathBuf::from("card_a.raw")));
+        assert_eq!(config.memcard_a_size, 251);
+        assert_eq!(config.slot_a, SlotDevice::MemoryCard);
+        assert_eq!(config.slot_b, SlotDevice::SdGecko);
+        assert_eq!(config.sd_card_b, Some(PathBuf::from("sd.img")));
+        assert_eq!(config.serial_port_1, SerialPortDevice::Bba);
+        assert_eq!(config.ports[0], PortDevice::Controller);
+        assert_eq!(config.ports[1], PortDevice::Gba);
+        assert_eq!(config.ports[3], PortDevice::Bongos);
+        assert_eq!(config.bba.mac, [0x00, 0x09, 0xBF, 0x12, 0x34, 0x56]);
+        assert_eq!(
+            config.bba.peer_addr,
+            SocketAddr::from(([127, 0, 0, 1], 6000))
+        );
+        assert_eq!(config.bba.backend, BbaBackend::Udp);
+        assert!(!config.hack("SkipIntro"));
+        assert_eq!(config.language, Some(Language::German));
+        assert_eq!(config.frames, 120);
+        assert_eq!(config.frame_limit, 60);
+        assert_eq!(config.sram_settings.video_mode, Some(VideoMode::Pal));
+        assert_eq!(config.sram_settings.pal60, Some(true));
+        assert_eq!(config.sram_settings.wireless_pad_ids[1], Some(0x1234));
+        assert_eq!(config.sram_settings.sound_mode, None);
+
+        assert!(config.merge_text("[Core]\nCpuTiming = slow").is_err());
+        assert!(config.merge_text("[MemoryCard]\nSizeB = 60").is_err());
+        assert!(config.merge_text("[SRAM]\nDisplayOffset = 200").is_err());
+        assert!(config
+            .merge_text("[BBA]\nMacAddress = 00:09:bf:12:34")
+            .is_err());
+    }
+
+    #[test]
+    fn region_from_game_code() {
+        assert_eq!(Region::from_game_code(0x4741_4C45), Some(Region::NtscU)); // GALE
+        assert_eq!(Region::from_game_code(0x4741_4C50), Some(Region::Pal)); // GALP
+        assert_eq!(Region::from_game_code(0x4741_4C4A), Some(Region::NtscJ)); // GALJ
+    }
+}
@@ -1,3 +1,4 @@
+pub(crate) mod assembler;
 #[allow(dead_code)]
 pub(crate) mod disassembler;
 mod float;
@@ -28,9 +29,11 @@ use self::{
 };
 use crate::{
     bus::{Bus, ReadWrite},
+    config::CpuTiming,
     hw::{
         bootrom::Bootrom,
         memory::{Memory, MEMORY_SIZE},
+        mi::MemoryInterface,
         mmio::Mmio,
     },
 };
@@ -123,6 +126,8 @@ pub(crate) struct Cpu {
     immu: Mmu,
     /// Data Memory Management Unit (DMMU)
     dmmu: Mmu,
+    /// Multiplier applied to instruction cycle counts
+    cycle_scale: u32,
 }
 
 impl Default for Cpu {
@@ -149,6 +154,7 @@ impl Default for Cpu {
             spr,
             immu: Default::default(),
             dmmu: Default::default(),
+            cycle_scale: 1,
         };
 
         cpu.check_exceptions();
@@ -391,6 +397,45 @@ impl Cpu {
         }
     }
 
+    /// Read by a load instruction, main memory accesses are checked by the Memory Interface.
+    /// Host side reads by cheats, patches and loaders use `read`.
+    pub fn load<T>(&mut self, bus: &mut Bus, ea: u32) -> Option<T>
+    where
+        Mmio: ReadWrite<T>,
+        Memory: ReadWrite<T>,
+        L1Cache: ReadWrite<T>,
+        Bootrom: ReadWrite<T>,
+    {
+        let addr = self.translate_data_address(ea, &mut bus.memory, false)?;
+
+        if addr < MEMORY_SIZE {
+            MemoryInterface::access(bus, &mut self.state, addr, false);
+        }
+
+        Some(bus.read(&mut self.state, addr))
+    }
+
+    /// Write by a store instruction, main memory accesses are checked by the Memory Interface.
+    /// Host side writes by cheats, patches and loaders use `write`.
+    pub fn store<T>(&mut self, bus: &mut Bus, ea: u32, val: T) -> bool
+    where
+        Mmio: ReadWrite<T>,
+        Memory: ReadWrite<T>,
+        L1Cache: ReadWrite<T>,
+    {
+        match self.translate_data_address(ea, &mut bus.memory, true) {
+            Some(addr) => {
+                if addr < MEMORY_SIZE {
+                    MemoryInterface::access(bus, &mut self.state, addr, true);
+                }
+
+                bus.write(&mut self.state, addr, val);
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn write_bytes(&mut self, bus: &mut Bus, ea: u32, data: &[u8]) -> bool {
         match self.translate_data_address(ea, &mut bus.memory, true) {
             Some(addr) => {
@@ -443,7 +488,26 @@ impl Cpu {
         self.ensure_fp()
     }
 
+    /// Hard reset to the power on state, the time base keeps counting
+    pub fn reset(&mut self) {
+        let timers = std::mem::take(&mut self.state.timers);
+        let cycle_scale = self.cycle_scale;
+
+        *self = Cpu::default();
+
+        self.state.timers = timers;
+        self.cycle_scale = cycle_scale;
+    }
+
+    pub fn set_timing(&mut self, timing: CpuTiming) {
+        self.cycle_scale = match timing {
+            CpuTiming::Accurate => 1,
+            CpuTiming::Fast => 2,
+        };
+    }
+
     pub fn tick(&mut self, cycles: u32) {
+        let cycles = cycles * self.cycle_scale;
         if self.state.timers.tick_decrementer(cycles) {
             self.state.timers.set_decrementer(0xFFFF_FFFF);
             self.spr[SPR_DEC] = 0xFFFF_FFFF;
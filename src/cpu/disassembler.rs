@@ -392,6 +392,7 @@ pub fn mnemonic(opcode: Opcode) -> &'static str {
     }
 }
 
+#[allow(clippy::collapsible_match)]
 pub fn simplified_mnemonic(
     instr: Instruction,
     opcode: Opcode,
@@ -486,6 +487,7 @@ pub fn simplified_mnemonic(
                     return Some(("bdnztlr", operands));
                 }
                 10 => {
+                    operands = format!("{bi}");
                     return Some(("bdztlr", operands));
                 }
                 12 => {
@@ -760,6 +762,7 @@ fn format_bclr_cr(bi: u32) -> String {
     }
 }
 
+#[allow(clippy::collapsible_match)]
 pub fn suffix(instr: Instruction, opcode: Opcode) -> &'static str {
     match opcode {
         Opcode::Bx | Opcode::Bcx => match (instr.aa(), instr.lk()) {
@@ -982,7 +985,7 @@ pub fn operands(instr: Instruction, opcode: Opcode, addr: u32) -> String {
             instr.d(),
             sign_ext_12(instr.uimm_1()),
             instr.a(),
-            instr.w(),
+            instr.w() as u8,
             instr.i()
         ),
         Opcode::PsqLx | Opcode::PsqLux => format!(
@@ -990,7 +993,7 @@ pub fn operands(instr: Instruction, opcode: Opcode, addr: u32) -> String {
             instr.d(),
             instr.a(),
             instr.b(),
-            instr.w(),
+            instr.w() as u8,
             instr.i()
         ),
         Opcode::PsqSt | Opcode::PsqStu => format!(
@@ -998,7 +1001,7 @@ pub fn operands(instr: Instruction, opcode: Opcode, addr: u32) -> String {
             instr.s(),
             sign_ext_12(instr.uimm_1()),
             instr.a(),
-            instr.w(),
+            instr.w() as u8,
             instr.i()
         ),
         Opcode::PsqStx | Opcode::PsqStux => format!(
@@ -1006,7 +1009,7 @@ pub fn operands(instr: Instruction, opcode: Opcode, addr: u32) -> String {
             instr.s(),
             instr.a(),
             instr.b(),
-            instr.w(),
+            instr.w() as u8,
             instr.i()
         ),
         Opcode::Crxor
@@ -110,6 +110,7 @@ impl Instruction {
 use super::opcodes::*;
 
 #[cfg(test)]
+#[allow(dead_code)]
 impl Instruction {
     pub fn new(opcd: u32) -> Self {
         Self(opcd << 26)
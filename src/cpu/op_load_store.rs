@@ -109,7 +109,7 @@ impl Cpu {
     pub fn op_lbz(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea(instr);
 
-        if let Some(val) = self.read::<u8>(bus, ea) {
+        if let Some(val) = self.load::<u8>(bus, ea) {
             self.gpr[instr.d()] = u32::from(val);
         }
 
@@ -123,7 +123,7 @@ impl Cpu {
 
         let ea = self.gpr[instr.a()].wrapping_add(instr.simm() as u32);
 
-        if let Some(val) = self.read::<u8>(bus, ea) {
+        if let Some(val) = self.load::<u8>(bus, ea) {
             self.gpr[instr.d()] = u32::from(val);
             self.gpr[instr.a()] = ea;
         }
@@ -138,7 +138,7 @@ impl Cpu {
     pub fn op_lbzx(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea_x(instr);
 
-        if let Some(val) = self.read::<u8>(bus, ea) {
+        if let Some(val) = self.load::<u8>(bus, ea) {
             self.gpr[instr.d()] = u32::from(val);
         }
 
@@ -152,7 +152,7 @@ impl Cpu {
 
         let ea = self.get_ea(instr);
 
-        if let Some(val) = self.read::<u64>(bus, ea) {
+        if let Some(val) = self.load::<u64>(bus, ea) {
             self.fpr[instr.d()].set_ps0(val);
         }
 
@@ -178,7 +178,7 @@ impl Cpu {
 
         let ea = self.get_ea(instr);
 
-        if let Some(raw) = self.read::<u32>(bus, ea) {
+        if let Some(raw) = self.load::<u32>(bus, ea) {
             let val = convert_to_double(raw);
             self.fpr[instr.d()].set_ps0(val);
             if self.hid2.pse() {
@@ -196,7 +196,7 @@ impl Cpu {
 
         let ea = self.get_ea_u(instr);
 
-        if let Some(raw) = self.read::<u32>(bus, ea) {
+        if let Some(raw) = self.load::<u32>(bus, ea) {
             let val = convert_to_double(raw);
             self.fpr[instr.d()].set_ps0(val);
             if self.hid2.pse() {
@@ -217,7 +217,7 @@ impl Cpu {
     pub fn op_lha(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea(instr);
 
-        if let Some(val) = self.read::<u16>(bus, ea) {
+        if let Some(val) = self.load::<u16>(bus, ea) {
             self.gpr[instr.d()] = i32::from(val as i16) as u32;
         }
 
@@ -243,7 +243,7 @@ impl Cpu {
     pub fn op_lhz(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea(instr);
 
-        if let Some(val) = self.read::<u16>(bus, ea) {
+        if let Some(val) = self.load::<u16>(bus, ea) {
             self.gpr[instr.d()] = u32::from(val);
         }
 
@@ -253,7 +253,7 @@ impl Cpu {
     pub fn op_lhzu(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea_u(instr);
 
-        if let Some(val) = self.read::<u16>(bus, ea) {
+        if let Some(val) = self.load::<u16>(bus, ea) {
             self.gpr[instr.d()] = u32::from(val);
             self.gpr[instr.a()] = ea;
         }
@@ -268,7 +268,7 @@ impl Cpu {
     pub fn op_lhzx(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea_x(instr);
 
-        if let Some(val) = self.read::<u16>(bus, ea) {
+        if let Some(val) = self.load::<u16>(bus, ea) {
             self.gpr[instr.d()] = u32::from(val);
         }
     }
@@ -279,7 +279,7 @@ impl Cpu {
         let n = (32 - r) as u32;
 
         while r < 32 {
-            match self.read::<u32>(bus, ea) {
+            match self.load::<u32>(bus, ea) {
                 Some(val) => self.gpr[r] = val,
                 None => break,
             }
@@ -306,7 +306,7 @@ impl Cpu {
             panic!("lwarx: unaligned address {ea:#x}");
         }
 
-        if let Some(val) = self.read::<u32>(bus, ea) {
+        if let Some(val) = self.load::<u32>(bus, ea) {
             self.gpr[instr.d()] = val;
             self.reserve = true;
             self.reserve_address = ea;
@@ -322,7 +322,7 @@ impl Cpu {
     pub fn op_lwz(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea(instr);
 
-        if let Some(val) = self.read::<u32>(bus, ea) {
+        if let Some(val) = self.load::<u32>(bus, ea) {
             self.gpr[instr.d()] = val;
         }
 
@@ -332,7 +332,7 @@ impl Cpu {
     pub fn op_lwzu(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea_u(instr);
 
-        if let Some(val) = self.read::<u32>(bus, ea) {
+        if let Some(val) = self.load::<u32>(bus, ea) {
             self.gpr[instr.d()] = val;
             self.gpr[instr.a()] = ea;
         }
@@ -347,7 +347,7 @@ impl Cpu {
     pub fn op_lwzx(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea_x(instr);
 
-        if let Some(val) = self.read::<u32>(bus, ea) {
+        if let Some(val) = self.load::<u32>(bus, ea) {
             self.gpr[instr.d()] = val;
         }
 
@@ -368,13 +368,13 @@ impl Cpu {
         if instr.w() {
             let val = match ld_type {
                 QUANTIZE_FLOAT => self
-                    .read::<u32>(bus, ea)
+                    .load::<u32>(bus, ea)
                     .map(|v| f64::from_bits(convert_to_double(v))),
                 QUANTIZE_U8 | QUANTIZE_I8 => self
-                    .read::<u8>(bus, ea)
+                    .load::<u8>(bus, ea)
                     .map(|v| dequantize(u32::from(v), ld_type, ld_scale) as f64),
                 QUANTIZE_U16 | QUANTIZE_I16 => self
-                    .read::<u16>(bus, ea)
+                    .load::<u16>(bus, ea)
                     .map(|v| dequantize(u32::from(v), ld_type, ld_scale) as f64),
                 _ => panic!("psq_l: invalid type {:}", ld_type),
             };
@@ -385,20 +385,20 @@ impl Cpu {
             }
         } else {
             let pair = match ld_type {
-                QUANTIZE_FLOAT => self.read::<u32>(bus, ea).and_then(|a| {
-                    self.read::<u32>(bus, ea + 4)
+                QUANTIZE_FLOAT => self.load::<u32>(bus, ea).and_then(|a| {
+                    self.load::<u32>(bus, ea + 4)
                         .map(|b| (f32::from_bits(a), f32::from_bits(b)))
                 }),
-                QUANTIZE_U8 | QUANTIZE_I8 => self.read::<u8>(bus, ea).and_then(|a| {
-                    self.read::<u8>(bus, ea + 1).map(|b| {
+                QUANTIZE_U8 | QUANTIZE_I8 => self.load::<u8>(bus, ea).and_then(|a| {
+                    self.load::<u8>(bus, ea + 1).map(|b| {
                         (
                             dequantize(u32::from(a), ld_type, ld_scale),
                             dequantize(u32::from(b), ld_type, ld_scale),
                         )
                     })
                 }),
-                QUANTIZE_U16 | QUANTIZE_I16 => self.read::<u16>(bus, ea).and_then(|a| {
-                    self.read::<u16>(bus, ea + 2).map(|b| {
+                QUANTIZE_U16 | QUANTIZE_I16 => self.load::<u16>(bus, ea).and_then(|a| {
+                    self.load::<u16>(bus, ea + 2).map(|b| {
                         (
                             dequantize(u32::from(a), ld_type, ld_scale),
                             dequantize(u32::from(b), ld_type, ld_scale),
@@ -465,28 +465,28 @@ impl Cpu {
 
         if instr.w() {
             match st_type {
-                QUANTIZE_FLOAT => self.write::<u32>(bus, ea, convert_to_single(ps0)),
+                QUANTIZE_FLOAT => self.store::<u32>(bus, ea, convert_to_single(ps0)),
                 QUANTIZE_U8 | QUANTIZE_I8 => {
-                    self.write::<u8>(bus, ea, quantize(ps0_f32, st_type, st_scale) as u8)
+                    self.store::<u8>(bus, ea, quantize(ps0_f32, st_type, st_scale) as u8)
                 }
                 QUANTIZE_U16 | QUANTIZE_I16 => {
-                    self.write::<u16>(bus, ea, quantize(ps0_f32, st_type, st_scale) as u16)
+                    self.store::<u16>(bus, ea, quantize(ps0_f32, st_type, st_scale) as u16)
                 }
                 _ => panic!("psq_st: invalid type {:}", st_type),
             }
         } else {
             match st_type {
                 QUANTIZE_FLOAT => {
-                    self.write::<u32>(bus, ea, convert_to_single(ps0))
-                        && self.write::<u32>(bus, ea.wrapping_add(4), convert_to_single(ps1))
+                    self.store::<u32>(bus, ea, convert_to_single(ps0))
+                        && self.store::<u32>(bus, ea.wrapping_add(4), convert_to_single(ps1))
                 }
                 QUANTIZE_U8 | QUANTIZE_I8 => {
-                    self.write::<u8>(bus, ea, quantize(ps0_f32, st_type, st_scale) as u8)
-                        && self.write::<u8>(bus, ea + 1, quantize(ps1_f32, st_type, st_scale) as u8)
+                    self.store::<u8>(bus, ea, quantize(ps0_f32, st_type, st_scale) as u8)
+                        && self.store::<u8>(bus, ea + 1, quantize(ps1_f32, st_type, st_scale) as u8)
                 }
                 QUANTIZE_U16 | QUANTIZE_I16 => {
-                    self.write::<u16>(bus, ea, quantize(ps0_f32, st_type, st_scale) as u16)
-                        && self.write::<u16>(
+                    self.store::<u16>(bus, ea, quantize(ps0_f32, st_type, st_scale) as u16)
+                        && self.store::<u16>(
                             bus,
                             ea + 2,
                             quantize(ps1_f32, st_type, st_scale) as u16,
@@ -508,7 +508,7 @@ impl Cpu {
     pub fn op_stb(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea(instr);
 
-        self.write::<u8>(bus, ea, self.gpr[instr.s()] as u8);
+        self.store::<u8>(bus, ea, self.gpr[instr.s()] as u8);
 
         self.tick(2);
     }
@@ -516,7 +516,7 @@ impl Cpu {
     pub fn op_stbu(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea_u(instr);
 
-        if self.write::<u8>(bus, ea, self.gpr[instr.s()] as u8) {
+        if self.store::<u8>(bus, ea, self.gpr[instr.s()] as u8) {
             self.gpr[instr.a()] = ea;
         }
 
@@ -530,7 +530,7 @@ impl Cpu {
     pub fn op_stbx(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea_x(instr);
 
-        self.write::<u8>(bus, ea, self.gpr[instr.s()] as u8);
+        self.store::<u8>(bus, ea, self.gpr[instr.s()] as u8);
 
         self.tick(2);
     }
@@ -538,7 +538,7 @@ impl Cpu {
     pub fn op_stfd(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea(instr);
 
-        self.write::<u64>(bus, ea, self.fpr[instr.s()].ps0());
+        self.store::<u64>(bus, ea, self.fpr[instr.s()].ps0());
 
         self.tick(2);
     }
@@ -564,7 +564,7 @@ impl Cpu {
 
         let val = self.fpr[instr.s()].ps0();
 
-        self.write::<u32>(bus, ea, convert_to_single(val));
+        self.store::<u32>(bus, ea, convert_to_single(val));
 
         self.tick(2);
     }
@@ -578,7 +578,7 @@ impl Cpu {
 
         let val = self.fpr[instr.s()].ps0();
 
-        if self.write::<u32>(bus, ea, convert_to_single(val)) {
+        if self.store::<u32>(bus, ea, convert_to_single(val)) {
             self.gpr[instr.a()] = ea;
         }
 
@@ -594,13 +594,13 @@ impl Cpu {
 
         let val = self.fpr[instr.s()].ps0();
 
-        self.write::<u32>(bus, ea, convert_to_single(val));
+        self.store::<u32>(bus, ea, convert_to_single(val));
     }
 
     pub fn op_sth(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea(instr);
 
-        self.write::<u16>(bus, ea, self.gpr[instr.s()] as u16);
+        self.store::<u16>(bus, ea, self.gpr[instr.s()] as u16);
 
         self.tick(2);
     }
@@ -612,7 +612,7 @@ impl Cpu {
     pub fn op_sthu(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea_u(instr);
 
-        if self.write::<u16>(bus, ea, self.gpr[instr.s()] as u16) {
+        if self.store::<u16>(bus, ea, self.gpr[instr.s()] as u16) {
             self.gpr[instr.a()] = ea;
         }
 
@@ -626,7 +626,7 @@ impl Cpu {
     pub fn op_sthx(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea_ux(instr);
 
-        self.write::<u16>(bus, ea, self.gpr[instr.s()] as u16);
+        self.store::<u16>(bus, ea, self.gpr[instr.s()] as u16);
 
         self.tick(2);
     }
@@ -638,7 +638,7 @@ impl Cpu {
         let n = (32 - r) as u32;
 
         while r < 32 {
-            if !self.write::<u32>(bus, ea, self.gpr[r]) {
+            if !self.store::<u32>(bus, ea, self.gpr[r]) {
                 break;
             }
 
@@ -660,7 +660,7 @@ impl Cpu {
     pub fn op_stw(&mut self, instr: Instruction, bus: &mut Bus) {
         let addr = self.get_ea(instr);
 
-        self.write::<u32>(bus, addr, self.gpr[instr.s()]);
+        self.store::<u32>(bus, addr, self.gpr[instr.s()]);
 
         self.tick(2);
     }
@@ -679,7 +679,7 @@ impl Cpu {
         let so = self.xer.summary_overflow() as u32;
 
         if self.reserve && ea == self.reserve_address {
-            if self.write::<u32>(bus, ea, self.gpr[instr.s()]) {
+            if self.store::<u32>(bus, ea, self.gpr[instr.s()]) {
                 self.reserve = false;
                 self.cr.set_field(0, 0x2 | so);
             }
@@ -698,7 +698,7 @@ impl Cpu {
 
         let ea = self.get_ea_u(instr);
 
-        if self.write::<u32>(bus, ea, self.gpr[instr.s()]) {
+        if self.store::<u32>(bus, ea, self.gpr[instr.s()]) {
             self.gpr[instr.a()] = ea;
         }
 
@@ -712,7 +712,7 @@ impl Cpu {
 
         let ea = self.get_ea_ux(instr);
 
-        if self.write::<u32>(bus, ea, self.gpr[instr.s()]) {
+        if self.store::<u32>(bus, ea, self.gpr[instr.s()]) {
             self.gpr[instr.a()] = ea;
         }
 
@@ -722,7 +722,7 @@ impl Cpu {
     pub fn op_stwx(&mut self, instr: Instruction, bus: &mut Bus) {
         let ea = self.get_ea_x(instr);
 
-        self.write::<u32>(bus, ea, self.gpr[instr.s()]);
+        self.store::<u32>(bus, ea, self.gpr[instr.s()]);
 
         self.tick(2);
     }
@@ -781,16 +781,16 @@ mod tests {
         cpu.state.exceptions = 0;
         cpu.spr[SPR_SRR1] = 0;
         for i in 0..8 {
-            cpu.write::<u32>(&mut bus, 0x0000_1000 + i * 4, 0xDEAD_BEEF);
+            cpu.store::<u32>(&mut bus, 0x0000_1000 + i * 4, 0xDEAD_BEEF);
         }
         // Neighboring line marker
-        cpu.write::<u32>(&mut bus, 0x0000_1020, 0xCAFE_BABE);
+        cpu.store::<u32>(&mut bus, 0x0000_1020, 0xCAFE_BABE);
 
         cpu.op_dcbz_l(instr, &mut bus);
         assert_eq!(cpu.state.exceptions, 0);
         for i in 0..8 {
-            assert_eq!(cpu.read::<u32>(&mut bus, 0x0000_1000 + i * 4), Some(0));
+            assert_eq!(cpu.load::<u32>(&mut bus, 0x0000_1000 + i * 4), Some(0));
         }
-        assert_eq!(cpu.read::<u32>(&mut bus, 0x0000_1020), Some(0xCAFE_BABE));
+        assert_eq!(cpu.load::<u32>(&mut bus, 0x0000_1020), Some(0xCAFE_BABE));
     }
 }
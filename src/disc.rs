@@ -1,3 +1,5 @@
+mod fst;
+
 use std::{
     fs,
     io::{prelude::*, Error, ErrorKind, Read, SeekFrom},
@@ -6,30 +8,97 @@ use std::{
 
 use byteorder::{BigEndian, ByteOrder};
 
-use crate::{bus::Bus, cpu::Cpu};
+pub use self::fst::{Fst, FstEntry};
+
+use crate::{bus::Bus, config::Region, cpu::Cpu, dol, patches::Patches};
 
 const DISC_MAGIC: u32 = 0xC2339F3D;
 const APL_INIT_OFFSET: u32 = 0x4; // AplInit
 const APL_MAIN_OFFSET: u32 = 0x8; // AplMain
 const APL_CLOSE_OFFSET: u32 = 0xC; // AplClose
 
+/// Size of the disc header, boot.bin
+pub const HEADER_SIZE: usize = 0x440;
+/// Offset and size of the disc header information, bi2.bin
+pub const BI2_OFFSET: u64 = 0x440;
+pub const BI2_SIZE: usize = 0x2000;
+/// Offset of the apploader, its header followed by the code and trailer
+pub const APPLOADER_OFFSET: u64 = 0x2440;
+pub const APPLOADER_HEADER_SIZE: usize = 0x20;
+
 pub struct Disc {
     file: std::fs::File,
+    /// Size of the image
+    len: u64,
+    header: Header,
+    bi2: Bi2,
+    apploader: Apploader,
+    /// Parsed when the files are first browsed, booting doesn't need it
+    fst: Option<Fst>,
+}
+
+/// Disc header, boot.bin
+#[derive(Debug)]
+pub struct Header {
+    pub game_code: u32,
+    pub maker_code: u16,
+    pub disc_number: u8,
+    pub version: u8,
+    pub audio_streaming: bool,
+    pub stream_buffer_size: u8,
+    pub game_name: String,
+    pub debug_monitor_offset: u32,
+    pub debug_monitor_address: u32,
+    /// Offset of the main DOL
+    pub dol_offset: u32,
+    pub fst_offset: u32,
+    pub fst_size: u32,
+    /// Largest FST of the discs of a multi-disc game
+    pub fst_max_size: u32,
+}
+
+/// Disc header information, bi2.bin
+#[derive(Debug)]
+pub struct Bi2 {
+    pub debug_monitor_size: u32,
+    pub simulated_memory_size: u32,
+    pub argument_offset: u32,
+    pub debug_flag: u32,
+    pub track_location: u32,
+    pub track_size: u32,
+    pub country_code: u32,
 }
 
+impl Bi2 {
+    pub fn region(&self) -> Option<Region> {
+        match self.country_code {
+            0 => Some(Region::NtscJ),
+            1 => Some(Region::NtscU),
+            2 => Some(Region::Pal),
+            _ => None,
+        }
+    }
+}
+
+/// Apploader header
 #[derive(Debug)]
-struct Header {
-    game_code: u32,
-    maker_code: u16,
-    game_name: String,
-    bootfile_offset: u32,
-    fst_offset: u32,
-    fst_size: u32,
+pub struct Apploader {
+    pub date: String,
+    pub entry_point: u32,
+    pub size: u32,
+    pub trailer_size: u32,
+}
+
+impl Apploader {
+    /// Size of the apploader on the disc, apploader.img
+    pub fn image_size(&self) -> usize {
+        APPLOADER_HEADER_SIZE + self.size as usize + self.trailer_size as usize
+    }
 }
 
 impl Disc {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Disc, Error> {
-        let mut buff = [0; 0x440];
+        let mut buff = [0; HEADER_SIZE];
         let mut file = fs::File::open(path)?;
 
         file.read_exact(&mut buff)?;
@@ -43,62 +112,180 @@ impl Disc {
             ));
         }
 
-        let game_code = BigEndian::read_u32(&buff[0x0..]);
-
-        let maker_code = BigEndian::read_u16(&buff[0x04..]);
-
-        let game_name = String::from_utf8_lossy(&buff[0x20..0x3FF])
-            .into_owned()
-            .trim_matches(char::from(0))
-            .to_string();
-        let bootfile_offset = BigEndian::read_u32(&buff[0x420..]);
-        let fst_offset = BigEndian::read_u32(&buff[0x424..]);
-        let fst_size = BigEndian::read_u32(&buff[0x428..]);
-
         let header = Header {
-            game_code,
-            maker_code,
-            game_name,
-            bootfile_offset,
-            fst_offset,
-            fst_size,
+            game_code: BigEndian::read_u32(&buff[0x0..]),
+            maker_code: BigEndian::read_u16(&buff[0x04..]),
+            disc_number: buff[0x06],
+            version: buff[0x07],
+            audio_streaming: buff[0x08] != 0,
+            stream_buffer_size: buff[0x09],
+            game_name: String::from_utf8_lossy(&buff[0x20..0x3FF])
+                .into_owned()
+                .trim_matches(char::from(0))
+                .to_string(),
+            debug_monitor_offset: BigEndian::read_u32(&buff[0x400..]),
+            debug_monitor_address: BigEndian::read_u32(&buff[0x404..]),
+            dol_offset: BigEndian::read_u32(&buff[0x420..]),
+            fst_offset: BigEndian::read_u32(&buff[0x424..]),
+            fst_size: BigEndian::read_u32(&buff[0x428..]),
+            fst_max_size: BigEndian::read_u32(&buff[0x42C..]),
         };
 
         info!(
-            "Reading Disc: game_code {:#x} | maker_code {:#x} | game_name {:} | bootfile_offset: {:#x} | fst_offset {:#x} | fst_size {:#x}",
-            header.game_code, header.maker_code, header.game_name, header.bootfile_offset, header.fst_offset, header.fst_size
+            "Reading Disc: game_code {:#x} | maker_code {:#x} | game_name {:} | dol_offset: {:#x} | fst_offset {:#x} | fst_size {:#x}",
+            header.game_code, header.maker_code, header.game_name, header.dol_offset, header.fst_offset, header.fst_size
         );
 
-        Ok(Disc { file })
+        let mut buff = [0; 0x1C];
+        file.seek(SeekFrom::Start(BI2_OFFSET))?;
+        file.read_exact(&mut buff)?;
+
+        let bi2 = Bi2 {
+            debug_monitor_size: BigEndian::read_u32(&buff[0x00..]),
+            simulated_memory_size: BigEndian::read_u32(&buff[0x04..]),
+            argument_offset: BigEndian::read_u32(&buff[0x08..]),
+            debug_flag: BigEndian::read_u32(&buff[0x0C..]),
+            track_location: BigEndian::read_u32(&buff[0x10..]),
+            track_size: BigEndian::read_u32(&buff[0x14..]),
+            country_code: BigEndian::read_u32(&buff[0x18..]),
+        };
+
+        let mut buff = [0; APPLOADER_HEADER_SIZE];
+        file.seek(SeekFrom::Start(APPLOADER_OFFSET))?;
+        file.read_exact(&mut buff)?;
+
+        let apploader = Apploader {
+            date: String::from_utf8_lossy(&buff[0x00..0x0A])
+                .into_owned()
+                .trim_matches(char::from(0))
+                .to_string(),
+            entry_point: BigEndian::read_u32(&buff[0x10..]),
+            size: BigEndian::read_u32(&buff[0x14..]),
+            trailer_size: BigEndian::read_u32(&buff[0x18..]),
+        };
+
+        let len = file.metadata()?.len();
+
+        Ok(Disc {
+            file,
+            len,
+            header,
+            bi2,
+            apploader,
+            fst: None,
+        })
     }
 
-    /// Execute apploader
-    pub fn load(&mut self, cpu: &mut Cpu, bus: &mut Bus) -> Result<(), Error> {
-        // TODO: Write disk header information to 0x8000_00F4
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn bi2(&self) -> &Bi2 {
+        &self.bi2
+    }
+
+    pub fn apploader(&self) -> &Apploader {
+        &self.apploader
+    }
+
+    /// File system table, read and parsed on first use
+    pub fn fst(&mut self) -> Result<&Fst, Error> {
+        let fst = match self.fst.take() {
+            Some(fst) => fst,
+            None => self.read_fst()?,
+        };
 
-        let mut buff = [0; 0x20];
+        Ok(self.fst.insert(fst))
+    }
 
-        self.file.seek(SeekFrom::Start(0x2440))?;
+    fn read_fst(&mut self) -> Result<Fst, Error> {
+        // names on japanese discs are in Shift JIS
+        let encoding = if self.bi2.region() == Some(Region::NtscJ) {
+            encoding_rs::SHIFT_JIS
+        } else {
+            encoding_rs::WINDOWS_1252
+        };
 
-        self.file.read_exact(&mut buff)?;
+        let data = self.read_vec(self.header.fst_offset as u64, self.header.fst_size as usize)?;
+        Fst::parse(&data, encoding)
+    }
+
+    /// Entries of the directory at `path`, the root directory is `/`
+    pub fn list(&mut self, path: &str) -> Result<&[FstEntry], Error> {
+        let fst = self.fst()?;
+
+        if path.split('/').all(str::is_empty) {
+            return Ok(fst.root());
+        }
 
-        let apploader_date = String::from_utf8_lossy(&buff[0x00..0x09])
-            .into_owned()
-            .trim_matches(char::from(0))
-            .to_string();
+        match fst.find(path) {
+            Some(FstEntry::Directory { entries, .. }) => Ok(entries),
+            Some(FstEntry::File { .. }) => Err(Error::new(
+                ErrorKind::NotADirectory,
+                format!("{path}: not a directory"),
+            )),
+            None => Err(Error::new(
+                ErrorKind::NotFound,
+                format!("{path}: no such file or directory"),
+            )),
+        }
+    }
+
+    /// Open the file at `path` for reading
+    pub fn open_file(&mut self, path: &str) -> Result<DiscFile<'_>, Error> {
+        match self.fst()?.find(path) {
+            Some(&FstEntry::File { offset, size, .. }) => Ok(DiscFile {
+                file: &mut self.file,
+                offset: offset as u64,
+                size: size as u64,
+                pos: 0,
+            }),
+            Some(FstEntry::Directory { .. }) => Err(Error::new(
+                ErrorKind::IsADirectory,
+                format!("{path}: is a directory"),
+            )),
+            None => Err(Error::new(
+                ErrorKind::NotFound,
+                format!("{path}: no such file or directory"),
+            )),
+        }
+    }
+
+    pub fn game_code(&self) -> u32 {
+        self.header.game_code
+    }
+
+    /// Six character game id, game code followed by maker code (e.g. `GALE01`)
+    pub fn game_id(&self) -> String {
+        let mut id = [0; 6];
+
+        BigEndian::write_u32(&mut id, self.header.game_code);
+        BigEndian::write_u16(&mut id[4..], self.header.maker_code);
+
+        String::from_utf8_lossy(&id).into_owned()
+    }
+
+    /// Execute apploader, then apply game patches
+    pub(crate) fn load(
+        &mut self,
+        cpu: &mut Cpu,
+        bus: &mut Bus,
+        patches: &Patches,
+    ) -> Result<(), Error> {
+        // TODO: Write disk header information to 0x8000_00F4
 
-        let apploader_entrypoint = BigEndian::read_u32(&buff[0x10..]);
-        let apploader_size = BigEndian::read_u32(&buff[0x14..]);
-        let trailer_size = BigEndian::read_u32(&buff[0x18..]);
+        let apploader = &self.apploader;
 
         info!(
             "Apploader: date {:} | entrypoint {:#x} | size {:#x} | trailer_size: {:}",
-            apploader_date, apploader_entrypoint, apploader_size, trailer_size
+            apploader.date, apploader.entry_point, apploader.size, apploader.trailer_size
         );
 
-        let mut buff = vec![0; apploader_size as usize];
-
-        self.file.read_exact(buff.as_mut_slice())?;
+        let apploader_entrypoint = apploader.entry_point;
+        let buff = self.read_vec(
+            APPLOADER_OFFSET + APPLOADER_HEADER_SIZE as u64,
+            apploader.size as usize,
+        )?;
 
         cpu.write_bytes(bus, 0x8120_0000, buff.as_slice());
 
@@ -168,6 +355,8 @@ impl Disc {
 
         cpu.cia = cpu.gpr[3];
 
+        patches.apply_on_load(cpu, bus);
+
         Ok(())
     }
 
@@ -176,6 +365,32 @@ impl Disc {
         self.file.read_exact(buf)?;
         Ok(())
     }
+
+    /// Read the main DOL, its size is taken from the DOL header
+    pub fn read_dol(&mut self) -> Result<Vec<u8>, Error> {
+        let offset = self.header.dol_offset as u64;
+
+        let mut header = [0; dol::HEADER_SIZE];
+        self.read_at(offset, &mut header)?;
+
+        self.read_vec(offset, dol::file_size(&header) as usize)
+    }
+
+    /// Read `size` bytes at `offset`, sizes from the disc are checked against the image
+    /// before allocating
+    pub fn read_vec(&mut self, offset: u64, size: usize) -> Result<Vec<u8>, Error> {
+        if offset.saturating_add(size as u64) > self.len {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{size:#x} bytes at {offset:#x} are past the end of the disc"),
+            ));
+        }
+
+        let mut data = vec![0; size];
+        self.read_at(offset, &mut data)?;
+
+        Ok(data)
+    }
 }
 
 fn run_function(cpu: &mut Cpu, bus: &mut Bus, address: u32) {
@@ -186,3 +401,162 @@ fn run_function(cpu: &mut Cpu, bus: &mut Bus, address: u32) {
         cpu.step(bus);
     }
 }
+
+/// File on a disc, read from the disc image
+pub struct DiscFile<'a> {
+    file: &'a mut fs::File,
+    offset: u64,
+    size: u64,
+    pos: u64,
+}
+
+impl DiscFile<'_> {
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+}
+
+impl Read for DiscFile<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let len = (self.size.saturating_sub(self.pos)).min(buf.len() as u64) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+
+        self.file.seek(SeekFrom::Start(self.offset + self.pos))?;
+        let len = self.file.read(&mut buf[..len])?;
+        self.pos += len as u64;
+
+        Ok(len)
+    }
+}
+
+impl Seek for DiscFile<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(delta) => self.size.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+
+        self.pos =
+            pos.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "seek to a negative position"))?;
+
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Image with `data/file.bin` and `opening.bnr`
+    fn test_image() -> Vec<u8> {
+        let mut image = vec![0; 0x8000];
+
+        image[..6].copy_from_slice(b"GTSE01");
+        BigEndian::write_u32(&mut image[0x1C..], DISC_MAGIC);
+        image[0x20..0x24].copy_from_slice(b"Test");
+        BigEndian::write_u32(&mut image[0x420..], 0x4000);
+        BigEndian::write_u32(&mut image[0x424..], 0x3000);
+        BigEndian::write_u32(&mut image[0x428..], 0x50);
+        BigEndian::write_u32(&mut image[0x42C..], 0x50);
+
+        // bi2 country code
+        BigEndian::write_u32(&mut image[0x440 + 0x18..], 1);
+
+        image[0x2440..0x244A].copy_from_slice(b"2004/02/03");
+        BigEndian::write_u32(&mut image[0x2440 + 0x10..], 0x8120_0000);
+        BigEndian::write_u32(&mut image[0x2440 + 0x14..], 0x100);
+
+        let fst: &[(u32, u32, u32)] = &[
+            (0x0100_0000, 0, 4),
+            (0x0100_0000, 0, 3),
+            (5, 0x6000, 5),
+            (14, 0x7000, 3),
+        ];
+        for (n, &(name, offset, length)) in fst.iter().enumerate() {
+            let entry = &mut image[0x3000 + n * 0xC..];
+            BigEndian::write_u32(entry, name);
+            BigEndian::write_u32(&mut entry[4..], offset);
+            BigEndian::write_u32(&mut entry[8..], length);
+        }
+        image[0x3030..0x3050].copy_from_slice(b"data\0file.bin\0opening.bnr\0\0\0\0\0\0\0");
+
+        // DOL with a text section of 0x20 bytes
+        BigEndian::write_u32(&mut image[0x4000..], 0x100);
+        BigEndian::write_u32(&mut image[0x4090..], 0x20);
+
+        image[0x6000..0x6005].copy_from_slice(b"hello");
+        image[0x7000..0x7003].copy_from_slice(b"bnr");
+
+        image
+    }
+
+    #[test]
+    fn browse() {
+        let path = std::env::temp_dir().join(format!("rustcube-disc-{}.iso", std::process::id()));
+        fs::write(&path, test_image()).unwrap();
+
+        let mut disc = Disc::open(&path).unwrap();
+        assert_eq!(disc.game_id(), "GTSE01");
+        assert_eq!(disc.header().game_name, "Test");
+        assert_eq!(disc.header().dol_offset, 0x4000);
+        assert_eq!(disc.bi2().region(), Some(Region::NtscU));
+        assert_eq!(disc.apploader().date, "2004/02/03");
+        assert_eq!(disc.apploader().size, 0x100);
+        assert_eq!(disc.read_dol().unwrap().len(), 0x120);
+
+        let names: Vec<&str> = disc.list("/").unwrap().iter().map(FstEntry::name).collect();
+        assert_eq!(names, ["data", "opening.bnr"]);
+        assert_eq!(disc.list("data").unwrap()[0].name(), "file.bin");
+        assert_eq!(
+            disc.list("opening.bnr").unwrap_err().kind(),
+            ErrorKind::NotADirectory
+        );
+        assert_eq!(
+            disc.open_file("missing").err().unwrap().kind(),
+            ErrorKind::NotFound
+        );
+
+        let mut file = disc.open_file("/data/file.bin").unwrap();
+        assert_eq!(file.len(), 5);
+        let mut data = String::new();
+        file.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "hello");
+
+        file.seek(SeekFrom::End(-2)).unwrap();
+        let mut data = String::new();
+        file.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "lo");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn invalid_fst() {
+        let path = std::env::temp_dir().join(format!("rustcube-fst-{}.iso", std::process::id()));
+
+        // no FST, the disc still opens for booting
+        let mut image = test_image();
+        BigEndian::write_u32(&mut image[0x428..], 0);
+        fs::write(&path, &image).unwrap();
+
+        let mut disc = Disc::open(&path).unwrap();
+        assert_eq!(disc.apploader().size, 0x100);
+        assert_eq!(disc.list("/").unwrap_err().kind(), ErrorKind::InvalidData);
+
+        // FST size past the end of the image
+        BigEndian::write_u32(&mut image[0x428..], 0xFFFF_FFF0);
+        fs::write(&path, &image).unwrap();
+
+        let mut disc = Disc::open(&path).unwrap();
+        assert_eq!(disc.list("/").unwrap_err().kind(), ErrorKind::InvalidData);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+use std::io::{Error, ErrorKind};
+
+use byteorder::{BigEndian, ByteOrder};
+use encoding_rs::Encoding;
+
+const ENTRY_SIZE: usize = 0xC;
+
+const ENTRY_FLAGS: usize = 0x0;
+const ENTRY_NAME_OFFSET: usize = 0x0;
+const ENTRY_OFFSET: usize = 0x4;
+const ENTRY_LENGTH: usize = 0x8;
+
+/// Entry of the disc filesystem
+#[derive(Clone, Debug, PartialEq)]
+pub enum FstEntry {
+    File {
+        name: String,
+        /// Offset on the disc
+        offset: u32,
+        size: u32,
+    },
+    Directory {
+        name: String,
+        entries: Vec<FstEntry>,
+    },
+}
+
+impl FstEntry {
+    pub fn name(&self) -> &str {
+        match self {
+            FstEntry::File { name, .. } | FstEntry::Directory { name, .. } => name,
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        matches!(self, FstEntry::Directory { .. })
+    }
+}
+
+/// File system table, the directory tree of the files on a disc
+#[derive(Debug, Default)]
+pub struct Fst {
+    root: Vec<FstEntry>,
+}
+
+impl Fst {
+    /// Entries are followed by the name table, names are decoded with `encoding`
+    pub fn parse(data: &[u8], encoding: &'static Encoding) -> Result<Fst, Error> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, format!("FST: {msg}"));
+
+        if data.len() < ENTRY_SIZE {
+            return Err(invalid("too small"));
+        }
+
+        // the root directory's length is the number of entries
+        let count = BigEndian::read_u32(&data[ENTRY_LENGTH..]) as usize;
+        if count == 0 || count > data.len() / ENTRY_SIZE {
+            return Err(invalid("invalid number of entries"));
+        }
+
+        let parser = Parser {
+            entries: &data[..count * ENTRY_SIZE],
+            names: &data[count * ENTRY_SIZE..],
+            encoding,
+        };
+
+        let root = parser.directory(0, count).map_err(|msg| invalid(&msg))?;
+
+        Ok(Fst { root })
+    }
+
+    pub fn root(&self) -> &[FstEntry] {
+        &self.root
+    }
+
+    /// Entry at a `/` separated path, names are compared case insensitively as the SDK does
+    pub fn find(&self, path: &str) -> Option<&FstEntry> {
+        let mut entries: &[FstEntry] = &self.root;
+        let mut found = None;
+
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            let entry = entries
+                .iter()
+                .find(|entry| entry.name().eq_ignore_ascii_case(name))?;
+
+            entries = match entry {
+                FstEntry::Directory { entries, .. } => entries,
+                FstEntry::File { .. } => &[],
+            };
+            found = Some(entry);
+        }
+
+        found
+    }
+}
+
+struct Parser<'a> {
+    entries: &'a [u8],
+    names: &'a [u8],
+    encoding: &'static Encoding,
+}
+
+impl Parser<'_> {
+    /// Entries of the directory at `index`, up to the entry at `end`
+    fn directory(&self, index: usize, end: usize) -> Result<Vec<FstEntry>, String> {
+        let mut entries = Vec::new();
+        let mut i = index + 1;
+
+        while i < end {
+            let entry = &self.entries[i * ENTRY_SIZE..];
+            let name = self.name(BigEndian::read_u32(&entry[ENTRY_NAME_OFFSET..]) & 0xFF_FFFF)?;
+            let offset = BigEndian::read_u32(&entry[ENTRY_OFFSET..]);
+            let length = BigEndian::read_u32(&entry[ENTRY_LENGTH..]);
+
+            if entry[ENTRY_FLAGS] & 1 != 0 {
+                // the length of a directory is the index of the entry following it
+                let next = length as usize;
+                if next <= i || next > end {
+                    return Err(format!("invalid directory `{name}`"));
+                }
+
+                entries.push(FstEntry::Directory {
+                    name,
+                    entries: self.directory(i, next)?,
+                });
+                i = next;
+            } else {
+                entries.push(FstEntry::File {
+                    name,
+                    offset,
+                    size: length,
+                });
+                i += 1;
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn name(&self, offset: u32) -> Result<String, String> {
+        let name = self
+            .names
+            .get(offset as usize..)
+            .ok_or_else(|| format!("invalid name offset {offset:#x}"))?;
+        let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+
+        let (name, _, _) = self.encoding.decode(&name[..len]);
+        Ok(name.into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::WINDOWS_1252;
+
+    use super::*;
+
+    fn entry(dir: bool, name_offset: u32, offset: u32, length: u32) -> Vec<u8> {
+        let mut entry = vec![0; ENTRY_SIZE];
+        BigEndian::write_u32(&mut entry[ENTRY_NAME_OFFSET..], name_offset);
+        entry[ENTRY_FLAGS] = dir as u8;
+        BigEndian::write_u32(&mut entry[ENTRY_OFFSET..], offset);
+        BigEndian::write_u32(&mut entry[ENTRY_LENGTH..], length);
+        entry
+    }
+
+    #[test]
+    fn parse() {
+        let data = [
+            entry(true, 0, 0, 5),
+            entry(true, 0, 0, 4),
+            entry(false, 5, 0x8000, 0x10),
+            entry(false, 14, 0x9000, 0x20),
+            entry(false, 24, 0xA000, 0x30),
+            b"data\0file.bin\0other.bin\0opening.bnr\0".to_vec(),
+        ]
+        .concat();
+
+        let fst = Fst::parse(&data, WINDOWS_1252).unwrap();
+
+        let names: Vec<&str> = fst.root().iter().map(FstEntry::name).collect();
+        assert_eq!(names, ["data", "opening.bnr"]);
+
+        assert_eq!(
+            fst.find("/DATA/Other.bin"),
+            Some(&FstEntry::File {
+                name: "other.bin".to_string(),
+                offset: 0x9000,
+                size: 0x20
+            })
+        );
+        assert!(fst.find("data").unwrap().is_dir());
+        assert_eq!(fst.find("data/missing.bin"), None);
+        assert_eq!(fst.find("opening.bnr/file.bin"), None);
+    }
+
+    #[test]
+    fn invalid() {
+        // directory ending before itself
+        let data = [entry(true, 0, 0, 2), entry(true, 0, 0, 1), b"\0".to_vec()].concat();
+        assert!(Fst::parse(&data, WINDOWS_1252).is_err());
+
+        // more entries than data
+        let data = entry(true, 0, 0, 3);
+        assert!(Fst::parse(&data, WINDOWS_1252).is_err());
+    }
+}
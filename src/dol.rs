@@ -11,6 +11,20 @@ use crate::{bus::Bus, cpu::Cpu};
 const NUM_TEXT: usize = 7;
 const NUM_DATA: usize = 11;
 
+/// Size of the DOL header
+pub const HEADER_SIZE: usize = 0x100;
+
+/// Size of a DOL file from its header, the end of its last section
+pub fn file_size(header: &[u8]) -> u32 {
+    (0..NUM_TEXT + NUM_DATA)
+        .map(|x| {
+            let offset = BigEndian::read_u32(&header[x * 4..]);
+            let size = BigEndian::read_u32(&header[0x90 + x * 4..]);
+            offset.saturating_add(size)
+        })
+        .fold(HEADER_SIZE as u32, u32::max)
+}
+
 #[derive(Default)]
 struct Header {
     //text_offset: [u32; NUM_TEXT],
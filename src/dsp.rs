@@ -1,12 +1,13 @@
 mod cpu;
 
-use std::fs;
+use std::{fs, path::Path};
 
 use byteorder::{BigEndian, ReadBytesExt};
 
 use self::cpu::{dsp_step, DspCpu, INTERRUPT_RESET};
 use crate::{
     bus::Bus,
+    config::DspMode,
     cpu::CpuState,
     hw::{
         mmio::{Mmio, MmioDevice},
@@ -52,6 +53,7 @@ pub struct DspInterface {
     aidmabl: u16,
     aidmabr: u16,
     cpu_ticks: u64,
+    mode: DspMode,
     ctx: DspContext,
 }
 
@@ -61,9 +63,7 @@ impl Default for DspInterface {
 
         control_register.set_halt(true);
 
-        let mut ctx = DspContext::default();
-
-        ctx.load_roms();
+        let ctx = DspContext::default();
 
         DspInterface {
             control_register,
@@ -77,6 +77,7 @@ impl Default for DspInterface {
             aidmabl: 0,
             aidmabr: 0,
             cpu_ticks: 0,
+            mode: Default::default(),
             ctx,
         }
     }
@@ -328,7 +329,39 @@ impl DspInterface {
         }
     }
 
+    /// Load the DSP instruction ROM and coefficient ROM
+    pub fn load_roms(&mut self, irom: &Path, drom: &Path) {
+        self.ctx.load_roms(irom, drom);
+    }
+
+    /// Reset the registers and halt the DSP, keeping the ROMs and ARAM contents
+    pub fn reset(&mut self) {
+        let mut control_register = ControlRegister(0);
+        control_register.set_halt(true);
+
+        self.control_register = control_register;
+        self.aram_conf = Default::default();
+        self.aram_state = 1;
+        self.aram_refresh = AramControlTestRegister(156);
+        self.aram_mma_addr = 0;
+        self.aram_ar_addr = 0;
+        self.aram_dma_size = 0;
+        self.aidma = 0;
+        self.aidmabl = 0;
+        self.aidmabr = 0;
+        self.cpu_ticks = 0;
+        self.ctx.hardware_reset();
+    }
+
+    pub fn set_mode(&mut self, mode: DspMode) {
+        self.mode = mode;
+    }
+
     pub fn update(bus: &mut Bus, cpu_state: &mut CpuState) {
+        if bus.dsp.mode == DspMode::Off {
+            return;
+        }
+
         let ticks = cpu_state.timers.get_ticks();
         if ticks - bus.dsp.cpu_ticks > TIMER_RATIO {
             bus.dsp.cpu_ticks = ticks;
@@ -471,14 +504,24 @@ impl Default for DspContext {
 }
 
 impl DspContext {
-    fn load_roms(&mut self) {
-        let irom_filename = "dsp_rom.bin";
-        let drom_filename = "dsp_coef.bin";
+    /// Reset through the system reset, as opposed to a reset of the DSP core by software
+    fn hardware_reset(&mut self) {
+        self.cpu = Default::default();
+        self.iram.fill(0);
+        self.dram.fill(0);
+        self.dsma = 0;
+        self.dspa = 0;
+        self.dsbl = 0;
+        self.dscr = 0;
+        self.cdmb = 0;
+        self.dcmb = 0;
+    }
 
+    fn load_roms(&mut self, irom_filename: &Path, drom_filename: &Path) {
         let mut irom_file = match fs::File::open(irom_filename) {
             Ok(v) => v,
             Err(e) => {
-                warn!("Unable to open file {}\n{}", irom_filename, e);
+                warn!("Unable to open file {}\n{}", irom_filename.display(), e);
                 return;
             }
         };
@@ -486,7 +529,7 @@ impl DspContext {
         let mut drom_file = match fs::File::open(drom_filename) {
             Ok(v) => v,
             Err(e) => {
-                warn!("Unable to open file {}\n{}", drom_filename, e);
+                warn!("Unable to open file {}\n{}", drom_filename.display(), e);
                 return;
             }
         };
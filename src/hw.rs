@@ -4,6 +4,7 @@ pub(crate) mod di;
 pub(crate) mod exi;
 pub(crate) mod gp_fifo;
 pub(crate) mod memory;
+pub(crate) mod mi;
 pub(crate) mod mmio;
 pub(crate) mod pe;
 pub(crate) mod pi;
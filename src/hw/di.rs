@@ -165,6 +165,13 @@ impl DvdInterface {
         self.disc = disc;
     }
 
+    /// Reset the registers, keeping the inserted disc
+    pub fn reset(&mut self) {
+        let disc = self.disc.take();
+        *self = Default::default();
+        self.set_disc(disc);
+    }
+
     pub fn reset_drive(&mut self, _spinup: bool) {
         debug!("DI reset drive");
     }
@@ -1,9 +1,35 @@
-use std::{cell::RefCell, rc::Rc};
+mod bba;
+mod memcard;
+mod sd_gecko;
+mod sram;
+mod usb_gecko;
+
+use std::{
+    cell::RefCell,
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
-use crate::hw::{
-    bootrom::IPL_MEM_SIZE,
-    memory::Memory,
-    mmio::{Mmio, MmioDevice},
+#[cfg(target_os = "linux")]
+pub use self::bba::TapBackend;
+pub use self::bba::{DeviceBba, PacketBackend, UdpBackend};
+pub use self::memcard::DeviceMemoryCard;
+pub use self::sd_gecko::DeviceSdGecko;
+pub use self::sram::Sram;
+use self::sram::SRAM_SIZE;
+pub use self::usb_gecko::{DeviceUsbGecko, USB_GECKO_PORT};
+use crate::{
+    bus::Bus,
+    cpu::{timers::CPU_CLOCK, CpuState},
+    hw::{
+        bootrom::IPL_MEM_SIZE,
+        memory::Memory,
+        mmio::{Mmio, MmioDevice},
+        pi::{ProcessorInterface, PI_INTERRUPT_EXI},
+    },
+    memcard::CONSOLE_EPOCH,
 };
 
 const EXI_STATUS: u32 = 0x00;
@@ -13,10 +39,15 @@ const EXI_DMA_CONTROL: u32 = 0x0C;
 const EXI_IMM_DATA: u32 = 0x10;
 const NUM_CHANNELS: usize = 3;
 const NUM_DEVICES: usize = 3;
+/// Device index of the serial port 1 (channel 0, device 2)
+const SERIAL_PORT_1: usize = 2;
+
+/// Minimum number of cpu ticks between device updates
+const UPDATE_TICKS: u64 = 100;
 
 const TRANSFER_TYPE_READ: u32 = 0;
 const TRANSFER_TYPE_WRITE: u32 = 1;
-//const TRANSFER_TYPE_RW: u32 = 2;
+const TRANSFER_TYPE_READ_WRITE: u32 = 2;
 
 const AD16_ID: u32 = 0x04120000;
 
@@ -30,7 +61,22 @@ pub struct ExternalInterface {
     dma_address: [u32; NUM_CHANNELS],
     dma_length: [u32; NUM_CHANNELS],
     imm_data: [u32; NUM_CHANNELS],
+    /// Cpu ticks until the transfer in progress completes
+    transfer_ticks: [u64; NUM_CHANNELS],
     devices: [Option<Box<dyn Device>>; NUM_CHANNELS * NUM_DEVICES],
+    /// Shared with Bootrom and the IPL device
+    ipl_mem: Rc<RefCell<Vec<u8>>>,
+    cpu_ticks: u64,
+    /// RTC seconds since 2000-01-01 when last set
+    rtc_base: u32,
+    /// Cpu ticks since the RTC was last set
+    rtc_ticks: u64,
+    /// RTC value last stored in IPL memory, a different value there was written by software
+    rtc: u32,
+    /// File SRAM is persisted to
+    sram_path: Option<PathBuf>,
+    /// SRAM contents last written to `sram_path`
+    sram: Option<Sram>,
 }
 
 impl ExternalInterface {
@@ -41,7 +87,15 @@ impl ExternalInterface {
             dma_address: Default::default(),
             dma_length: Default::default(),
             imm_data: Default::default(),
+            transfer_ticks: Default::default(),
             devices: Default::default(),
+            ipl_mem: bootrom.clone(),
+            cpu_ticks: 0,
+            rtc_base: 0,
+            rtc_ticks: 0,
+            rtc: 0,
+            sram_path: None,
+            sram: None,
         };
 
         let device_ad16 = DeviceAd16::default();
@@ -52,6 +106,189 @@ impl ExternalInterface {
         exi
     }
 
+    /// Reset the channels, deselecting their devices. Devices, SRAM and the RTC are kept.
+    pub fn reset(&mut self) {
+        for c in 0..NUM_CHANNELS {
+            if let Some(device) = self.status[c]
+                .selected_device()
+                .and_then(|d| self.devices[c * NUM_CHANNELS + d].as_mut())
+            {
+                device.device_deselect();
+            }
+        }
+
+        self.status = Default::default();
+        self.control = Default::default();
+        self.dma_address = Default::default();
+        self.dma_length = Default::default();
+        self.imm_data = Default::default();
+        self.transfer_ticks = Default::default();
+    }
+
+    pub fn sram(&self) -> Sram {
+        Sram::from_bytes(&self.ipl_mem.borrow()[IPL_SRAM_BASE + RTC_SIZE..])
+    }
+
+    pub fn set_sram(&mut self, sram: &Sram) {
+        self.ipl_mem.borrow_mut()[IPL_SRAM_BASE + RTC_SIZE..IPL_SRAM_BASE + RTC_SIZE + SRAM_SIZE]
+            .copy_from_slice(sram.as_bytes());
+    }
+
+    /// Set the RTC to `time` in seconds since the unix epoch, it advances with emulated time
+    pub fn set_rtc(&mut self, time: u64) {
+        self.rtc_base = time.saturating_sub(CONSOLE_EPOCH) as u32;
+        self.rtc_ticks = 0;
+        self.store_rtc();
+    }
+
+    /// Load SRAM from a file and write it back there whenever it changes. The file holds the
+    /// RTC followed by SRAM, as the SRAM.raw files of other emulators do, the RTC is ignored.
+    pub fn load_sram<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+
+        if path.exists() {
+            self.set_sram(&Sram::load(path)?);
+
+            info!("Loaded SRAM {}", path.display());
+        }
+
+        self.sram_path = Some(path.to_path_buf());
+        self.save_sram();
+
+        Ok(())
+    }
+
+    /// Stop persisting SRAM and go back to the power on contents
+    pub fn reset_sram(&mut self) {
+        self.sram_path = None;
+        self.sram = None;
+        self.set_sram(&Sram::default());
+    }
+
+    /// Write SRAM to its file if it changed
+    fn save_sram(&mut self) {
+        let Some(path) = self.sram_path.as_ref() else {
+            return;
+        };
+
+        let sram = self.sram();
+
+        if self.sram.as_ref() == Some(&sram) {
+            return;
+        }
+
+        let mut data = self.rtc.to_be_bytes().to_vec();
+        data.extend_from_slice(sram.as_bytes());
+
+        if let Err(e) = fs::write(path, data) {
+            error!("Unable to write SRAM {}: {}", path.display(), e);
+        }
+
+        self.sram = Some(sram);
+    }
+
+    /// Advance the RTC, a value written by software becomes the new base
+    fn update_rtc(&mut self, ticks: u64) {
+        let mem = self.ipl_mem.borrow();
+        let written = u32::from_be_bytes(
+            mem[IPL_SRAM_BASE..IPL_SRAM_BASE + RTC_SIZE]
+                .try_into()
+                .unwrap(),
+        );
+        drop(mem);
+
+        if written != self.rtc {
+            self.rtc_base = written;
+            self.rtc_ticks = 0;
+        }
+
+        self.rtc_ticks += ticks;
+        self.store_rtc();
+    }
+
+    fn store_rtc(&mut self) {
+        self.rtc = self
+            .rtc_base
+            .wrapping_add((self.rtc_ticks / CPU_CLOCK) as u32);
+
+        self.ipl_mem.borrow_mut()[IPL_SRAM_BASE..IPL_SRAM_BASE + RTC_SIZE]
+            .copy_from_slice(&self.rtc.to_be_bytes());
+    }
+
+    /// Insert a device into slot A (0) or B (1), or remove it. Insertion and removal raise the
+    /// EXT interrupt of the slot's channel.
+    pub fn insert_device(&mut self, slot: usize, device: Option<Box<dyn Device>>) {
+        let was_connected = self.devices[slot * NUM_CHANNELS].is_some();
+
+        self.devices[slot * NUM_CHANNELS] = device;
+
+        if self.devices[slot * NUM_CHANNELS].is_some() != was_connected {
+            self.status[slot].set_ext_interrupt(true);
+        }
+    }
+
+    /// Attach a device to the serial port 1 (EXI channel 0, device 2), or remove it
+    pub fn insert_serial_port_1(&mut self, device: Option<Box<dyn Device>>) {
+        self.devices[SERIAL_PORT_1] = device;
+    }
+
+    /// Advance device timing and raise device interrupts
+    pub fn update(bus: &mut Bus, cpu_state: &mut CpuState) {
+        let ticks = cpu_state.timers.get_ticks();
+        let elapsed = ticks - bus.exi.cpu_ticks;
+        if elapsed < UPDATE_TICKS {
+            return;
+        }
+        bus.exi.cpu_ticks = ticks;
+
+        for device in bus.exi.devices.iter_mut().flatten() {
+            device.update(elapsed);
+        }
+
+        bus.exi.update_rtc(elapsed);
+        bus.exi.save_sram();
+
+        for c in 0..NUM_CHANNELS {
+            if bus.exi.transfer_ticks[c] == 0 {
+                continue;
+            }
+
+            bus.exi.transfer_ticks[c] = bus.exi.transfer_ticks[c].saturating_sub(elapsed);
+
+            if bus.exi.transfer_ticks[c] == 0 {
+                bus.exi.control[c].set_transfer_start(false);
+                bus.exi.status[c].set_tc_interrupt(true);
+            }
+        }
+
+        // the first device of channels 0 and 1 is wired to the EXI interrupt of its channel,
+        // device 2 of channel 0 (the serial port 1) to the EXI interrupt of channel 2
+        for (c, index) in [(0, 0), (1, NUM_CHANNELS), (2, SERIAL_PORT_1)] {
+            if bus.exi.devices[index]
+                .as_ref()
+                .is_some_and(|device| device.interrupt())
+            {
+                bus.exi.status[c].set_exi_interrupt_status(true);
+            }
+        }
+
+        Self::update_interrupts(bus, cpu_state);
+    }
+
+    pub fn update_interrupts(bus: &mut Bus, cpu_state: &mut CpuState) {
+        let pending = bus.exi.status.iter().any(|status| {
+            (status.exi_interrupt_status() && status.exi_interrupt_mask())
+                || (status.tc_interrupt() && status.tc_interrupt_mask())
+                || (status.ext_interrupt() && status.ext_interrupt_mask())
+        });
+
+        if pending {
+            ProcessorInterface::set_interrupt(bus, cpu_state, PI_INTERRUPT_EXI);
+        } else {
+            ProcessorInterface::clear_interrupt(bus, cpu_state, PI_INTERRUPT_EXI);
+        }
+    }
+
     fn get_channel(addr: u32) -> usize {
         ((addr - Self::BASE_ADDR) / 0x14) as usize
     }
@@ -66,10 +303,16 @@ impl MmioDevice for ExternalInterface {
                 Self::BASE_ADDR + channel * 0x14 + EXI_STATUS,
                 |bus, _, addr| {
                     let c = ExternalInterface::get_channel(addr);
+                    let mut status = bus.exi.status[c];
+
+                    // memory card slots report whether a card is inserted
+                    if c < 2 {
+                        status.set_device_connected(bus.exi.devices[c * NUM_CHANNELS].is_some());
+                    }
 
-                    bus.exi.status[c].into()
+                    status.into()
                 },
-                |bus, _, addr, val| {
+                |bus, cpu_state, addr, val| {
                     let c = ExternalInterface::get_channel(addr);
                     let mut status = bus.exi.status[c];
                     let new_status = StatusRegister(val);
@@ -78,19 +321,50 @@ impl MmioDevice for ExternalInterface {
                     status.set_tc_interrupt_mask(new_status.tc_interrupt_mask());
                     status.set_clock_frequency(new_status.clock_frequency());
 
+                    // write 1 to clear
+                    if new_status.exi_interrupt_status() {
+                        status.set_exi_interrupt_status(false);
+                    }
+                    if new_status.tc_interrupt() {
+                        status.set_tc_interrupt(false);
+                    }
+
+                    // only the memory card slots detect insertion
+                    if c < 2 {
+                        status.set_ext_interrupt_mask(new_status.ext_interrupt_mask());
+
+                        if new_status.ext_interrupt() {
+                            status.set_ext_interrupt(false);
+                        }
+                    }
+
                     if c == 0 && !status.rom_descramble() {
                         status.set_rom_descramble(new_status.rom_descramble());
                     }
 
+                    let old_device = status.selected_device();
+
                     status.set_device_select(new_status.device_select());
 
-                    let device_index = c * NUM_CHANNELS + status.get_selected_device() as usize;
+                    let new_device = status.selected_device();
 
                     bus.exi.status[c] = status;
 
-                    if let Some(device) = bus.exi.devices[device_index].as_mut() {
-                        device.device_select();
+                    if old_device != new_device {
+                        if let Some(device) =
+                            old_device.and_then(|d| bus.exi.devices[c * NUM_CHANNELS + d].as_mut())
+                        {
+                            device.device_deselect();
+                        }
+
+                        if let Some(device) =
+                            new_device.and_then(|d| bus.exi.devices[c * NUM_CHANNELS + d].as_mut())
+                        {
+                            device.device_select();
+                        }
                     }
+
+                    ExternalInterface::update_interrupts(bus, cpu_state);
                 },
             );
             mmio.register_u32(
@@ -129,12 +403,18 @@ impl MmioDevice for ExternalInterface {
                 |bus, _, addr, val| {
                     let c = ExternalInterface::get_channel(addr);
 
-                    let mut control = ControlRegister(val);
+                    let control = ControlRegister(val);
 
                     if control.transfer_start() {
                         let device_index =
                             c * NUM_CHANNELS + bus.exi.status[c].get_selected_device() as usize;
 
+                        let length = if control.transfer_mode() {
+                            bus.exi.dma_length[c]
+                        } else {
+                            control.transfer_len() + 1
+                        };
+
                         match bus.exi.devices[device_index].as_mut() {
                             Some(device) => {
                                 if control.transfer_mode() {
@@ -155,6 +435,11 @@ impl MmioDevice for ExternalInterface {
                                         bus.exi.imm_data[c] = device.imm_read(transfer_len as u8);
                                     } else if control.transfer_type() == TRANSFER_TYPE_WRITE {
                                         device.imm_write(bus.exi.imm_data[c], transfer_len as u8);
+                                    } else if control.transfer_type() == TRANSFER_TYPE_READ_WRITE {
+                                        bus.exi.imm_data[c] = device.imm_read_write(
+                                            bus.exi.imm_data[c],
+                                            transfer_len as u8,
+                                        );
                                     }
                                 }
                             }
@@ -165,7 +450,10 @@ impl MmioDevice for ExternalInterface {
                             ),
                         }
 
-                        control.set_transfer_start(false);
+                        // data moves immediately, transfer start stays set and the transfer
+                        // complete interrupt is raised once the transfer would have finished
+                        bus.exi.transfer_ticks[c] =
+                            transfer_ticks(length, bus.exi.status[c].clock_frequency());
                     }
 
                     bus.exi.control[c] = control;
@@ -193,14 +481,14 @@ bitfield! {
     pub struct StatusRegister(u32);
     impl Debug;
     pub exi_interrupt_mask, set_exi_interrupt_mask : 0;
-    pub exi_interrupt_status, _ : 1;
+    pub exi_interrupt_status, set_exi_interrupt_status : 1;
     pub tc_interrupt_mask, set_tc_interrupt_mask : 2;
-    pub tc_interrupt, _ : 3;
+    pub tc_interrupt, set_tc_interrupt : 3;
     pub clock_frequency, set_clock_frequency : 6, 4;
     pub device_select, set_device_select : 9, 7;
-    pub ext_interrupt_mask, _ : 10;
-    pub ext_insertion_interrupt_status, _ : 11;
-    pub device_connected, _ : 12;
+    pub ext_interrupt_mask, set_ext_interrupt_mask : 10;
+    pub ext_interrupt, set_ext_interrupt : 11;
+    pub device_connected, set_device_connected : 12;
     pub rom_descramble, set_rom_descramble : 13;
 }
 
@@ -217,6 +505,15 @@ impl From<StatusRegister> for u32 {
 }
 
 impl StatusRegister {
+    fn selected_device(&self) -> Option<usize> {
+        match self.device_select() {
+            1 => Some(0),
+            2 => Some(1),
+            4 => Some(2),
+            _ => None,
+        }
+    }
+
     fn get_selected_device(&self) -> u8 {
         match self.device_select() {
             1 => 0,
@@ -227,6 +524,14 @@ impl StatusRegister {
     }
 }
 
+/// Cpu ticks to transfer `length` bytes at the clock selected in the status register, 1MHz
+/// doubling up to 32MHz
+fn transfer_ticks(length: u32, clock_frequency: u32) -> u64 {
+    let frequency = 1_000_000 << clock_frequency.min(5);
+
+    (u64::from(length) * 8 * CPU_CLOCK / frequency).max(1)
+}
+
 bitfield! {
     #[derive(Copy, Clone, Default)]
     pub struct ControlRegister(u32);
@@ -246,6 +551,16 @@ impl From<ControlRegister> for u32 {
 pub trait Device {
     fn device_select(&mut self);
 
+    fn device_deselect(&mut self) {}
+
+    /// Called regularly with the number of cpu ticks elapsed
+    fn update(&mut self, _ticks: u64) {}
+
+    /// State of the device interrupt line
+    fn interrupt(&self) -> bool {
+        false
+    }
+
     fn transfer_byte(&mut self, _byte: &mut u8) {}
 
     fn imm_read(&mut self, mut len: u8) -> u32 {
@@ -272,6 +587,20 @@ pub trait Device {
         }
     }
 
+    /// Bytes written are replaced with the bytes the device returns
+    fn imm_read_write(&mut self, mut value: u32, len: u8) -> u32 {
+        let mut result: u32 = 0;
+
+        for position in 0..len as u32 {
+            let mut byte = (value >> 24) as u8;
+            self.transfer_byte(&mut byte);
+            result |= (byte as u32) << (24 - (position * 8));
+            value <<= 8;
+        }
+
+        result
+    }
+
     fn dma_read(&mut self, mem: &mut Memory, mut address: u32, mut len: u32) {
         while len > 0 {
             len -= 1;
@@ -305,6 +634,7 @@ impl Device for DeviceAd16 {
         self.command = 0;
     }
 
+    #[allow(clippy::collapsible_match)]
     fn transfer_byte(&mut self, byte: &mut u8) {
         if self.position == 0 {
             self.command = *byte;
@@ -359,6 +689,9 @@ impl Device for DeviceAd16 {
 const IPL_SRAM_BASE: usize = 0x80_0000;
 const IPL_UART_BASE: usize = 0x80_0400;
 
+/// The RTC precedes SRAM
+const RTC_SIZE: usize = 4;
+
 pub struct DeviceIpl {
     position: u32,
     /// Decoded device address: `(raw >> 6) & 0x1ffffff`
@@ -379,33 +712,8 @@ impl DeviceIpl {
             let mut data = mem.borrow_mut();
             // RTC (first 4 bytes of SRAM region)
             data[IPL_SRAM_BASE..IPL_SRAM_BASE + 4].copy_from_slice(&[0x38, 0x62, 0x43, 0x80]);
-            // SRAM settings / flash id (Dolphin SRAM_SIZE 0x44 total with RTC)
-            let sram = [
-                0xFF, 0x6B, // checksum 1
-                0x00, 0x91, // checksum 2
-                0x00, 0x00, 0x00, 0x00, // ead 0
-                0x00, 0x00, 0x00, 0x00, // ead 1
-                0x00, 0x00, 0x00, 0x00, // counter bias
-                0x00, // display offset h
-                0x00, // ntd
-                0x00, // language
-                0x2C, // flags
-                0x44, 0x4F, 0x4C, 0x50, 0x48, 0x49, 0x4E, 0x53, 0x4C, 0x4F, 0x54,
-                0x41, // flash id
-                0x44, 0x4F, 0x4C, 0x50, 0x48, 0x49, 0x4E, 0x53, 0x4C, 0x4F, 0x54,
-                0x42, // flash id
-                0x00, 0x00, 0x00, 0x00, // wireless keyboard id
-                0x00, 0x00, // wireless pad id
-                0x00, 0x00, // wireless pad id
-                0x00, 0x00, // wireless pad id
-                0x00, 0x00, // wireless pad id
-                0x00, // last dvd error code
-                0x00, // padding
-                0x6E, 0x6D, // flash id checksum
-                0x00, 0x00, // flash id checksum
-                0x00, 0x00, // padding
-            ];
-            data[IPL_SRAM_BASE + 4..IPL_SRAM_BASE + 4 + sram.len()].copy_from_slice(&sram);
+            data[IPL_SRAM_BASE + RTC_SIZE..IPL_SRAM_BASE + RTC_SIZE + SRAM_SIZE]
+                .copy_from_slice(Sram::default().as_bytes());
         }
 
         DeviceIpl {
@@ -478,3 +786,125 @@ impl Device for DeviceIpl {
         self.position += 1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Language;
+
+    const CHANNEL_2: u32 = ExternalInterface::BASE_ADDR + 2 * 0x14;
+    const PI_INTERRUPT_CAUSE: u32 = 0x0C00_3000;
+
+    #[test]
+    fn transfer_complete_interrupt() {
+        let mut bus = Bus::default();
+        let mut cpu_state = CpuState::default();
+
+        // select the AD16 at 32MHz with the transfer complete interrupt enabled
+        bus.write::<u32>(
+            &mut cpu_state,
+            CHANNEL_2 + EXI_STATUS,
+            1 << 7 | 5 << 4 | 1 << 2,
+        );
+
+        // immediate write of 4 bytes takes 486 cpu ticks
+        bus.write::<u32>(
+            &mut cpu_state,
+            CHANNEL_2 + EXI_DMA_CONTROL,
+            3 << 4 | 1 << 2 | 1,
+        );
+
+        cpu_state.timers.tick(400);
+        ExternalInterface::update(&mut bus, &mut cpu_state);
+        assert_eq!(
+            bus.read::<u32>(&mut cpu_state, CHANNEL_2 + EXI_DMA_CONTROL) & 1,
+            1
+        );
+
+        cpu_state.timers.tick(100);
+        ExternalInterface::update(&mut bus, &mut cpu_state);
+        assert_eq!(
+            bus.read::<u32>(&mut cpu_state, CHANNEL_2 + EXI_DMA_CONTROL) & 1,
+            0
+        );
+
+        let status = bus.read::<u32>(&mut cpu_state, CHANNEL_2 + EXI_STATUS);
+        assert!(StatusRegister(status).tc_interrupt());
+        assert_ne!(
+            bus.read::<u32>(&mut cpu_state, PI_INTERRUPT_CAUSE) & PI_INTERRUPT_EXI,
+            0
+        );
+
+        // write 1 to clear
+        bus.write::<u32>(&mut cpu_state, CHANNEL_2 + EXI_STATUS, status);
+        assert!(
+            !StatusRegister(bus.read::<u32>(&mut cpu_state, CHANNEL_2 + EXI_STATUS)).tc_interrupt()
+        );
+        assert_eq!(
+            bus.read::<u32>(&mut cpu_state, PI_INTERRUPT_CAUSE) & PI_INTERRUPT_EXI,
+            0
+        );
+    }
+
+    #[test]
+    fn ext_interrupt() {
+        let mut bus = Bus::default();
+        let mut cpu_state = CpuState::default();
+
+        bus.write::<u32>(
+            &mut cpu_state,
+            ExternalInterface::BASE_ADDR + EXI_STATUS,
+            1 << 10,
+        );
+
+        let card = DeviceMemoryCard::new(vec![0xFF; 0x8_0000]);
+        bus.exi.insert_device(0, Some(Box::new(card)));
+        ExternalInterface::update_interrupts(&mut bus, &mut cpu_state);
+
+        let status = StatusRegister(bus.read::<u32>(&mut cpu_state, ExternalInterface::BASE_ADDR));
+        assert!(status.ext_interrupt() && status.device_connected());
+        assert_ne!(
+            bus.read::<u32>(&mut cpu_state, PI_INTERRUPT_CAUSE) & PI_INTERRUPT_EXI,
+            0
+        );
+    }
+
+    #[test]
+    fn rtc_and_sram() {
+        let path = std::env::temp_dir().join(format!("rustcube-sram-{}.raw", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mem = Rc::new(RefCell::new(vec![0; IPL_MEM_SIZE]));
+        let mut exi = ExternalInterface::new(mem.clone());
+
+        exi.load_sram(&path).unwrap();
+        exi.set_rtc(CONSOLE_EPOCH + 100);
+        exi.update_rtc(CPU_CLOCK * 2);
+        assert_eq!(
+            mem.borrow()[IPL_SRAM_BASE..IPL_SRAM_BASE + 4],
+            102u32.to_be_bytes()
+        );
+
+        // software sets the clock
+        mem.borrow_mut()[IPL_SRAM_BASE..IPL_SRAM_BASE + 4].copy_from_slice(&50u32.to_be_bytes());
+        exi.update_rtc(CPU_CLOCK);
+        assert_eq!(
+            mem.borrow()[IPL_SRAM_BASE..IPL_SRAM_BASE + 4],
+            51u32.to_be_bytes()
+        );
+
+        let mut sram = exi.sram();
+        sram.set_language(Language::Spanish);
+        exi.set_sram(&sram);
+        exi.save_sram();
+
+        let mem = Rc::new(RefCell::new(vec![0; IPL_MEM_SIZE]));
+        let mut exi = ExternalInterface::new(mem);
+        exi.load_sram(&path).unwrap();
+
+        assert_eq!(exi.sram().language(), Language::Spanish);
+        assert!(exi.sram().checksums_valid());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,519 @@
+#[cfg(target_os = "linux")]
+mod tap;
+mod udp;
+
+use std::io::Error;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+#[cfg(target_os = "linux")]
+pub use self::tap::TapBackend;
+pub use self::udp::UdpBackend;
+use super::Device;
+use crate::hw::memory::Memory;
+
+/// Sends and receives ethernet frames for the Broadband Adapter
+pub trait PacketBackend {
+    fn send(&mut self, frame: &[u8]) -> Result<(), Error>;
+
+    /// Receive a frame into `buf` without blocking, `None` when no frame is pending
+    fn receive(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error>;
+}
+
+// EXI registers, selected by 16-bit commands
+const EXI_ID: u8 = 0x00;
+const EXI_REVISION_ID: u8 = 0x01;
+const EXI_INTERRUPT_MASK: u8 = 0x02;
+const EXI_INTERRUPT: u8 = 0x03;
+const EXI_DEVICE_ID: u8 = 0x04;
+const EXI_ACSTART: u8 = 0x05;
+
+const BBA_EXI_ID: u32 = 0x0402_0200;
+/// EXI interrupt bit for interrupts of the MX chip
+const EXI_INTERRUPT_MX: u8 = 0x80;
+
+// MX chip registers, selected by 32-bit commands
+const BBA_NCRA: usize = 0x00;
+const BBA_NCRB: usize = 0x01;
+const BBA_LTPS: usize = 0x04;
+const BBA_LRPS: usize = 0x05;
+const BBA_IMR: usize = 0x08;
+const BBA_IR: usize = 0x09;
+const BBA_BP: usize = 0x0A;
+const BBA_RWP: usize = 0x16;
+const BBA_RRP: usize = 0x18;
+const BBA_RHBP: usize = 0x1A;
+const BBA_NAFR_PAR0: usize = 0x20;
+const BBA_NWAYC: usize = 0x30;
+const BBA_NWAYS: usize = 0x31;
+const BBA_MISC: usize = 0x3D;
+const BBA_TXFIFOCNT: usize = 0x3E;
+const BBA_WRTXFIFOD: usize = 0x48;
+const BBA_SI_ACTRL2: usize = 0x60;
+
+const NCRA_RESET: u8 = 0x01;
+const NCRA_ST0: u8 = 0x02;
+const NCRA_ST1: u8 = 0x04;
+const NCRA_SR: u8 = 0x08;
+
+const NCRB_PR: u8 = 0x01;
+
+const INT_R: u8 = 0x02;
+const INT_T: u8 = 0x04;
+const INT_R_ERR: u8 = 0x08;
+
+const NWAYC_ANE: u8 = 0x10;
+const NWAYC_LTE: u8 = 0x80;
+const NWAYS_LS100: u8 = 0x02;
+const NWAYS_LPNWAY: u8 = 0x04;
+const NWAYS_ANCLPT: u8 = 0x08;
+const NWAYS_100TXF: u8 = 0x10;
+
+/// Receive status of frames sent to the broadcast address
+const DESC_BF: u8 = 0x80;
+/// Receive status of frames sent to a multicast address
+const DESC_MF: u8 = 0x10;
+
+const BBA_MEM_SIZE: usize = 0x1000;
+const TX_FIFO_SIZE: usize = 0x800;
+const PAGE_SIZE: usize = 0x100;
+/// Frames shorter than this are padded
+const MIN_FRAME_SIZE: usize = 60;
+const MAX_FRAME_SIZE: usize = 1518;
+
+/// Cpu ticks between polls of the backend for received frames
+const POLL_TICKS: u64 = 486_000;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Region {
+    Exi,
+    Mx,
+}
+
+/// Broadband Adapter in the serial port 1 (EXI channel 0, device 2). Transfers start with a
+/// command selecting an EXI register (16-bit) or an MX chip register (32-bit), followed by
+/// data. Frames are sent from the transmit FIFO and received into a ring of 256 byte pages in
+/// MX memory, each frame preceded by a descriptor.
+pub struct DeviceBba {
+    backend: Box<dyn PacketBackend>,
+    mac: [u8; 6],
+    mem: Box<[u8; BBA_MEM_SIZE]>,
+    tx_fifo: Box<[u8; TX_FIFO_SIZE]>,
+    exi_interrupt: u8,
+    exi_interrupt_mask: u8,
+    /// A command has been received for the current selection
+    command: Option<(Region, bool)>,
+    address: usize,
+    ticks: u64,
+}
+
+impl DeviceBba {
+    pub fn new(backend: Box<dyn PacketBackend>, mac: [u8; 6]) -> DeviceBba {
+        let mut bba = DeviceBba {
+            backend,
+            mac,
+            mem: Box::new([0; BBA_MEM_SIZE]),
+            tx_fifo: Box::new([0; TX_FIFO_SIZE]),
+            exi_interrupt: 0,
+            exi_interrupt_mask: 0,
+            command: None,
+            address: 0,
+            ticks: 0,
+        };
+
+        bba.reset();
+        bba
+    }
+
+    fn reset(&mut self) {
+        self.mem.fill(0);
+
+        self.mem[BBA_NWAYC] = NWAYC_LTE | NWAYC_ANE;
+        self.mem[BBA_NWAYS] = NWAYS_LS100 | NWAYS_LPNWAY | NWAYS_ANCLPT | NWAYS_100TXF;
+        self.mem[BBA_MISC] = 0x0F;
+        self.mem[BBA_SI_ACTRL2] = 0x74;
+        self.mem[BBA_NAFR_PAR0..BBA_NAFR_PAR0 + 6].copy_from_slice(&self.mac);
+
+        self.set_page_pointer(BBA_BP, 0x01);
+        self.set_page_pointer(BBA_RWP, 0x01);
+        self.set_page_pointer(BBA_RRP, 0x01);
+        self.set_page_pointer(BBA_RHBP, 0x0F);
+    }
+
+    /// Page pointers are little endian
+    fn page_pointer(&self, register: usize) -> usize {
+        LittleEndian::read_u16(&self.mem[register..]) as usize & 0xFFF
+    }
+
+    fn set_page_pointer(&mut self, register: usize, page: usize) {
+        LittleEndian::write_u16(&mut self.mem[register..], page as u16);
+    }
+
+    fn next_page(&self, page: usize) -> usize {
+        if page >= self.page_pointer(BBA_RHBP) {
+            self.page_pointer(BBA_BP)
+        } else {
+            page + 1
+        }
+    }
+
+    fn tx_fifo_count(&self) -> usize {
+        LittleEndian::read_u16(&self.mem[BBA_TXFIFOCNT..]) as usize
+    }
+
+    fn set_tx_fifo_count(&mut self, count: usize) {
+        LittleEndian::write_u16(&mut self.mem[BBA_TXFIFOCNT..], count as u16);
+    }
+
+    fn raise(&mut self, interrupt: u8) {
+        if self.mem[BBA_IMR] & interrupt != 0 {
+            self.mem[BBA_IR] |= interrupt;
+            self.exi_interrupt |= EXI_INTERRUPT_MX;
+        }
+    }
+
+    fn write_tx_fifo(&mut self, bytes: &[u8]) {
+        let count = self.tx_fifo_count();
+        let len = bytes.len().min(TX_FIFO_SIZE - count);
+
+        self.tx_fifo[count..count + len].copy_from_slice(&bytes[..len]);
+        self.set_tx_fifo_count(count + len);
+    }
+
+    /// Send the frame in the transmit FIFO
+    fn send(&mut self) {
+        let count = self.tx_fifo_count();
+
+        if let Err(e) = self.backend.send(&self.tx_fifo[..count]) {
+            warn!("BBA: unable to send frame: {}", e);
+        }
+
+        self.mem[BBA_NCRA] &= !(NCRA_ST0 | NCRA_ST1);
+        self.mem[BBA_LTPS] = 0;
+        self.set_tx_fifo_count(0);
+        self.raise(INT_T);
+    }
+
+    /// Whether a frame sent to `destination` is received
+    fn accepts(&self, destination: &[u8]) -> bool {
+        self.mem[BBA_NCRB] & NCRB_PR != 0 || destination == self.mac || destination[0] & 1 != 0
+    }
+
+    /// Store a received frame in the receive ring
+    fn receive(&mut self, frame: &[u8]) {
+        if frame.len() < 6 || !self.accepts(&frame[..6]) {
+            return;
+        }
+
+        let len = frame.len().max(MIN_FRAME_SIZE);
+        let pages = (len + 4).div_ceil(PAGE_SIZE);
+
+        let first = self.page_pointer(BBA_RWP);
+        let read = self.page_pointer(BBA_RRP);
+        let ring = (self.page_pointer(BBA_RHBP) + 1).saturating_sub(self.page_pointer(BBA_BP));
+        let used = if first >= read {
+            first - read
+        } else {
+            (first + ring).saturating_sub(read)
+        };
+
+        if pages >= ring.saturating_sub(used) {
+            debug!("BBA: receive ring full, dropping frame");
+            self.raise(INT_R_ERR);
+            return;
+        }
+
+        let mut data = frame.to_vec();
+        data.resize(len, 0);
+
+        // the descriptor occupies the first 4 bytes of the first page
+        let mut page = first;
+        let mut offset = 4;
+        for byte in data {
+            if offset == PAGE_SIZE {
+                page = self.next_page(page);
+                offset = 0;
+            }
+            self.mem[page * PAGE_SIZE + offset] = byte;
+            offset += 1;
+        }
+
+        let next = self.next_page(page);
+
+        let status = if frame[..6] == [0xFF; 6] {
+            DESC_BF
+        } else if frame[0] & 1 != 0 {
+            DESC_MF
+        } else {
+            0
+        };
+
+        let descriptor = next as u32 | ((len as u32 + 4) << 12) | (status as u32) << 24;
+        LittleEndian::write_u32(&mut self.mem[first * PAGE_SIZE..], descriptor);
+
+        self.mem[BBA_LRPS] = status;
+        self.set_page_pointer(BBA_RWP, next);
+        self.raise(INT_R);
+    }
+
+    fn write_exi_register(&mut self, value: u8) {
+        match self.address as u8 {
+            EXI_INTERRUPT_MASK => self.exi_interrupt_mask = value,
+            // write 1 to clear
+            EXI_INTERRUPT => self.exi_interrupt &= !value,
+            register => debug!("BBA: EXI register {:#04x} write {:#04x}", register, value),
+        }
+    }
+
+    fn read_exi_register(&self, len: u8) -> u32 {
+        let value = match self.address as u8 {
+            EXI_ID => BBA_EXI_ID,
+            EXI_REVISION_ID => 0xF0,
+            EXI_INTERRUPT_MASK => self.exi_interrupt_mask as u32,
+            EXI_INTERRUPT => self.exi_interrupt as u32,
+            EXI_DEVICE_ID => 0xD107,
+            EXI_ACSTART => 0x4E,
+            _ => 0,
+        };
+
+        if len == 4 {
+            value
+        } else {
+            value << ((4 - len as u32) * 8)
+        }
+    }
+
+    fn write_mx_register(&mut self, value: u32, len: u8) {
+        let bytes = &value.to_be_bytes()[..len as usize];
+
+        match self.address {
+            BBA_NCRA => {
+                let ncra = bytes[0];
+
+                if ncra & NCRA_RESET != 0 {
+                    self.reset();
+                }
+
+                self.mem[BBA_NCRA] = ncra & !NCRA_RESET;
+
+                if ncra & NCRA_ST1 != 0 {
+                    self.send();
+                } else if ncra & NCRA_ST0 != 0 {
+                    warn!("BBA: transmit from packet buffer is not supported");
+                    self.mem[BBA_NCRA] &= !NCRA_ST0;
+                }
+            }
+            BBA_WRTXFIFOD => {
+                self.write_tx_fifo(bytes);
+                return;
+            }
+            // write 1 to clear
+            BBA_IR => self.mem[BBA_IR] &= !bytes[0],
+            BBA_TXFIFOCNT => (),
+            address => {
+                for (i, byte) in bytes.iter().enumerate() {
+                    self.mem[(address + i) & (BBA_MEM_SIZE - 1)] = *byte;
+                }
+            }
+        }
+
+        self.address += len as usize;
+    }
+
+    fn read_mx_register(&mut self, len: u8) -> u32 {
+        let mut value = 0;
+
+        for i in 0..len as u32 {
+            value |= (self.mem[self.address & (BBA_MEM_SIZE - 1)] as u32) << (24 - i * 8);
+            self.address += 1;
+        }
+
+        value
+    }
+}
+
+impl Device for DeviceBba {
+    fn device_select(&mut self) {
+        self.command = None;
+    }
+
+    fn update(&mut self, ticks: u64) {
+        self.ticks += ticks;
+
+        if self.ticks < POLL_TICKS {
+            return;
+        }
+        self.ticks = 0;
+
+        let mut buf = [0; MAX_FRAME_SIZE];
+
+        while self.mem[BBA_NCRA] & NCRA_SR != 0 {
+            match self.backend.receive(&mut buf) {
+                Ok(Some(len)) => self.receive(&buf[..len]),
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("BBA: unable to receive frame: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn interrupt(&self) -> bool {
+        self.exi_interrupt & self.exi_interrupt_mask != 0
+    }
+
+    fn imm_write(&mut self, value: u32, len: u8) {
+        match self.command {
+            None => {
+                // MX commands are 32-bit with bit 31 set, EXI commands are 16-bit
+                if len == 4 && value & 0x8000_0000 != 0 {
+                    self.command = Some((Region::Mx, value & 0x4000_0000 != 0));
+                    self.address = ((value >> 8) & 0xFFFF) as usize;
+                } else {
+                    let command = value >> 16;
+                    self.command = Some((Region::Exi, command & 0x4000 != 0));
+                    self.address = ((command >> 8) & 0x3F) as usize;
+                }
+            }
+            Some((Region::Exi, true)) => self.write_exi_register((value >> 24) as u8),
+            Some((Region::Mx, true)) => self.write_mx_register(value, len),
+            Some((_, false)) => warn!("BBA: write during a read command"),
+        }
+    }
+
+    fn imm_read(&mut self, len: u8) -> u32 {
+        match self.command {
+            Some((Region::Exi, false)) => self.read_exi_register(len),
+            Some((Region::Mx, false)) => self.read_mx_register(len),
+            _ => 0,
+        }
+    }
+
+    fn dma_read(&mut self, mem: &mut Memory, address: u32, len: u32) {
+        for i in 0..len {
+            let value = self.read_mx_register(1);
+            mem.write_u8(address + i, (value >> 24) as u8);
+        }
+    }
+
+    fn dma_write(&mut self, mem: &mut Memory, address: u32, len: u32) {
+        if self.command == Some((Region::Mx, true)) && self.address == BBA_WRTXFIFOD {
+            let bytes: Vec<u8> = (0..len).map(|i| mem.read_u8(address + i)).collect();
+            self.write_tx_fifo(&bytes);
+        } else {
+            warn!("BBA: unexpected DMA write to {:#x}", self.address);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Loopback {
+        sent: Rc<RefCell<Vec<Vec<u8>>>>,
+        pending: Rc<RefCell<VecDeque<Vec<u8>>>>,
+    }
+
+    impl PacketBackend for Loopback {
+        fn send(&mut self, frame: &[u8]) -> Result<(), Error> {
+            self.sent.borrow_mut().push(frame.to_vec());
+            Ok(())
+        }
+
+        fn receive(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+            Ok(self.pending.borrow_mut().pop_front().map(|frame| {
+                buf[..frame.len()].copy_from_slice(&frame);
+                frame.len()
+            }))
+        }
+    }
+
+    const MAC: [u8; 6] = [0x00, 0x09, 0xBF, 0x00, 0x00, 0x01];
+
+    fn write_mx(bba: &mut DeviceBba, register: u32, bytes: &[u8]) {
+        bba.device_select();
+        bba.imm_write(0xC000_0000 | register << 8, 4);
+        for byte in bytes {
+            bba.imm_write((*byte as u32) << 24, 1);
+        }
+    }
+
+    fn read_mx(bba: &mut DeviceBba, register: u32) -> u8 {
+        bba.device_select();
+        bba.imm_write(0x8000_0000 | register << 8, 4);
+        (bba.imm_read(1) >> 24) as u8
+    }
+
+    #[test]
+    fn exi_registers() {
+        let mut bba = DeviceBba::new(Box::new(Loopback::default()), MAC);
+
+        bba.device_select();
+        bba.imm_write(0, 2);
+        assert_eq!(bba.imm_read(4), BBA_EXI_ID);
+
+        // interrupt mask write then read back
+        bba.device_select();
+        bba.imm_write((0x4000 | (EXI_INTERRUPT_MASK as u32) << 8) << 16, 2);
+        bba.imm_write(0xF8 << 24, 1);
+        bba.device_select();
+        bba.imm_write(((EXI_INTERRUPT_MASK as u32) << 8) << 16, 2);
+        assert_eq!(bba.imm_read(1), 0xF8 << 24);
+    }
+
+    #[test]
+    fn send_and_receive() {
+        let backend = Loopback::default();
+        let mut bba = DeviceBba::new(Box::new(backend.clone()), MAC);
+
+        write_mx(&mut bba, BBA_IMR as u32, &[INT_R | INT_T]);
+        bba.exi_interrupt_mask = EXI_INTERRUPT_MX;
+
+        // transmit through the FIFO
+        write_mx(&mut bba, BBA_WRTXFIFOD as u32, &[1, 2, 3, 4]);
+        write_mx(&mut bba, BBA_NCRA as u32, &[NCRA_ST1]);
+
+        assert_eq!(backend.sent.borrow()[0], [1, 2, 3, 4]);
+        assert_eq!(read_mx(&mut bba, BBA_IR as u32), INT_T);
+        assert!(bba.interrupt());
+
+        write_mx(&mut bba, BBA_IR as u32, &[INT_T]);
+        bba.exi_interrupt = 0;
+
+        // frames for other stations are filtered
+        let mut frame = vec![0xAA; 6];
+        frame.extend_from_slice(&[0x55; 100]);
+        backend.pending.borrow_mut().push_back(frame);
+
+        let mut frame = MAC.to_vec();
+        frame.extend((0..300).map(|i| i as u8));
+        backend.pending.borrow_mut().push_back(frame.clone());
+
+        write_mx(&mut bba, BBA_NCRA as u32, &[NCRA_SR]);
+        bba.update(POLL_TICKS);
+
+        assert_eq!(read_mx(&mut bba, BBA_IR as u32), INT_R);
+        assert_eq!(bba.page_pointer(BBA_RWP), 0x03);
+
+        let descriptor = LittleEndian::read_u32(&bba.mem[0x100..]);
+        assert_eq!(descriptor & 0xFFF, 0x03);
+        assert_eq!((descriptor >> 12) & 0xFFF, 306 + 4);
+
+        // DMA the frame out of the ring
+        bba.device_select();
+        bba.imm_write(0x8000_0000 | 0x104 << 8, 4);
+        let mut mem = Memory::default();
+        bba.dma_read(&mut mem, 0, 252);
+        bba.device_select();
+        bba.imm_write(0x8000_0000 | 0x200 << 8, 4);
+        bba.dma_read(&mut mem, 252, 54);
+
+        let received: Vec<u8> = (0..306).map(|i| mem.read_u8(i)).collect();
+        assert_eq!(received, frame);
+    }
+}
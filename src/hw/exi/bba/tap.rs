@@ -0,0 +1,72 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{Error, ErrorKind, Read, Write},
+    os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+};
+
+use libc::{ioctl, IFF_NO_PI, IFF_TAP, IFNAMSIZ, O_NONBLOCK, TUNSETIFF};
+
+use super::PacketBackend;
+
+const TUN_PATH: &str = "/dev/net/tun";
+
+/// `struct ifreq` with the name and flags members used by `TUNSETIFF`
+#[repr(C)]
+struct InterfaceRequest {
+    name: [u8; IFNAMSIZ],
+    flags: i16,
+    padding: [u8; 22],
+}
+
+/// Exchanges frames with a Linux TAP interface, which the host can bridge or route to a real
+/// network. The interface must exist or the user must be allowed to create it.
+pub struct TapBackend {
+    file: File,
+}
+
+impl TapBackend {
+    pub fn new(name: &str) -> Result<TapBackend, Error> {
+        if name.len() >= IFNAMSIZ {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("interface name {} is too long", name),
+            ));
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(O_NONBLOCK)
+            .open(TUN_PATH)?;
+
+        let mut request = InterfaceRequest {
+            name: [0; IFNAMSIZ],
+            flags: (IFF_TAP | IFF_NO_PI) as i16,
+            padding: [0; 22],
+        };
+        request.name[..name.len()].copy_from_slice(name.as_bytes());
+
+        // SAFETY: the request outlives the call and has the layout of `struct ifreq`
+        if unsafe { ioctl(file.as_raw_fd(), TUNSETIFF, &mut request) } < 0 {
+            return Err(Error::last_os_error());
+        }
+
+        info!("BBA: attached to TAP interface {}", name);
+
+        Ok(TapBackend { file })
+    }
+}
+
+impl PacketBackend for TapBackend {
+    fn send(&mut self, frame: &[u8]) -> Result<(), Error> {
+        self.file.write(frame).map(|_| ())
+    }
+
+    fn receive(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        match self.file.read(buf) {
+            Ok(len) => Ok(Some(len)),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
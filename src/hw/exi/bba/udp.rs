@@ -0,0 +1,86 @@
+use std::{
+    io::{Error, ErrorKind},
+    net::{SocketAddr, UdpSocket},
+};
+
+use super::PacketBackend;
+
+/// Tunnels frames as UDP datagrams to a peer, such as another emulator instance on the same
+/// host. Needs no privileges, but only the peer can be reached.
+pub struct UdpBackend {
+    socket: UdpSocket,
+    peer: SocketAddr,
+}
+
+impl UdpBackend {
+    pub fn new(local: SocketAddr, peer: SocketAddr) -> Result<UdpBackend, Error> {
+        let socket = UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
+
+        info!(
+            "BBA: tunneling frames from {} to {}",
+            socket.local_addr()?,
+            peer
+        );
+
+        Ok(UdpBackend { socket, peer })
+    }
+}
+
+impl PacketBackend for UdpBackend {
+    fn send(&mut self, frame: &[u8]) -> Result<(), Error> {
+        self.socket.send_to(frame, self.peer).map(|_| ())
+    }
+
+    fn receive(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        loop {
+            match self.socket.recv_from(buf) {
+                // ignore datagrams from anyone but the peer
+                Ok((len, addr)) if addr == self.peer => return Ok(Some(len)),
+                Ok(_) => continue,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(None),
+                // the peer not listening yet is reported on the next receive
+                Err(e) if e.kind() == ErrorKind::ConnectionRefused => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn tunnel() {
+        let a = UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let mut backend_a = UdpBackend::new(a, b).unwrap();
+        let mut backend_b = UdpBackend::new(b, a).unwrap();
+
+        backend_a.send(&[1, 2, 3]).unwrap();
+
+        let mut buf = [0; 16];
+        let mut received = None;
+        for _ in 0..100 {
+            received = backend_b.receive(&mut buf).unwrap();
+            if received.is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(received, Some(3));
+        assert_eq!(buf[..3], [1, 2, 3]);
+        assert_eq!(backend_a.receive(&mut buf).unwrap(), None);
+    }
+}
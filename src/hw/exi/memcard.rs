@@ -0,0 +1,447 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{Error, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use super::Device;
+use crate::{
+    config::Language,
+    cpu::timers::CPU_CLOCK,
+    memcard::{card_size, GciFolder, BLOCK_SIZE, CARD_SIZES, SYSTEM_BLOCKS},
+};
+
+const COMMAND_NINTENDO_ID: u8 = 0x00;
+const COMMAND_CLEAR_STATUS: u8 = 0x89;
+const COMMAND_READ_STATUS: u8 = 0x83;
+const COMMAND_READ_ID: u8 = 0x85;
+const COMMAND_READ_ARRAY: u8 = 0x52;
+const COMMAND_SET_INTERRUPT: u8 = 0x81;
+const COMMAND_SECTOR_ERASE: u8 = 0xF1;
+const COMMAND_PAGE_PROGRAM: u8 = 0xF2;
+const COMMAND_CHIP_ERASE: u8 = 0xF4;
+const COMMAND_SLEEP: u8 = 0x88;
+const COMMAND_WAKE_UP: u8 = 0x87;
+
+const STATUS_BUSY: u8 = 0x80;
+const STATUS_UNLOCKED: u8 = 0x40;
+const STATUS_SLEEP: u8 = 0x20;
+const STATUS_ERASE_ERROR: u8 = 0x10;
+const STATUS_PROGRAM_ERROR: u8 = 0x08;
+const STATUS_READY: u8 = 0x01;
+
+/// Flash manufacturer/device id (Macronix)
+const FLASH_ID: u16 = 0xC221;
+
+const SECTOR_SIZE: usize = BLOCK_SIZE;
+const PAGE_SIZE: usize = 0x80;
+
+/// Address bytes and dummy bytes that precede data of a read array command
+const READ_ARRAY_DATA_START: u32 = 9;
+/// Command byte and address bytes that precede data of a page program command
+const PAGE_PROGRAM_DATA_START: u32 = 5;
+
+/// Time in cpu ticks until the card reports ready again after a write or erase. These are the
+/// typical times of the Macronix MX25L1605D serial flash datasheet (1.4 ms page program, 90 ms
+/// sector erase, 15 s chip erase), the exact parts in cards are not documented.
+const PAGE_PROGRAM_TICKS: u64 = CPU_CLOCK * 14 / 10_000;
+const SECTOR_ERASE_TICKS: u64 = CPU_CLOCK * 90 / 1_000;
+const CHIP_ERASE_TICKS: u64 = CPU_CLOCK * 15;
+
+/// Time in cpu ticks without writes after which a folder card writes its saves back, so a
+/// save operation made up of several erases and programs is complete
+const WRITE_BACK_TICKS: u64 = 243_000_000;
+
+enum Backing {
+    None,
+    /// Raw card image, written through on every program and erase
+    File(File),
+    /// Directory of `.gci` saves
+    Folder(GciFolder),
+}
+
+/// Memory card in slot A (channel 0) or B (channel 1)
+pub struct DeviceMemoryCard {
+    data: Vec<u8>,
+    backing: Backing,
+    /// Ticks until modified saves are written back to a folder
+    write_back_ticks: u64,
+    position: u32,
+    command: u8,
+    address: u32,
+    status: u8,
+    /// Set interrupt command enables the interrupt line
+    interrupt_enable: bool,
+    interrupt: bool,
+    /// Ticks until the current program/erase operation completes
+    busy_ticks: u64,
+    page_buffer: [u8; PAGE_SIZE],
+}
+
+impl DeviceMemoryCard {
+    /// Open a raw card image, creating an erased card of `blocks` size if it doesn't exist
+    pub fn open<P: AsRef<Path>>(path: P, blocks: u16) -> Result<DeviceMemoryCard, Error> {
+        let path = path.as_ref();
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+
+        if data.is_empty() {
+            info!("Creating {} block memory card {}", blocks, path.display());
+
+            data = vec![0xFF; card_size(blocks)];
+            file.write_all(&data)?;
+        }
+
+        if !CARD_SIZES
+            .iter()
+            .any(|blocks| card_size(*blocks) == data.len())
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: invalid memory card size", path.display()),
+            ));
+        }
+
+        info!(
+            "Memory card {} ({} blocks)",
+            path.display(),
+            data.len() / BLOCK_SIZE - SYSTEM_BLOCKS
+        );
+
+        let mut card = DeviceMemoryCard::new(data);
+        card.backing = Backing::File(file);
+
+        Ok(card)
+    }
+
+    /// Card built from the `.gci` saves in a directory. `flash_id` and `language` are taken from
+    /// SRAM so the synthesised card is formatted for this console.
+    pub fn open_folder<P: AsRef<Path>>(
+        path: P,
+        blocks: u16,
+        flash_id: &[u8; 12],
+        language: Language,
+    ) -> Result<DeviceMemoryCard, Error> {
+        let (folder, card) = GciFolder::open(path.as_ref(), blocks, flash_id, language)?;
+
+        info!(
+            "Memory card folder {} ({} blocks, {} free)",
+            path.as_ref().display(),
+            card.blocks(),
+            card.free_blocks()
+        );
+
+        let mut card = DeviceMemoryCard::new(card.into_data());
+        card.backing = Backing::Folder(folder);
+
+        Ok(card)
+    }
+
+    /// Card not backed by a file
+    pub fn new(data: Vec<u8>) -> DeviceMemoryCard {
+        DeviceMemoryCard {
+            data,
+            backing: Backing::None,
+            write_back_ticks: 0,
+            position: 0,
+            command: 0,
+            address: 0,
+            status: STATUS_BUSY | STATUS_UNLOCKED | STATUS_READY,
+            interrupt_enable: false,
+            interrupt: false,
+            busy_ticks: 0,
+            page_buffer: [0; PAGE_SIZE],
+        }
+    }
+
+    /// Card contents, as the console sees them
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Keep the contents but stop writing changes back to the card's file or folder
+    pub fn detach(&mut self) {
+        self.backing = Backing::None;
+        self.write_back_ticks = 0;
+    }
+
+    /// Card size in megabits, reported by the Nintendo ID command
+    fn size_mbits(&self) -> u32 {
+        (self.data.len() * 8 / (1024 * 1024)) as u32
+    }
+
+    fn mask(&self) -> usize {
+        self.data.len() - 1
+    }
+
+    fn busy(&mut self, ticks: u64) {
+        self.status |= STATUS_BUSY;
+        self.status &= !STATUS_READY;
+        self.busy_ticks = ticks;
+    }
+
+    /// Write a modified range back to the card image, or schedule a folder write back
+    fn flush(&mut self, offset: usize, len: usize) {
+        match &mut self.backing {
+            Backing::None => (),
+            Backing::File(file) => {
+                let result = file
+                    .seek(SeekFrom::Start(offset as u64))
+                    .and_then(|_| file.write_all(&self.data[offset..offset + len]));
+
+                if let Err(e) = result {
+                    error!("Unable to write memory card: {}", e);
+                }
+            }
+            Backing::Folder(_) => self.write_back_ticks = WRITE_BACK_TICKS,
+        }
+    }
+
+    fn write_back(&mut self) {
+        if let Backing::Folder(folder) = &mut self.backing {
+            folder.write_back(&self.data);
+        }
+        self.write_back_ticks = 0;
+    }
+
+    fn set_address_byte(&mut self, byte: u8) {
+        match self.position {
+            1 => self.address = (byte as u32) << 17,
+            2 => self.address |= (byte as u32) << 9,
+            3 => self.address |= (byte as u32 & 3) << 7,
+            4 => self.address |= byte as u32 & 0x7F,
+            _ => (),
+        }
+    }
+}
+
+impl Device for DeviceMemoryCard {
+    fn device_select(&mut self) {
+        self.position = 0;
+    }
+
+    /// Erase and program commands are executed once the card is deselected
+    fn device_deselect(&mut self) {
+        match self.command {
+            COMMAND_SECTOR_ERASE if self.position > 2 => {
+                let offset = self.address as usize & self.mask() & !(SECTOR_SIZE - 1);
+
+                self.data[offset..offset + SECTOR_SIZE].fill(0xFF);
+                self.flush(offset, SECTOR_SIZE);
+
+                self.busy(SECTOR_ERASE_TICKS);
+            }
+            COMMAND_CHIP_ERASE if self.position > 2 => {
+                self.data.fill(0xFF);
+                self.flush(0, self.data.len());
+
+                self.busy(CHIP_ERASE_TICKS);
+            }
+            COMMAND_PAGE_PROGRAM if self.position >= PAGE_PROGRAM_DATA_START => {
+                let count = (self.position - PAGE_PROGRAM_DATA_START) as usize;
+                let page = self.address as usize & self.mask() & !(PAGE_SIZE - 1);
+
+                // data wraps around within the page
+                for i in 0..count.min(PAGE_SIZE) {
+                    let offset = page | ((self.address as usize + i) & (PAGE_SIZE - 1));
+                    self.data[offset] = self.page_buffer[i];
+                }
+
+                self.flush(page, PAGE_SIZE);
+
+                self.busy(PAGE_PROGRAM_TICKS);
+            }
+            _ => (),
+        }
+
+        self.command = 0;
+    }
+
+    fn transfer_byte(&mut self, byte: &mut u8) {
+        if self.position == 0 {
+            self.command = *byte;
+            *byte = 0xFF;
+
+            match self.command {
+                COMMAND_CLEAR_STATUS => {
+                    self.status &= !(STATUS_PROGRAM_ERROR | STATUS_ERASE_ERROR);
+                    self.interrupt = false;
+                }
+                COMMAND_SLEEP => self.status |= STATUS_SLEEP,
+                COMMAND_WAKE_UP => self.status &= !STATUS_SLEEP,
+                COMMAND_NINTENDO_ID
+                | COMMAND_READ_STATUS
+                | COMMAND_READ_ID
+                | COMMAND_READ_ARRAY
+                | COMMAND_SET_INTERRUPT
+                | COMMAND_SECTOR_ERASE
+                | COMMAND_PAGE_PROGRAM
+                | COMMAND_CHIP_ERASE => (),
+                _ => warn!("MemoryCard: unknown command {:#04x}", self.command),
+            }
+        } else {
+            match self.command {
+                COMMAND_NINTENDO_ID => {
+                    *byte = if self.position == 1 {
+                        0x80
+                    } else {
+                        (self.size_mbits() >> (24 - ((self.position - 2) & 3) * 8)) as u8
+                    };
+                }
+                COMMAND_READ_ARRAY => {
+                    self.set_address_byte(*byte);
+
+                    if self.position >= READ_ARRAY_DATA_START {
+                        *byte = self.data[self.address as usize & self.mask()];
+
+                        // address wraps around within the 0x200 byte page
+                        self.address = (self.address & !0x1FF) | ((self.address + 1) & 0x1FF);
+                    } else {
+                        *byte = 0xFF;
+                    }
+                }
+                COMMAND_READ_STATUS => *byte = self.status,
+                COMMAND_READ_ID => {
+                    *byte = if self.position & 1 != 0 {
+                        (FLASH_ID >> 8) as u8
+                    } else {
+                        FLASH_ID as u8
+                    };
+                }
+                COMMAND_SET_INTERRUPT => {
+                    if self.position == 1 {
+                        self.interrupt_enable = *byte & 1 != 0;
+                    }
+                    *byte = 0xFF;
+                }
+                COMMAND_SECTOR_ERASE | COMMAND_CHIP_ERASE => {
+                    self.set_address_byte(*byte);
+                    *byte = 0xFF;
+                }
+                COMMAND_PAGE_PROGRAM => {
+                    self.set_address_byte(*byte);
+
+                    if self.position >= PAGE_PROGRAM_DATA_START {
+                        let index = (self.position - PAGE_PROGRAM_DATA_START) as usize;
+                        self.page_buffer[index & (PAGE_SIZE - 1)] = *byte;
+                    }
+                    *byte = 0xFF;
+                }
+                _ => *byte = 0xFF,
+            }
+        }
+
+        self.position += 1;
+    }
+
+    fn update(&mut self, ticks: u64) {
+        if self.write_back_ticks != 0 {
+            self.write_back_ticks = self.write_back_ticks.saturating_sub(ticks);
+
+            if self.write_back_ticks == 0 {
+                self.write_back();
+            }
+        }
+
+        if self.busy_ticks == 0 {
+            return;
+        }
+
+        self.busy_ticks = self.busy_ticks.saturating_sub(ticks);
+
+        if self.busy_ticks == 0 {
+            self.status &= !STATUS_BUSY;
+            self.status |= STATUS_READY;
+            self.interrupt = true;
+        }
+    }
+
+    fn interrupt(&self) -> bool {
+        self.interrupt_enable && self.interrupt
+    }
+}
+
+impl Drop for DeviceMemoryCard {
+    fn drop(&mut self) {
+        if self.write_back_ticks != 0 {
+            self.write_back();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(card: &mut DeviceMemoryCard, bytes: &[u8]) -> Vec<u8> {
+        card.device_select();
+
+        let result = bytes
+            .iter()
+            .map(|byte| {
+                let mut byte = *byte;
+                card.transfer_byte(&mut byte);
+                byte
+            })
+            .collect();
+
+        card.device_deselect();
+
+        result
+    }
+
+    #[test]
+    fn program_and_read() {
+        let mut card = DeviceMemoryCard::new(vec![0xFF; card_size(59)]);
+
+        // Nintendo ID reports card size in megabits
+        assert_eq!(command(&mut card, &[0, 0, 0, 0, 0, 0])[2..], [0, 0, 0, 4]);
+
+        command(&mut card, &[COMMAND_SET_INTERRUPT, 1]);
+
+        // page program at 0x2080
+        let mut program = vec![COMMAND_PAGE_PROGRAM, 0x00, 0x10, 0x01, 0x00];
+        program.extend_from_slice(&[0xAA, 0xBB, 0xCC]);
+        command(&mut card, &program);
+
+        assert_eq!(
+            command(&mut card, &[COMMAND_READ_STATUS, 0])[1] & STATUS_BUSY,
+            STATUS_BUSY
+        );
+        assert!(!card.interrupt());
+
+        // still programming just before the page program time
+        card.update(PAGE_PROGRAM_TICKS - 1);
+        assert_eq!(
+            command(&mut card, &[COMMAND_READ_STATUS, 0])[1] & STATUS_BUSY,
+            STATUS_BUSY
+        );
+        card.update(1);
+
+        assert_eq!(
+            command(&mut card, &[COMMAND_READ_STATUS, 0])[1],
+            STATUS_UNLOCKED | STATUS_READY
+        );
+        assert!(card.interrupt());
+
+        command(&mut card, &[COMMAND_CLEAR_STATUS]);
+        assert!(!card.interrupt());
+
+        let mut read = vec![COMMAND_READ_ARRAY, 0x00, 0x10, 0x01, 0x00, 0, 0, 0, 0];
+        read.extend_from_slice(&[0; 4]);
+        assert_eq!(command(&mut card, &read)[9..], [0xAA, 0xBB, 0xCC, 0xFF]);
+
+        // erase the sector again
+        command(&mut card, &[COMMAND_SECTOR_ERASE, 0x00, 0x10]);
+        card.update(SECTOR_ERASE_TICKS);
+        assert_eq!(command(&mut card, &read)[9..], [0xFF; 4]);
+    }
+}
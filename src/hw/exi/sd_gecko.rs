@@ -0,0 +1,594 @@
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{Error, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use super::Device;
+use crate::hw::memory::Memory;
+
+const CMD_GO_IDLE_STATE: u8 = 0;
+const CMD_SEND_OP_COND: u8 = 1;
+const CMD_SEND_IF_COND: u8 = 8;
+const CMD_SEND_CSD: u8 = 9;
+const CMD_SEND_CID: u8 = 10;
+const CMD_STOP_TRANSMISSION: u8 = 12;
+const CMD_SEND_STATUS: u8 = 13;
+const CMD_SET_BLOCKLEN: u8 = 16;
+const CMD_READ_SINGLE_BLOCK: u8 = 17;
+const CMD_READ_MULTIPLE_BLOCK: u8 = 18;
+const CMD_WRITE_BLOCK: u8 = 24;
+const CMD_WRITE_MULTIPLE_BLOCK: u8 = 25;
+const CMD_APP_CMD: u8 = 55;
+const CMD_READ_OCR: u8 = 58;
+const CMD_CRC_ON_OFF: u8 = 59;
+
+const ACMD_SD_STATUS: u8 = 13;
+const ACMD_SET_WR_BLK_ERASE_COUNT: u8 = 23;
+const ACMD_SD_SEND_OP_COND: u8 = 41;
+const ACMD_SET_CLR_CARD_DETECT: u8 = 42;
+const ACMD_SEND_SCR: u8 = 51;
+
+const R1_IDLE: u8 = 0x01;
+const R1_ILLEGAL_COMMAND: u8 = 0x04;
+const R1_ADDRESS_ERROR: u8 = 0x20;
+const R1_PARAMETER_ERROR: u8 = 0x40;
+
+const TOKEN_START_BLOCK: u8 = 0xFE;
+const TOKEN_START_MULTIPLE_WRITE: u8 = 0xFC;
+const TOKEN_STOP_TRANSMISSION: u8 = 0xFD;
+const DATA_ACCEPTED: u8 = 0x05;
+const DATA_WRITE_ERROR: u8 = 0x0D;
+
+/// Power up complete, 2.7-3.6V
+const OCR: u32 = 0x80FF_8000;
+/// Card capacity status, set for SDHC cards which are addressed in blocks
+const OCR_CCS: u32 = 0x4000_0000;
+
+const BLOCK_SIZE: usize = 512;
+/// Cards larger than this are SDHC
+const SDSC_MAX_SIZE: u64 = 2 << 30;
+
+const COMMAND_SIZE: usize = 6;
+
+enum State {
+    /// Waiting for a command
+    Command,
+    /// Waiting for the data token of a write, `multiple` for a multiple block write
+    WriteToken { block: u64, multiple: bool },
+    /// Receiving the data of a write followed by its CRC
+    WriteData { block: u64, multiple: bool },
+}
+
+/// SD Gecko adapter in slot A (channel 0) or B (channel 1), an SD card driven over SPI. The
+/// card is backed by a raw disk image, cards over 2GB are SDHC.
+pub struct DeviceSdGecko {
+    file: File,
+    blocks: u64,
+    sdhc: bool,
+    state: State,
+    command: Vec<u8>,
+    data: Vec<u8>,
+    /// Bytes shifted out on the following transfers
+    response: VecDeque<u8>,
+    /// Next block of a multiple block read
+    reading: Option<u64>,
+    idle: bool,
+    /// Next command is an application specific command
+    app_command: bool,
+}
+
+impl DeviceSdGecko {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<DeviceSdGecko, Error> {
+        let path = path.as_ref();
+
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let size = file.metadata()?.len();
+
+        if size == 0 || !size.is_multiple_of(BLOCK_SIZE as u64) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("SD card image size {size:#x} is not a multiple of {BLOCK_SIZE}"),
+            ));
+        }
+
+        let sdhc = size > SDSC_MAX_SIZE;
+
+        info!(
+            "SD card {} ({} MiB{})",
+            path.display(),
+            size >> 20,
+            if sdhc { ", SDHC" } else { "" }
+        );
+
+        Ok(DeviceSdGecko {
+            file,
+            blocks: size / BLOCK_SIZE as u64,
+            sdhc,
+            state: State::Command,
+            command: Vec::with_capacity(COMMAND_SIZE),
+            data: Vec::with_capacity(BLOCK_SIZE + 2),
+            response: VecDeque::new(),
+            reading: None,
+            idle: true,
+            app_command: false,
+        })
+    }
+
+    fn r1(&self, flags: u8) -> u8 {
+        if self.idle {
+            flags | R1_IDLE
+        } else {
+            flags
+        }
+    }
+
+    /// Queue a response after the byte of command response time
+    fn respond(&mut self, bytes: &[u8]) {
+        self.response.push_back(0xFF);
+        self.response.extend(bytes);
+    }
+
+    /// Queue a data block: a gap, the start token, the data and its CRC
+    fn respond_data(&mut self, data: &[u8]) {
+        self.response.push_back(0xFF);
+        self.response.push_back(TOKEN_START_BLOCK);
+        self.response.extend(data);
+        self.response.extend(crc16(data).to_be_bytes());
+    }
+
+    /// Block addressed by a read or write command, SDSC cards are addressed in bytes
+    fn block(&self, argument: u32) -> Option<u64> {
+        let block = if self.sdhc {
+            argument as u64
+        } else if (argument as usize).is_multiple_of(BLOCK_SIZE) {
+            argument as u64 / BLOCK_SIZE as u64
+        } else {
+            return None;
+        };
+
+        (block < self.blocks).then_some(block)
+    }
+
+    fn read_block(&mut self, block: u64) -> Result<[u8; BLOCK_SIZE], Error> {
+        let mut data = [0; BLOCK_SIZE];
+
+        self.file.seek(SeekFrom::Start(block * BLOCK_SIZE as u64))?;
+        self.file.read_exact(&mut data)?;
+
+        Ok(data)
+    }
+
+    fn write_block(&mut self, block: u64, data: &[u8]) -> Result<(), Error> {
+        self.file.seek(SeekFrom::Start(block * BLOCK_SIZE as u64))?;
+        self.file.write_all(data)
+    }
+
+    /// Queue the next block of a multiple block read
+    fn queue_read(&mut self, block: u64) {
+        match self.read_block(block) {
+            Ok(data) => {
+                self.respond_data(&data);
+                self.reading = Some(block + 1).filter(|block| *block < self.blocks);
+            }
+            Err(e) => {
+                error!("SD card: unable to read block {}: {}", block, e);
+                // data error token: card ECC failed
+                self.response.extend([0xFF, 0x04]);
+                self.reading = None;
+            }
+        }
+    }
+
+    fn csd(&self) -> [u8; 16] {
+        let mut csd = Bits::default();
+
+        if self.sdhc {
+            csd.set(126, 2, 1);
+            csd.set(112, 8, 0x0E); // TAAC
+            csd.set(96, 8, 0x32); // TRAN_SPEED, 25MHz
+            csd.set(84, 12, 0x5B5); // CCC
+            csd.set(80, 4, 9); // READ_BL_LEN
+            csd.set(48, 22, self.blocks / 1024 - 1); // C_SIZE, in 512KiB units
+            csd.set(46, 1, 1); // ERASE_BLK_EN
+            csd.set(39, 7, 0x7F); // SECTOR_SIZE
+            csd.set(26, 3, 2); // R2W_FACTOR
+            csd.set(22, 4, 9); // WRITE_BL_LEN
+        } else {
+            // smallest block length that can describe the card size
+            let size = self.blocks * BLOCK_SIZE as u64;
+            let read_bl_len = (9..11)
+                .find(|len| size / (512 << len) <= 4096)
+                .unwrap_or(11);
+
+            csd.set(112, 8, 0x26); // TAAC
+            csd.set(96, 8, 0x32); // TRAN_SPEED
+            csd.set(84, 12, 0x5F5); // CCC
+            csd.set(80, 4, read_bl_len);
+            csd.set(79, 1, 1); // READ_BL_PARTIAL
+            csd.set(62, 12, (size / (512 << read_bl_len)).max(1) - 1); // C_SIZE
+            csd.set(47, 3, 7); // C_SIZE_MULT, 512
+            csd.set(46, 1, 1); // ERASE_BLK_EN
+            csd.set(39, 7, 0x7F); // SECTOR_SIZE
+            csd.set(26, 3, 2); // R2W_FACTOR
+            csd.set(22, 4, 9); // WRITE_BL_LEN
+        }
+
+        csd.finish()
+    }
+
+    fn cid() -> [u8; 16] {
+        let mut cid = Bits::default();
+
+        cid.set(120, 8, 0x03); // MID
+        cid.set(104, 16, u16::from_be_bytes(*b"RC") as u64); // OID
+        cid.set(64, 40, u64::from_be_bytes(*b"\0\0\0RCUBE")); // PNM
+        cid.set(56, 8, 0x10); // PRV
+        cid.set(24, 32, 0x1234_5678); // PSN
+        cid.set(8, 12, 0x18C); // MDT, 2024-12
+
+        cid.finish()
+    }
+
+    fn execute(&mut self) {
+        let index = self.command[0] & 0x3F;
+        let argument = u32::from_be_bytes(self.command[1..5].try_into().unwrap());
+        let app_command = std::mem::take(&mut self.app_command);
+
+        self.command.clear();
+
+        if app_command {
+            return self.execute_app_command(index);
+        }
+
+        match index {
+            CMD_GO_IDLE_STATE => {
+                self.idle = true;
+                self.reading = None;
+                self.respond(&[R1_IDLE]);
+            }
+            CMD_SEND_OP_COND => {
+                self.idle = false;
+                self.respond(&[0]);
+            }
+            CMD_SEND_IF_COND => {
+                // echo the voltage range and check pattern
+                let r1 = self.r1(0);
+                self.respond(&[r1, 0, 0, (argument >> 8) as u8 & 0x0F, argument as u8]);
+            }
+            CMD_SEND_CSD => {
+                let csd = self.csd();
+                self.respond(&[self.r1(0)]);
+                self.respond_data(&csd);
+            }
+            CMD_SEND_CID => {
+                self.respond(&[self.r1(0)]);
+                self.respond_data(&Self::cid());
+            }
+            CMD_STOP_TRANSMISSION => {
+                self.reading = None;
+                self.response.clear();
+                // stuff byte, R1 and busy
+                let r1 = self.r1(0);
+                self.response.extend([0xFF, 0xFF, r1, 0x00]);
+            }
+            CMD_SEND_STATUS => self.respond(&[self.r1(0), 0]),
+            CMD_SET_BLOCKLEN => {
+                let r1 = if argument as usize == BLOCK_SIZE {
+                    0
+                } else {
+                    R1_PARAMETER_ERROR
+                };
+                self.respond(&[self.r1(r1)]);
+            }
+            CMD_READ_SINGLE_BLOCK | CMD_READ_MULTIPLE_BLOCK => match self.block(argument) {
+                Some(block) => {
+                    self.respond(&[self.r1(0)]);
+                    self.queue_read(block);
+
+                    if index == CMD_READ_SINGLE_BLOCK {
+                        self.reading = None;
+                    }
+                }
+                None => self.respond(&[self.r1(R1_ADDRESS_ERROR)]),
+            },
+            CMD_WRITE_BLOCK | CMD_WRITE_MULTIPLE_BLOCK => match self.block(argument) {
+                Some(block) => {
+                    self.respond(&[self.r1(0)]);
+                    self.state = State::WriteToken {
+                        block,
+                        multiple: index == CMD_WRITE_MULTIPLE_BLOCK,
+                    };
+                }
+                None => self.respond(&[self.r1(R1_ADDRESS_ERROR)]),
+            },
+            CMD_APP_CMD => {
+                self.app_command = true;
+                self.respond(&[self.r1(0)]);
+            }
+            CMD_READ_OCR => {
+                let mut ocr = OCR;
+                if self.sdhc {
+                    ocr |= OCR_CCS;
+                }
+                if self.idle {
+                    ocr &= !0x8000_0000;
+                }
+
+                let r1 = self.r1(0);
+                self.respond(&[r1]);
+                self.response.extend(ocr.to_be_bytes());
+            }
+            CMD_CRC_ON_OFF => self.respond(&[self.r1(0)]),
+            _ => {
+                warn!("SD card: unhandled command CMD{} {:#010x}", index, argument);
+                self.respond(&[self.r1(R1_ILLEGAL_COMMAND)]);
+            }
+        }
+    }
+
+    fn execute_app_command(&mut self, index: u8) {
+        match index {
+            ACMD_SD_STATUS => {
+                self.respond(&[self.r1(0), 0]);
+                self.respond_data(&[0; 64]);
+            }
+            ACMD_SD_SEND_OP_COND => {
+                self.idle = false;
+                self.respond(&[0]);
+            }
+            ACMD_SET_WR_BLK_ERASE_COUNT | ACMD_SET_CLR_CARD_DETECT => self.respond(&[self.r1(0)]),
+            ACMD_SEND_SCR => {
+                self.respond(&[self.r1(0)]);
+                // SD spec 2.00, 1 and 4 bit bus widths
+                self.respond_data(&[0x02, 0x35, 0x80, 0, 0, 0, 0, 0]);
+            }
+            _ => {
+                warn!("SD card: unhandled command ACMD{}", index);
+                self.respond(&[self.r1(R1_ILLEGAL_COMMAND)]);
+            }
+        }
+    }
+
+    fn receive(&mut self, byte: u8) {
+        match self.state {
+            State::Command => {
+                // commands start with a 0 start bit and a 1 transmission bit
+                if self.command.is_empty() && byte & 0xC0 != 0x40 {
+                    return;
+                }
+
+                self.command.push(byte);
+
+                if self.command.len() == COMMAND_SIZE {
+                    self.execute();
+                }
+            }
+            State::WriteToken { block, multiple } => match byte {
+                TOKEN_START_BLOCK if !multiple => {
+                    self.data.clear();
+                    self.state = State::WriteData { block, multiple };
+                }
+                TOKEN_START_MULTIPLE_WRITE if multiple => {
+                    self.data.clear();
+                    self.state = State::WriteData { block, multiple };
+                }
+                TOKEN_STOP_TRANSMISSION if multiple => {
+                    self.response.extend([0xFF, 0x00]);
+                    self.state = State::Command;
+                }
+                _ => (),
+            },
+            State::WriteData { block, multiple } => {
+                self.data.push(byte);
+
+                // data block followed by its CRC
+                if self.data.len() < BLOCK_SIZE + 2 {
+                    return;
+                }
+
+                let data = std::mem::take(&mut self.data);
+                let token = match self.write_block(block, &data[..BLOCK_SIZE]) {
+                    Ok(()) => DATA_ACCEPTED,
+                    Err(e) => {
+                        error!("SD card: unable to write block {}: {}", block, e);
+                        DATA_WRITE_ERROR
+                    }
+                };
+                self.data = data;
+
+                // data response followed by busy
+                self.response.extend([token, 0x00]);
+
+                self.state = if multiple && block + 1 < self.blocks && token == DATA_ACCEPTED {
+                    State::WriteToken {
+                        block: block + 1,
+                        multiple,
+                    }
+                } else {
+                    State::Command
+                };
+            }
+        }
+    }
+}
+
+impl Device for DeviceSdGecko {
+    fn device_select(&mut self) {}
+
+    fn transfer_byte(&mut self, byte: &mut u8) {
+        if self.response.is_empty() {
+            if let Some(block) = self.reading {
+                self.queue_read(block);
+            }
+        }
+
+        let output = self.response.pop_front().unwrap_or(0xFF);
+
+        self.receive(*byte);
+
+        *byte = output;
+    }
+
+    fn imm_read(&mut self, len: u8) -> u32 {
+        // the host shifts out ones while reading
+        self.imm_read_write(0xFFFF_FFFF, len)
+    }
+
+    fn dma_read(&mut self, mem: &mut Memory, address: u32, len: u32) {
+        for i in 0..len {
+            let mut byte = 0xFF;
+            self.transfer_byte(&mut byte);
+            mem.write_u8(address + i, byte);
+        }
+    }
+}
+
+/// Card register assembled from bit fields, most significant bit first
+#[derive(Default)]
+struct Bits(u128);
+
+impl Bits {
+    fn set(&mut self, lsb: u32, width: u32, value: u64) {
+        let mask = (1u128 << width) - 1;
+        self.0 |= (value as u128 & mask) << lsb;
+    }
+
+    /// Register bytes with the CRC7 and end bit
+    fn finish(self) -> [u8; 16] {
+        let mut bytes = self.0.to_be_bytes();
+        bytes[15] = crc7(&bytes[..15]) << 1 | 1;
+        bytes
+    }
+}
+
+fn crc7(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+
+    for byte in data {
+        for bit in (0..8).rev() {
+            let feedback = ((byte >> bit) & 1) ^ (crc >> 6);
+            crc = (crc << 1) & 0x7F;
+            if feedback != 0 {
+                crc ^= 0x09;
+            }
+        }
+    }
+
+    crc
+}
+
+/// CRC-16-CCITT of data blocks
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn write(sd: &mut DeviceSdGecko, bytes: &[u8]) {
+        for mut byte in bytes.iter().copied() {
+            sd.transfer_byte(&mut byte);
+        }
+    }
+
+    fn command(sd: &mut DeviceSdGecko, index: u8, argument: u32) {
+        let mut bytes = vec![0x40 | index];
+        bytes.extend(argument.to_be_bytes());
+        bytes.push(0x95);
+
+        write(sd, &bytes);
+    }
+
+    fn read(sd: &mut DeviceSdGecko, len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|_| {
+                let mut byte = 0xFF;
+                sd.transfer_byte(&mut byte);
+                byte
+            })
+            .collect()
+    }
+
+    /// Skip bytes until the first one that isn't 0xFF
+    fn response(sd: &mut DeviceSdGecko) -> u8 {
+        (0..16)
+            .map(|_| read(sd, 1)[0])
+            .find(|byte| *byte != 0xFF)
+            .unwrap()
+    }
+
+    #[test]
+    fn crc() {
+        // CMD0 with its well known CRC
+        assert_eq!(crc7(&[0x40, 0, 0, 0, 0]) << 1 | 1, 0x95);
+        assert_eq!(crc16(&[0xFF; 512]), 0x7FA1);
+    }
+
+    #[test]
+    fn init_read_write() {
+        let path = std::env::temp_dir().join(format!("rustcube-sd-{}.img", std::process::id()));
+        let mut image = vec![0; 64 * BLOCK_SIZE];
+        image[3 * BLOCK_SIZE..4 * BLOCK_SIZE].fill(0xAB);
+        fs::write(&path, &image).unwrap();
+
+        let mut sd = DeviceSdGecko::open(&path).unwrap();
+
+        command(&mut sd, CMD_GO_IDLE_STATE, 0);
+        assert_eq!(response(&mut sd), R1_IDLE);
+
+        command(&mut sd, CMD_SEND_IF_COND, 0x1AA);
+        assert_eq!(response(&mut sd), R1_IDLE);
+        assert_eq!(read(&mut sd, 4), [0, 0, 0x01, 0xAA]);
+
+        command(&mut sd, CMD_APP_CMD, 0);
+        assert_eq!(response(&mut sd), R1_IDLE);
+        command(&mut sd, ACMD_SD_SEND_OP_COND, 0x4000_0000);
+        assert_eq!(response(&mut sd), 0);
+
+        // SDSC cards are byte addressed
+        command(&mut sd, CMD_READ_SINGLE_BLOCK, 3 * BLOCK_SIZE as u32);
+        assert_eq!(response(&mut sd), 0);
+        assert_eq!(response(&mut sd), TOKEN_START_BLOCK);
+        let data = read(&mut sd, BLOCK_SIZE + 2);
+        assert_eq!(data[..BLOCK_SIZE], [0xAB; BLOCK_SIZE]);
+        assert_eq!(data[BLOCK_SIZE..], crc16(&[0xAB; BLOCK_SIZE]).to_be_bytes());
+
+        command(&mut sd, CMD_WRITE_BLOCK, 5 * BLOCK_SIZE as u32);
+        assert_eq!(response(&mut sd), 0);
+        read(&mut sd, 1);
+        write(&mut sd, &[TOKEN_START_BLOCK]);
+        write(
+            &mut sd,
+            &(0..BLOCK_SIZE + 2).map(|i| i as u8).collect::<Vec<_>>(),
+        );
+        assert_eq!(response(&mut sd) & 0x1F, DATA_ACCEPTED);
+
+        command(&mut sd, CMD_READ_SINGLE_BLOCK, 0x1001);
+        assert_eq!(response(&mut sd), R1_ADDRESS_ERROR);
+
+        drop(sd);
+        let image = fs::read(&path).unwrap();
+        assert_eq!(image[5 * BLOCK_SIZE + 7], 7);
+        assert_eq!(image[6 * BLOCK_SIZE], 0);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,327 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use super::RTC_SIZE;
+use crate::config::{Language, SoundMode, VideoMode};
+
+/// SRAM size, not counting the RTC in front of it
+pub(super) const SRAM_SIZE: usize = 0x40;
+
+const SRAM_CHECKSUM: usize = 0x00;
+const SRAM_CHECKSUM_INV: usize = 0x02;
+const SRAM_COUNTER_BIAS: usize = 0x0C;
+const SRAM_DISPLAY_OFFSET: usize = 0x10;
+const SRAM_NTD: usize = 0x11;
+const SRAM_LANGUAGE: usize = 0x12;
+const SRAM_FLAGS: usize = 0x13;
+const SRAM_FLASH_ID: usize = 0x14;
+const SRAM_WIRELESS_KEYBOARD_ID: usize = 0x2C;
+const SRAM_WIRELESS_PAD_ID: usize = 0x30;
+const SRAM_LAST_DVD_ERROR: usize = 0x38;
+
+/// The checksums cover the counter bias and the display, language and flag settings
+const SRAM_CHECKSUM_RANGE: std::ops::Range<usize> = 0x0C..0x14;
+
+const NTD_PAL60: u8 = 0x40;
+
+const FLAGS_VIDEO_MODE: u8 = 0x03;
+const FLAGS_SOUND_STEREO: u8 = 0x04;
+const FLAGS_PROGRESSIVE_SCAN: u8 = 0x80;
+
+/// Battery backed settings read by the IPL and games. Setters recompute the checksum and
+/// inverted checksum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sram {
+    data: [u8; SRAM_SIZE],
+}
+
+impl Default for Sram {
+    /// Contents the emulator powers on with, formatted for the default memory cards
+    fn default() -> Self {
+        Sram {
+            data: [
+                0xFF, 0x6B, // checksum 1
+                0x00, 0x91, // checksum 2
+                0x00, 0x00, 0x00, 0x00, // ead 0
+                0x00, 0x00, 0x00, 0x00, // ead 1
+                0x00, 0x00, 0x00, 0x00, // counter bias
+                0x00, // display offset h
+                0x00, // ntd
+                0x00, // language
+                0x2C, // flags
+                0x44, 0x4F, 0x4C, 0x50, 0x48, 0x49, 0x4E, 0x53, 0x4C, 0x4F, 0x54,
+                0x41, // flash id
+                0x44, 0x4F, 0x4C, 0x50, 0x48, 0x49, 0x4E, 0x53, 0x4C, 0x4F, 0x54,
+                0x42, // flash id
+                0x00, 0x00, 0x00, 0x00, // wireless keyboard id
+                0x00, 0x00, // wireless pad id
+                0x00, 0x00, // wireless pad id
+                0x00, 0x00, // wireless pad id
+                0x00, 0x00, // wireless pad id
+                0x00, // last dvd error code
+                0x00, // padding
+                0x6E, 0x6D, // flash id checksum
+                0x00, 0x00, // flash id checksum
+                0x00, 0x00, // padding
+            ],
+        }
+    }
+}
+
+impl Sram {
+    pub fn from_bytes(data: &[u8]) -> Sram {
+        let mut sram = Sram {
+            data: [0; SRAM_SIZE],
+        };
+        sram.data.copy_from_slice(&data[..SRAM_SIZE]);
+        sram
+    }
+
+    /// Read a file holding the RTC followed by SRAM, as the SRAM.raw files of other emulators
+    /// do. The RTC is ignored and invalid checksums are fixed.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Sram, Error> {
+        let path = path.as_ref();
+        let data = fs::read(path)?;
+
+        if data.len() != RTC_SIZE + SRAM_SIZE {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: invalid SRAM size", path.display()),
+            ));
+        }
+
+        let mut sram = Sram::from_bytes(&data[RTC_SIZE..]);
+        if !sram.checksums_valid() {
+            warn!("{}: invalid SRAM checksums, fixing", path.display());
+            sram.fix_checksums();
+        }
+
+        Ok(sram)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Seconds added to the RTC to get the time shown by the IPL
+    pub fn counter_bias(&self) -> u32 {
+        u32::from_be_bytes(
+            self.data[SRAM_COUNTER_BIAS..SRAM_COUNTER_BIAS + 4]
+                .try_into()
+                .unwrap(),
+        )
+    }
+
+    pub fn set_counter_bias(&mut self, bias: u32) {
+        self.write(SRAM_COUNTER_BIAS, &bias.to_be_bytes());
+    }
+
+    /// Horizontal display offset set in the IPL menu
+    pub fn display_offset(&self) -> i8 {
+        self.data[SRAM_DISPLAY_OFFSET] as i8
+    }
+
+    pub fn set_display_offset(&mut self, offset: i8) {
+        self.write(SRAM_DISPLAY_OFFSET, &[offset as u8]);
+    }
+
+    pub fn language(&self) -> Language {
+        match self.data[SRAM_LANGUAGE] {
+            1 => Language::German,
+            2 => Language::French,
+            3 => Language::Spanish,
+            4 => Language::Italian,
+            5 => Language::Dutch,
+            _ => Language::English,
+        }
+    }
+
+    pub fn set_language(&mut self, language: Language) {
+        self.write(SRAM_LANGUAGE, &[language as u8]);
+    }
+
+    pub fn video_mode(&self) -> VideoMode {
+        match self.data[SRAM_FLAGS] & FLAGS_VIDEO_MODE {
+            1 => VideoMode::Pal,
+            2 => VideoMode::Mpal,
+            _ => VideoMode::Ntsc,
+        }
+    }
+
+    pub fn set_video_mode(&mut self, mode: VideoMode) {
+        let flags = (self.data[SRAM_FLAGS] & !FLAGS_VIDEO_MODE) | mode as u8;
+        self.write(SRAM_FLAGS, &[flags]);
+    }
+
+    /// PAL games run in 60Hz mode (EURGB60)
+    pub fn pal60(&self) -> bool {
+        self.data[SRAM_NTD] & NTD_PAL60 != 0
+    }
+
+    pub fn set_pal60(&mut self, enabled: bool) {
+        self.set_bit(SRAM_NTD, NTD_PAL60, enabled);
+    }
+
+    /// Games offer progressive scan output
+    pub fn progressive_scan(&self) -> bool {
+        self.data[SRAM_FLAGS] & FLAGS_PROGRESSIVE_SCAN != 0
+    }
+
+    pub fn set_progressive_scan(&mut self, enabled: bool) {
+        self.set_bit(SRAM_FLAGS, FLAGS_PROGRESSIVE_SCAN, enabled);
+    }
+
+    pub fn sound_mode(&self) -> SoundMode {
+        if self.data[SRAM_FLAGS] & FLAGS_SOUND_STEREO != 0 {
+            SoundMode::Stereo
+        } else {
+            SoundMode::Mono
+        }
+    }
+
+    pub fn set_sound_mode(&mut self, mode: SoundMode) {
+        self.set_bit(SRAM_FLAGS, FLAGS_SOUND_STEREO, mode == SoundMode::Stereo);
+    }
+
+    /// Flash id of the memory card formatted in slot A (0) or B (1)
+    pub fn flash_id(&self, slot: usize) -> [u8; 12] {
+        let offset = SRAM_FLASH_ID + slot * 12;
+
+        let mut flash_id = [0; 12];
+        flash_id.copy_from_slice(&self.data[offset..offset + 12]);
+        flash_id
+    }
+
+    pub fn wireless_keyboard_id(&self) -> u32 {
+        u32::from_be_bytes(
+            self.data[SRAM_WIRELESS_KEYBOARD_ID..SRAM_WIRELESS_KEYBOARD_ID + 4]
+                .try_into()
+                .unwrap(),
+        )
+    }
+
+    pub fn set_wireless_keyboard_id(&mut self, id: u32) {
+        self.write(SRAM_WIRELESS_KEYBOARD_ID, &id.to_be_bytes());
+    }
+
+    /// Id of the WaveBird paired with controller port `pad` (0 - 3)
+    pub fn wireless_pad_id(&self, pad: usize) -> u16 {
+        let offset = SRAM_WIRELESS_PAD_ID + pad * 2;
+        u16::from_be_bytes([self.data[offset], self.data[offset + 1]])
+    }
+
+    pub fn set_wireless_pad_id(&mut self, pad: usize, id: u16) {
+        self.write(SRAM_WIRELESS_PAD_ID + pad * 2, &id.to_be_bytes());
+    }
+
+    /// Error code of the last disc error, shown by the IPL
+    pub fn last_dvd_error(&self) -> u8 {
+        self.data[SRAM_LAST_DVD_ERROR]
+    }
+
+    pub fn set_last_dvd_error(&mut self, error: u8) {
+        self.write(SRAM_LAST_DVD_ERROR, &[error]);
+    }
+
+    pub fn checksums_valid(&self) -> bool {
+        let (checksum, checksum_inv) = self.checksums();
+
+        self.data[SRAM_CHECKSUM..SRAM_CHECKSUM + 2] == checksum.to_be_bytes()
+            && self.data[SRAM_CHECKSUM_INV..SRAM_CHECKSUM_INV + 2] == checksum_inv.to_be_bytes()
+    }
+
+    pub fn fix_checksums(&mut self) {
+        let (checksum, checksum_inv) = self.checksums();
+
+        self.data[SRAM_CHECKSUM..SRAM_CHECKSUM + 2].copy_from_slice(&checksum.to_be_bytes());
+        self.data[SRAM_CHECKSUM_INV..SRAM_CHECKSUM_INV + 2]
+            .copy_from_slice(&checksum_inv.to_be_bytes());
+    }
+
+    /// 16-bit sums of the checksummed words and of their inverse
+    fn checksums(&self) -> (u16, u16) {
+        let mut checksum: u16 = 0;
+        let mut checksum_inv: u16 = 0;
+
+        for word in self.data[SRAM_CHECKSUM_RANGE].chunks_exact(2) {
+            let word = u16::from_be_bytes([word[0], word[1]]);
+
+            checksum = checksum.wrapping_add(word);
+            checksum_inv = checksum_inv.wrapping_add(!word);
+        }
+
+        (checksum, checksum_inv)
+    }
+
+    fn set_bit(&mut self, offset: usize, mask: u8, enabled: bool) {
+        let value = if enabled {
+            self.data[offset] | mask
+        } else {
+            self.data[offset] & !mask
+        };
+        self.write(offset, &[value]);
+    }
+
+    fn write(&mut self, offset: usize, bytes: &[u8]) {
+        self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        self.fix_checksums();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings() {
+        let mut sram = Sram::from_bytes(&[0; SRAM_SIZE]);
+        sram.fix_checksums();
+        assert!(sram.checksums_valid());
+
+        sram.set_language(Language::French);
+        sram.set_display_offset(-8);
+        sram.set_video_mode(VideoMode::Pal);
+        sram.set_pal60(true);
+        sram.set_progressive_scan(true);
+        sram.set_sound_mode(SoundMode::Stereo);
+        sram.set_wireless_pad_id(3, 0x1234);
+
+        assert!(sram.checksums_valid());
+        assert_eq!(sram.language(), Language::French);
+        assert_eq!(sram.display_offset(), -8);
+        assert_eq!(sram.video_mode(), VideoMode::Pal);
+        assert!(sram.pal60());
+        assert!(sram.progressive_scan());
+        assert_eq!(sram.sound_mode(), SoundMode::Stereo);
+        assert_eq!(sram.wireless_pad_id(3), 0x1234);
+        assert_eq!(sram.as_bytes()[SRAM_FLAGS], 0x85);
+
+        sram.set_video_mode(VideoMode::Ntsc);
+        sram.set_progressive_scan(false);
+        assert_eq!(sram.as_bytes()[SRAM_FLAGS], 0x04);
+    }
+
+    #[test]
+    fn load() {
+        let path = std::env::temp_dir().join(format!("rustcube-sram-{}", std::process::id()));
+
+        let mut data = vec![0; RTC_SIZE + SRAM_SIZE];
+        data[RTC_SIZE + SRAM_LANGUAGE] = 3;
+        data[RTC_SIZE + SRAM_FLASH_ID + 12..RTC_SIZE + SRAM_FLASH_ID + 24]
+            .copy_from_slice(b"0123456789AB");
+        fs::write(&path, &data).unwrap();
+
+        let sram = Sram::load(&path).unwrap();
+        assert!(sram.checksums_valid());
+        assert_eq!(sram.language(), Language::Spanish);
+        assert_eq!(&sram.flash_id(1), b"0123456789AB");
+
+        fs::write(&path, &data[RTC_SIZE..]).unwrap();
+        assert!(Sram::load(&path).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
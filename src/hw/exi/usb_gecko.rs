@@ -0,0 +1,200 @@
+use std::{
+    collections::VecDeque,
+    io::{Error, ErrorKind, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+};
+
+use super::Device;
+
+/// Base TCP port, slot A listens on this port and slot B on the next
+pub const USB_GECKO_PORT: u16 = 55020;
+
+const COMMAND_LED_OFF: u32 = 0x7;
+const COMMAND_LED_ON: u32 = 0x8;
+const COMMAND_INIT: u32 = 0x9;
+const COMMAND_RECEIVE: u32 = 0xA;
+const COMMAND_SEND: u32 = 0xB;
+const COMMAND_CHECK_TX: u32 = 0xC;
+const COMMAND_CHECK_RX: u32 = 0xD;
+
+/// Cpu ticks between polls of the socket, about a millisecond
+const POLL_TICKS: u64 = 486_000;
+
+const USB_GECKO_ID: u32 = 0x0470_0000;
+/// Set in replies when a byte was sent/received or the FIFO is ready
+const ACK: u32 = 0x0400_0000;
+const RECEIVED: u32 = 0x0800_0000;
+
+/// USB Gecko adapter in a memory card slot. Its FIFO is bridged to a TCP connection on
+/// localhost: bytes sent by software are written to the connected client and bytes from the
+/// client are received by software.
+pub struct DeviceUsbGecko {
+    listener: TcpListener,
+    client: Option<TcpStream>,
+    receive_fifo: VecDeque<u8>,
+    send_fifo: Vec<u8>,
+    /// Cpu ticks since the socket was last polled
+    ticks: u64,
+}
+
+impl DeviceUsbGecko {
+    pub fn new(port: u16) -> Result<DeviceUsbGecko, Error> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        listener.set_nonblocking(true)?;
+
+        info!("USB Gecko listening on {}", listener.local_addr()?);
+
+        Ok(DeviceUsbGecko {
+            listener,
+            client: None,
+            receive_fifo: VecDeque::new(),
+            send_fifo: Vec::new(),
+            ticks: 0,
+        })
+    }
+
+    /// Accept a client and move data between it and the FIFOs without blocking
+    fn poll(&mut self) {
+        if self.client.is_none() {
+            match self.listener.accept() {
+                Ok((client, addr)) => {
+                    if let Err(e) = client.set_nonblocking(true) {
+                        error!("USB Gecko: {}", e);
+                        return;
+                    }
+
+                    info!("USB Gecko: {} connected", addr);
+                    self.client = Some(client);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+                Err(e) => {
+                    error!("USB Gecko: {}", e);
+                    return;
+                }
+            }
+        }
+
+        if let Err(e) = self.transfer() {
+            info!("USB Gecko: client disconnected ({})", e);
+            self.client = None;
+        }
+    }
+
+    fn transfer(&mut self) -> Result<(), Error> {
+        let Some(client) = self.client.as_mut() else {
+            return Ok(());
+        };
+
+        let mut buf = [0; 1024];
+
+        loop {
+            match client.read(&mut buf) {
+                Ok(0) => return Err(Error::from(ErrorKind::ConnectionAborted)),
+                Ok(n) => self.receive_fifo.extend(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        while !self.send_fifo.is_empty() {
+            match client.write(&self.send_fifo) {
+                Ok(n) => {
+                    self.send_fifo.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Device for DeviceUsbGecko {
+    fn device_select(&mut self) {}
+
+    fn update(&mut self, ticks: u64) {
+        self.ticks += ticks;
+
+        if self.ticks >= POLL_TICKS {
+            self.ticks = 0;
+            self.poll();
+        }
+    }
+
+    /// Commands are in the top nibble of a 16-bit read/write transfer
+    fn imm_read_write(&mut self, value: u32, _len: u8) -> u32 {
+        match value >> 28 {
+            COMMAND_LED_OFF | COMMAND_LED_ON => value,
+            COMMAND_INIT => USB_GECKO_ID,
+            COMMAND_RECEIVE => match self.receive_fifo.pop_front() {
+                Some(byte) => RECEIVED | (byte as u32) << 16,
+                None => 0,
+            },
+            COMMAND_SEND => {
+                self.send_fifo.push((value >> 20) as u8);
+                ACK
+            }
+            COMMAND_CHECK_TX => ACK,
+            COMMAND_CHECK_RX if !self.receive_fifo.is_empty() => ACK,
+            COMMAND_CHECK_RX => 0,
+            command => {
+                warn!("USB Gecko: unknown command {:#x}", command);
+                0
+            }
+        }
+    }
+
+    fn imm_read(&mut self, len: u8) -> u32 {
+        self.imm_read_write(0, len)
+    }
+
+    fn imm_write(&mut self, value: u32, len: u8) {
+        self.imm_read_write(value, len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bridge() {
+        let mut gecko = DeviceUsbGecko::new(0).unwrap();
+        let mut client = TcpStream::connect(gecko.listener.local_addr().unwrap()).unwrap();
+
+        assert_eq!(gecko.imm_read_write(COMMAND_INIT << 28, 2), USB_GECKO_ID);
+        assert_eq!(gecko.imm_read_write(COMMAND_CHECK_RX << 28, 2), 0);
+
+        client.write_all(b"hi").unwrap();
+
+        // wait for the client to be accepted and its data to arrive
+        for _ in 0..100 {
+            gecko.update(POLL_TICKS);
+            if gecko.receive_fifo.len() == 2 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        assert_eq!(gecko.imm_read_write(COMMAND_CHECK_RX << 28, 2), ACK);
+        assert_eq!(
+            gecko.imm_read_write(COMMAND_RECEIVE << 28, 2),
+            RECEIVED | (b'h' as u32) << 16
+        );
+        assert_eq!(
+            gecko.imm_read_write(COMMAND_RECEIVE << 28, 2),
+            RECEIVED | (b'i' as u32) << 16
+        );
+
+        assert_eq!(
+            gecko.imm_read_write(COMMAND_SEND << 28 | (b'!' as u32) << 20, 2),
+            ACK
+        );
+        gecko.update(POLL_TICKS);
+
+        let mut buf = [0; 1];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"!");
+    }
+}
@@ -21,6 +21,11 @@ impl Default for Memory {
 }
 
 impl Memory {
+    /// Zero all of main memory
+    pub fn clear(&mut self) {
+        self.data.fill(0);
+    }
+
     pub fn read_u8(&self, addr: u32) -> u8 {
         self.data[addr as usize]
     }
@@ -0,0 +1,293 @@
+use crate::{
+    bus::Bus,
+    cpu::CpuState,
+    hw::{
+        mmio::{Mmio, MmioDevice},
+        pi::{ProcessorInterface, PI_INTERRUPT_MEM},
+    },
+};
+
+const MI_PROTECTED_REGION: u32 = 0x00;
+const MI_PROTECTION_TYPE: u32 = 0x10;
+const MI_INTERRUPT_MASK: u32 = 0x1C;
+const MI_INTERRUPT_CAUSE: u32 = 0x1E;
+const MI_INTERRUPT_ACK: u32 = 0x20;
+const MI_ADDRESS_LOW: u32 = 0x22;
+const MI_ADDRESS_HIGH: u32 = 0x24;
+const MI_COUNTERS: u32 = 0x32;
+
+const NUM_REGIONS: usize = 4;
+/// Region boundaries are in 1KB pages
+const PAGE_SHIFT: u32 = 10;
+
+/// Bits of a region's protection type, the accesses allowed to it
+const PROTECTION_READ: u16 = 0x1;
+const PROTECTION_WRITE: u16 = 0x2;
+const PROTECTION_READ_WRITE: u16 = PROTECTION_READ | PROTECTION_WRITE;
+
+/// Performance counters: CP, TC, CPU read, CPU write, DSP, IO, VI, PE, RF and FI requests.
+/// Only the CPU counters are counted, the others are never incremented by the devices and only
+/// hold what software writes to them.
+const NUM_COUNTERS: usize = 10;
+const COUNTER_CPU_READ: usize = 2;
+const COUNTER_CPU_WRITE: usize = 3;
+
+#[derive(Clone, Copy, Default)]
+struct Region {
+    first_page: u16,
+    last_page: u16,
+}
+
+impl Region {
+    /// Pages from the first up to, not including, the last
+    fn contains(&self, addr: u32) -> bool {
+        let page = addr >> PAGE_SHIFT;
+        page >= self.first_page as u32 && page < self.last_page as u32
+    }
+}
+
+/// Memory Interface, protects regions of main memory and counts cpu memory requests
+#[derive(Default)]
+pub struct MemoryInterface {
+    regions: [Region; NUM_REGIONS],
+    /// Allowed accesses to each region, 2 bits per region
+    protection_type: u16,
+    interrupt_mask: u16,
+    interrupt_cause: u16,
+    /// Address of the last violation
+    address: u32,
+    counters: [u32; NUM_COUNTERS],
+    /// Any region restricts accesses
+    protecting: bool,
+}
+
+impl MemoryInterface {
+    /// Count a load or store instruction's access of main memory, raising an interrupt when it
+    /// violates the protection of a region
+    pub fn access(bus: &mut Bus, cpu_state: &mut CpuState, addr: u32, write: bool) {
+        let mi = &mut bus.mi;
+
+        let counter = if write {
+            COUNTER_CPU_WRITE
+        } else {
+            COUNTER_CPU_READ
+        };
+        mi.counters[counter] = mi.counters[counter].wrapping_add(1);
+
+        if !mi.protecting {
+            return;
+        }
+
+        let allowed = if write {
+            PROTECTION_WRITE
+        } else {
+            PROTECTION_READ
+        };
+
+        let mut cause = 0;
+        for (n, region) in mi.regions.iter().enumerate() {
+            if mi.region_protection(n) & allowed == 0 && region.contains(addr) {
+                cause |= 1 << n;
+            }
+        }
+
+        if cause != 0 {
+            debug!(
+                "MI: protected {} at {:#010x}",
+                if write { "write" } else { "read" },
+                addr
+            );
+
+            mi.interrupt_cause |= cause;
+            mi.address = addr;
+            Self::update_interrupts(bus, cpu_state);
+        }
+    }
+
+    fn region_protection(&self, region: usize) -> u16 {
+        (self.protection_type >> (region * 2)) & PROTECTION_READ_WRITE
+    }
+
+    fn update_protecting(&mut self) {
+        self.protecting = (0..NUM_REGIONS).any(|n| {
+            let region = self.regions[n];
+            self.region_protection(n) != PROTECTION_READ_WRITE
+                && region.first_page < region.last_page
+        });
+    }
+
+    pub fn update_interrupts(bus: &mut Bus, cpu_state: &mut CpuState) {
+        if bus.mi.interrupt_cause & bus.mi.interrupt_mask != 0 {
+            ProcessorInterface::set_interrupt(bus, cpu_state, PI_INTERRUPT_MEM);
+        } else {
+            ProcessorInterface::clear_interrupt(bus, cpu_state, PI_INTERRUPT_MEM);
+        }
+    }
+}
+
+impl MmioDevice for MemoryInterface {
+    const BASE_ADDR: u32 = 0x0C00_4000;
+
+    fn register_mmio(mmio: &mut Mmio) {
+        for n in 0..NUM_REGIONS as u32 {
+            let first = Self::BASE_ADDR + MI_PROTECTED_REGION + n * 4;
+
+            mmio.register_u16(
+                first,
+                |bus, _, addr| bus.mi.regions[region_index(addr)].first_page,
+                |bus, _, addr, val| {
+                    bus.mi.regions[region_index(addr)].first_page = val;
+                    bus.mi.update_protecting();
+                },
+            );
+            mmio.register_u16(
+                first + 2,
+                |bus, _, addr| bus.mi.regions[region_index(addr)].last_page,
+                |bus, _, addr, val| {
+                    bus.mi.regions[region_index(addr)].last_page = val;
+                    bus.mi.update_protecting();
+                },
+            );
+        }
+
+        mmio.register_u16(
+            Self::BASE_ADDR + MI_PROTECTION_TYPE,
+            |bus, _, _| bus.mi.protection_type,
+            |bus, _, _, val| {
+                bus.mi.protection_type = val;
+                bus.mi.update_protecting();
+            },
+        );
+        mmio.register_u16(
+            Self::BASE_ADDR + MI_INTERRUPT_MASK,
+            |bus, _, _| bus.mi.interrupt_mask,
+            |bus, cpu_state, _, val| {
+                bus.mi.interrupt_mask = val;
+                Self::update_interrupts(bus, cpu_state);
+            },
+        );
+        mmio.register_u16(
+            Self::BASE_ADDR + MI_INTERRUPT_CAUSE,
+            |bus, _, _| bus.mi.interrupt_cause,
+            |bus, cpu_state, _, val| {
+                bus.mi.interrupt_cause &= !val;
+                Self::update_interrupts(bus, cpu_state);
+            },
+        );
+        // any write acknowledges all interrupts
+        mmio.register_u16(
+            Self::BASE_ADDR + MI_INTERRUPT_ACK,
+            |_, _, _| 0,
+            |bus, cpu_state, _, _| {
+                bus.mi.interrupt_cause = 0;
+                Self::update_interrupts(bus, cpu_state);
+            },
+        );
+        mmio.register_read_u16(Self::BASE_ADDR + MI_ADDRESS_LOW, |bus, _, _| {
+            bus.mi.address as u16
+        });
+        mmio.register_read_u16(Self::BASE_ADDR + MI_ADDRESS_HIGH, |bus, _, _| {
+            (bus.mi.address >> 16) as u16 & 0x3FF
+        });
+
+        for n in 0..NUM_COUNTERS as u32 {
+            let high = Self::BASE_ADDR + MI_COUNTERS + n * 4;
+
+            mmio.register_u16(
+                high,
+                |bus, _, addr| (bus.mi.counters[counter_index(addr)] >> 16) as u16,
+                |bus, _, addr, val| {
+                    let counter = &mut bus.mi.counters[counter_index(addr)];
+                    *counter = (*counter & 0xFFFF) | (val as u32) << 16;
+                },
+            );
+            mmio.register_u16(
+                high + 2,
+                |bus, _, addr| bus.mi.counters[counter_index(addr)] as u16,
+                |bus, _, addr, val| {
+                    let counter = &mut bus.mi.counters[counter_index(addr)];
+                    *counter = (*counter & 0xFFFF_0000) | val as u32;
+                },
+            );
+        }
+    }
+}
+
+fn region_index(addr: u32) -> usize {
+    ((addr - MemoryInterface::BASE_ADDR - MI_PROTECTED_REGION) / 4) as usize
+}
+
+fn counter_index(addr: u32) -> usize {
+    ((addr - MemoryInterface::BASE_ADDR - MI_COUNTERS) / 4) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::Cpu;
+
+    const BASE: u32 = MemoryInterface::BASE_ADDR;
+    const PI_INTERRUPT_CAUSE: u32 = 0x0C00_3000;
+
+    #[test]
+    fn protected_region() {
+        let mut bus = Bus::default();
+        let mut cpu = Cpu::default();
+        let cpu_state = &mut cpu.state;
+
+        // region 1 covers 0x1000-0x1FFF and is read only, as set up by OSProtectRange
+        bus.write::<u16>(cpu_state, BASE + 0x04, 0x1000 >> 10);
+        bus.write::<u16>(cpu_state, BASE + 0x06, 0x2000 >> 10);
+        bus.write::<u16>(
+            cpu_state,
+            BASE + MI_PROTECTION_TYPE,
+            0xFF & !(PROTECTION_WRITE << 2),
+        );
+        bus.write::<u16>(cpu_state, BASE + MI_INTERRUPT_MASK, 0x2);
+
+        cpu.load::<u32>(&mut bus, 0x1000);
+        cpu.store::<u32>(&mut bus, 0x2000, 0);
+        cpu.store::<u32>(&mut bus, 0x0FFC, 0);
+        // host side writes, e.g. by cheats, are not seen by the Memory Interface
+        cpu.write::<u32>(&mut bus, 0x1000, 0);
+        assert_eq!(
+            bus.read::<u16>(&mut cpu.state, BASE + MI_INTERRUPT_CAUSE),
+            0
+        );
+
+        cpu.store::<u8>(&mut bus, 0x1ABC, 1);
+
+        let cpu_state = &mut cpu.state;
+        assert_eq!(bus.read::<u16>(cpu_state, BASE + MI_INTERRUPT_CAUSE), 0x2);
+        assert_eq!(bus.read::<u16>(cpu_state, BASE + MI_ADDRESS_LOW), 0x1ABC);
+        assert_eq!(bus.read::<u16>(cpu_state, BASE + MI_ADDRESS_HIGH), 0);
+        assert_ne!(
+            bus.read::<u32>(cpu_state, PI_INTERRUPT_CAUSE) & PI_INTERRUPT_MEM,
+            0
+        );
+
+        bus.write::<u16>(cpu_state, BASE + MI_INTERRUPT_ACK, 0);
+        assert_eq!(bus.read::<u16>(cpu_state, BASE + MI_INTERRUPT_CAUSE), 0);
+        assert_eq!(
+            bus.read::<u32>(cpu_state, PI_INTERRUPT_CAUSE) & PI_INTERRUPT_MEM,
+            0
+        );
+    }
+
+    #[test]
+    fn counters() {
+        let mut bus = Bus::default();
+        let mut cpu = Cpu::default();
+
+        let cpu_reads = BASE + MI_COUNTERS + COUNTER_CPU_READ as u32 * 4;
+        bus.write::<u16>(&mut cpu.state, cpu_reads, 0);
+        bus.write::<u16>(&mut cpu.state, cpu_reads + 2, 0xFFFF);
+
+        cpu.load::<u32>(&mut bus, 0x100);
+        cpu.load::<u8>(&mut bus, 0x104);
+        cpu.read::<u32>(&mut bus, 0x108);
+
+        assert_eq!(bus.read::<u16>(&mut cpu.state, cpu_reads), 1);
+        assert_eq!(bus.read::<u16>(&mut cpu.state, cpu_reads + 2), 1);
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     bus::Bus,
-    cpu::CpuState,
+    cpu::{timers::CPU_CLOCK, CpuState},
     hw::{
         gp_fifo::BURST_SIZE,
         mmio::{Mmio, MmioDevice},
@@ -24,7 +24,10 @@ const PI_UNKNOWN: u32 = 0x30;
 // Flipper ID Revision C as per Dolphin Emulator
 const FLIPPER_REV: u32 = 0x2465_00B1;
 
-pub const PI_INTERRUPT_RSWST: u32 = 0x10000; // Reset Switch State (1 when pressed)
+/// How long a tap of the reset button holds it down, half a second
+const RESET_BUTTON_TICKS: u64 = CPU_CLOCK / 2;
+
+pub const PI_INTERRUPT_RSWST: u32 = 0x10000; // Reset Switch State (0 when pressed)
 pub const PI_INTERRUPT_HSP: u32 = 0x02000; // High Speed Port
 pub const PI_INTERRUPT_DEBUG: u32 = 0x01000; // Debug Hardware
 pub const PI_INTERRUPT_CP: u32 = 0x0800; // Command FIFO
@@ -50,6 +53,10 @@ pub struct ProcessorInterface {
     reset: ResetRegister,
     revision: u32,
     unknown: u32,
+    /// Cpu tick the held reset button is released at
+    reset_button_release: Option<u64>,
+    /// A system reset was requested through the reset register, and whether memory is reset
+    system_reset: Option<bool>,
 }
 
 impl Default for ProcessorInterface {
@@ -63,6 +70,8 @@ impl Default for ProcessorInterface {
             reset: Default::default(),
             revision: FLIPPER_REV,
             unknown: 0,
+            reset_button_release: None,
+            system_reset: None,
         }
     }
 }
@@ -73,7 +82,13 @@ impl MmioDevice for ProcessorInterface {
     fn register_mmio(mmio: &mut Mmio) {
         mmio.register_u32(
             Self::BASE_ADDR + PI_INTERRUPT_CAUSE,
-            |bus, _, _| bus.pi.interrupt_cause,
+            |bus, _, _| {
+                if bus.pi.reset_button_release.is_some() {
+                    bus.pi.interrupt_cause
+                } else {
+                    bus.pi.interrupt_cause | PI_INTERRUPT_RSWST
+                }
+            },
             |bus, cpu_state, _, val| {
                 bus.pi.interrupt_cause &= !val;
                 Self::update_interrupts(bus, cpu_state);
@@ -119,11 +134,12 @@ impl MmioDevice for ProcessorInterface {
             |bus, _, _, val| {
                 bus.pi.reset = val.into();
                 info!("PI_RESET_CODE {val:#010x}");
+                // resets are asserted by clearing their bit
                 if !bus.pi.reset.dvd() {
                     bus.di.reset_drive(true);
                 }
                 if !bus.pi.reset.system() {
-                    warn!("PI system reset ignored");
+                    bus.pi.system_reset = Some(!bus.pi.reset.memory());
                 }
             },
         );
@@ -135,6 +151,38 @@ impl MmioDevice for ProcessorInterface {
 }
 
 impl ProcessorInterface {
+    /// Tap the reset button, it is held down for half a second
+    pub fn press_reset_button(bus: &mut Bus, cpu_state: &mut CpuState) {
+        info!("Reset button pressed");
+
+        bus.pi.reset_button_release = Some(cpu_state.timers.get_ticks() + RESET_BUTTON_TICKS);
+        Self::set_interrupt(bus, cpu_state, PI_INTERRUPT_RSW);
+    }
+
+    /// Release the reset button once it has been held long enough
+    pub fn update(bus: &mut Bus, cpu_state: &mut CpuState) {
+        if let Some(release) = bus.pi.reset_button_release {
+            if cpu_state.timers.get_ticks() >= release {
+                bus.pi.reset_button_release = None;
+            }
+        }
+    }
+
+    /// Reset the registers, the reset code written to the reset register is kept for the
+    /// software booted after the reset (`OSGetResetCode`)
+    pub fn reset(&mut self) {
+        *self = ProcessorInterface {
+            reset: self.reset,
+            ..Default::default()
+        };
+    }
+
+    /// System reset requested by software since the last call, `Some(true)` when memory is
+    /// reset too
+    pub fn take_system_reset(&mut self) -> Option<bool> {
+        self.system_reset.take()
+    }
+
     pub fn fifo_write_address(&self) -> u32 {
         self.fifo_write_pointer & FIFO_ADDR_MASK
     }
@@ -219,3 +267,64 @@ impl From<ResetRegister> for u32 {
         s.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: u32 = ProcessorInterface::BASE_ADDR;
+
+    #[test]
+    fn reset_button() {
+        let mut bus = Bus::default();
+        let mut cpu_state = CpuState::default();
+
+        bus.write::<u32>(&mut cpu_state, BASE + PI_INTERRUPT_MASK, PI_INTERRUPT_RSW);
+        assert_eq!(
+            bus.read::<u32>(&mut cpu_state, BASE + PI_INTERRUPT_CAUSE),
+            PI_INTERRUPT_RSWST
+        );
+
+        ProcessorInterface::press_reset_button(&mut bus, &mut cpu_state);
+        assert_eq!(
+            bus.read::<u32>(&mut cpu_state, BASE + PI_INTERRUPT_CAUSE),
+            PI_INTERRUPT_RSW
+        );
+
+        // acknowledged while still held
+        bus.write::<u32>(&mut cpu_state, BASE + PI_INTERRUPT_CAUSE, PI_INTERRUPT_RSW);
+        assert_eq!(
+            bus.read::<u32>(&mut cpu_state, BASE + PI_INTERRUPT_CAUSE),
+            0
+        );
+
+        cpu_state.timers.tick(RESET_BUTTON_TICKS as u32);
+        ProcessorInterface::update(&mut bus, &mut cpu_state);
+        assert_eq!(
+            bus.read::<u32>(&mut cpu_state, BASE + PI_INTERRUPT_CAUSE),
+            PI_INTERRUPT_RSWST
+        );
+    }
+
+    #[test]
+    fn reset_register() {
+        let mut bus = Bus::default();
+        let mut cpu_state = CpuState::default();
+
+        // reset code, nothing asserted
+        bus.write::<u32>(&mut cpu_state, BASE + PI_RESET, 0x8000_0000 | 0x7);
+        assert_eq!(bus.pi.take_system_reset(), None);
+
+        // hot reset keeping memory
+        bus.write::<u32>(&mut cpu_state, BASE + PI_RESET, 0x8000_0000 | 0x6);
+        assert_eq!(bus.pi.take_system_reset(), Some(false));
+        assert_eq!(bus.pi.take_system_reset(), None);
+        assert_eq!(
+            bus.read::<u32>(&mut cpu_state, BASE + PI_RESET),
+            0x8000_0006
+        );
+
+        bus.write::<u32>(&mut cpu_state, BASE + PI_RESET, 0);
+        assert_eq!(bus.pi.take_system_reset(), Some(true));
+    }
+}
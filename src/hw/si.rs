@@ -1,62 +1,427 @@
-use crate::hw::mmio::{Mmio, MmioDevice};
+mod bongos;
+mod controller;
+mod gba;
+mod keyboard;
+mod steering_wheel;
 
+pub use self::{
+    bongos::DeviceBongos,
+    controller::DeviceController,
+    gba::{DeviceGba, GBA_CLOCK_PORT, GBA_DATA_PORT},
+    keyboard::DeviceKeyboard,
+    steering_wheel::DeviceSteeringWheel,
+};
+use crate::{
+    bus::Bus,
+    cpu::{timers::CPU_CLOCK, CpuState},
+    hw::{
+        mmio::{Mmio, MmioDevice},
+        pi::{ProcessorInterface, PI_INTERRUPT_SI},
+    },
+    input::{InputSource, NoInput, PadState, Rumble},
+};
+
+const SI_CHANNEL_OUT: u32 = 0x00;
+const SI_CHANNEL_IN_HIGH: u32 = 0x04;
+const SI_CHANNEL_IN_LOW: u32 = 0x08;
+const SI_CHANNEL_SIZE: u32 = 0x0C;
 const SI_POLL: u32 = 0x30;
 const SI_COMM_CONTROL: u32 = 0x34;
 const SI_STATUS: u32 = 0x38;
 const SI_EXI_CLOCK_LOCK: u32 = 0x3C;
 const SI_IO_BUFFER: u32 = 0x80;
 
-#[derive(Debug, Default)]
+pub const NUM_PORTS: usize = 4;
+const IO_BUFFER_SIZE: usize = 0x80;
+
+/// Per channel status bits, channel 0 in the highest byte
+const STATUS_NO_RESPONSE: u32 = 0x08;
+const STATUS_WRITE: u32 = 0x10;
+const STATUS_READ: u32 = 0x20;
+const STATUS_ERRORS: u32 = 0x0F;
+
+/// Set in the high input word when the device didn't respond
+const IN_HIGH_ERROR_STATUS: u32 = 0x8000_0000;
+const IN_HIGH_ERROR_LATCH: u32 = 0x4000_0000;
+
+/// The SI bus runs at 250kbit/s
+const TICKS_PER_BYTE: u64 = CPU_CLOCK / 250_000 * 8;
+
+/// Cpu ticks between device updates
+const UPDATE_TICKS: u64 = 100;
+
+/// Half line of a field the first poll happens on, polls then repeat every X lines
+const FIRST_POLL_HALF_LINE: u32 = 7 * 2 + 1;
+
+/// Device on one of the four controller ports
+pub trait SiDevice {
+    /// Run the command at the start of `buffer`, replacing it with the response. Returns the
+    /// response length, 0 when the device doesn't respond to the command.
+    fn transfer(&mut self, buffer: &mut [u8], input: &PadState) -> usize;
+
+    /// Response to the poll command in the channel output buffer, the high and low input words
+    fn poll(&mut self, command: u32, input: &PadState) -> Option<(u32, u32)>;
+
+    /// Command sent to every channel by a status register write
+    fn send_command(&mut self, _command: u32) {}
+
+    /// Called periodically with the cpu ticks passed since the last call
+    fn update(&mut self, _ticks: u64) {}
+
+    /// New state of the rumble motor, if it changed since the last call
+    fn rumble(&mut self) -> Option<Rumble> {
+        None
+    }
+}
+
+#[derive(Default)]
+struct Channel {
+    out: u32,
+    in_high: u32,
+    in_low: u32,
+}
+
 pub struct SerialInterface {
+    channels: [Channel; NUM_PORTS],
+    devices: [Option<Box<dyn SiDevice>>; NUM_PORTS],
+    input: Box<dyn InputSource>,
+    /// Input state of each port at the last poll
+    pads: [PadState; NUM_PORTS],
     poll: PollRegister,
     comm_cont_status: CommunicationControlStatusRegister,
     status: StatusRegister,
-    clock_count: u32,
+    exi_clock_lock: ExiClockLockRegister,
+    io_buffer: [u8; IO_BUFFER_SIZE],
+    /// Cpu tick the current communication transfer completes at
+    transfer_end: Option<u64>,
+    next_poll_half_line: u32,
+    /// Polls made in the current field
+    polls: u32,
+    /// Cpu tick the devices were last updated at
+    cpu_ticks: u64,
+}
+
+impl Default for SerialInterface {
+    fn default() -> Self {
+        SerialInterface {
+            channels: Default::default(),
+            // a standard controller in port 1
+            devices: [
+                Some(Box::new(DeviceController::default())),
+                None,
+                None,
+                None,
+            ],
+            input: Box::new(NoInput),
+            pads: Default::default(),
+            poll: Default::default(),
+            comm_cont_status: Default::default(),
+            status: Default::default(),
+            exi_clock_lock: Default::default(),
+            io_buffer: [0; IO_BUFFER_SIZE],
+            transfer_end: None,
+            next_poll_half_line: FIRST_POLL_HALF_LINE,
+            polls: 0,
+            cpu_ticks: 0,
+        }
+    }
+}
+
+impl SerialInterface {
+    /// Reset the channels, keeping the devices and input
+    pub fn reset(&mut self) {
+        let devices = std::mem::take(&mut self.devices);
+        let input = std::mem::replace(&mut self.input, Box::new(NoInput));
+
+        *self = SerialInterface {
+            devices,
+            input,
+            ..Default::default()
+        };
+    }
+
+    /// Source of the controller state returned when the devices are polled
+    pub fn set_input(&mut self, input: Box<dyn InputSource>) {
+        self.input = input;
+    }
+
+    /// Replace the device in `port` (0-3)
+    pub fn set_device(&mut self, port: usize, device: Option<Box<dyn SiDevice>>) {
+        self.devices[port] = device;
+    }
+
+    /// Let the input source know VI started frame `frame`
+    pub fn on_frame(&mut self, frame: u64) {
+        self.input.frame(frame);
+    }
+
+    /// Remove the input source, leaving controllers untouched
+    pub fn take_input(&mut self) -> Box<dyn InputSource> {
+        std::mem::replace(&mut self.input, Box::new(NoInput))
+    }
+
+    fn get_channel(addr: u32) -> usize {
+        ((addr - Self::BASE_ADDR) / SI_CHANNEL_SIZE) as usize
+    }
+
+    /// Called by the video interface on every half line, `half_line` counts from the start of
+    /// the current field
+    pub fn on_half_line(bus: &mut Bus, cpu_state: &mut CpuState, half_line: u32) {
+        if half_line == 0 {
+            bus.si.next_poll_half_line = FIRST_POLL_HALF_LINE;
+            bus.si.polls = 0;
+        }
+
+        let x_lines = bus.si.poll.x_lines();
+
+        if x_lines == 0
+            || half_line != bus.si.next_poll_half_line
+            || bus.si.polls >= bus.si.poll.y_times()
+        {
+            return;
+        }
+
+        bus.si.next_poll_half_line += 2 * x_lines;
+        bus.si.polls += 1;
+
+        Self::poll_channels(bus, cpu_state);
+    }
+
+    /// Send the poll command of each enabled channel and latch the responses
+    fn poll_channels(bus: &mut Bus, cpu_state: &mut CpuState) {
+        let si = &mut bus.si;
+
+        if !(0..NUM_PORTS).any(|c| si.poll.enabled(c)) {
+            return;
+        }
+
+        si.pads = si.input.poll(bus.vi.keys());
+
+        for c in 0..NUM_PORTS {
+            if !si.poll.enabled(c) {
+                continue;
+            }
+
+            let command = si.channels[c].out;
+            let response = si.devices[c]
+                .as_mut()
+                .and_then(|device| device.poll(command, &si.pads[c]));
+
+            Self::report_rumble(si, c, bus.vi.headless());
+
+            match response {
+                Some((high, low)) => {
+                    si.channels[c].in_high = high;
+                    si.channels[c].in_low = low;
+                    si.status.set_channel_flags(c, STATUS_READ);
+                }
+                None => {
+                    si.channels[c].in_high = IN_HIGH_ERROR_STATUS | IN_HIGH_ERROR_LATCH;
+                    si.channels[c].in_low = 0;
+                    si.status.set_channel_flags(c, STATUS_NO_RESPONSE);
+                }
+            }
+        }
+
+        Self::update_interrupts(bus, cpu_state);
+    }
+
+    /// Forward a change of the rumble motor in channel `c` to the input source
+    fn report_rumble(si: &mut SerialInterface, c: usize, log: bool) {
+        let Some(rumble) = si.devices[c].as_mut().and_then(|device| device.rumble()) else {
+            return;
+        };
+
+        if log {
+            info!("SI: port {} rumble {:?}", c + 1, rumble);
+        }
+
+        si.input.rumble(c, rumble);
+    }
+
+    /// Run the communication transfer set up in the control register
+    fn start_transfer(bus: &mut Bus, cpu_state: &mut CpuState) {
+        let si = &mut bus.si;
+        let c = si.comm_cont_status.channel() as usize;
+
+        // a length of 0 transfers the whole buffer
+        let length = |len: u32| {
+            if len == 0 {
+                IO_BUFFER_SIZE
+            } else {
+                len as usize
+            }
+        };
+        let output_length = length(si.comm_cont_status.output_length());
+        let input_length = length(si.comm_cont_status.input_length());
+
+        let mut buffer = si.io_buffer;
+        let response = si.devices[c]
+            .as_mut()
+            .map(|device| device.transfer(&mut buffer[..], &si.pads[c]))
+            .unwrap_or(0);
+
+        Self::report_rumble(si, c, bus.vi.headless());
+
+        if response == 0 {
+            si.status.set_channel_flags(c, STATUS_NO_RESPONSE);
+            si.comm_cont_status.set_comm_error(true);
+        } else {
+            let len = response.min(input_length);
+            si.io_buffer[..len].copy_from_slice(&buffer[..len]);
+            si.comm_cont_status.set_comm_error(false);
+        }
+
+        let ticks = (output_length + input_length) as u64 * TICKS_PER_BYTE;
+        si.transfer_end = Some(cpu_state.timers.get_ticks() + ticks);
+        si.comm_cont_status.set_tstart(true);
+    }
+
+    /// Update the devices and complete communication transfers
+    pub fn update(bus: &mut Bus, cpu_state: &mut CpuState) {
+        let ticks = cpu_state.timers.get_ticks();
+        let elapsed = ticks - bus.si.cpu_ticks;
+        if elapsed >= UPDATE_TICKS {
+            bus.si.cpu_ticks = ticks;
+
+            for device in bus.si.devices.iter_mut().flatten() {
+                device.update(elapsed);
+            }
+        }
+
+        let Some(end) = bus.si.transfer_end else {
+            return;
+        };
+
+        if ticks < end {
+            return;
+        }
+
+        bus.si.transfer_end = None;
+        bus.si.comm_cont_status.set_tstart(false);
+        bus.si.comm_cont_status.set_tc_interrupt(true);
+
+        Self::update_interrupts(bus, cpu_state);
+    }
+
+    pub fn update_interrupts(bus: &mut Bus, cpu_state: &mut CpuState) {
+        let csr = &mut bus.si.comm_cont_status;
+
+        let read_status = (0..NUM_PORTS).any(|c| bus.si.status.channel_flags(c) & STATUS_READ != 0);
+        csr.set_rdst_interrupt(read_status);
+
+        if (csr.rdst_interrupt() && csr.rdst_interrupt_mask())
+            || (csr.tc_interrupt() && csr.tc_interrupt_mask())
+        {
+            ProcessorInterface::set_interrupt(bus, cpu_state, PI_INTERRUPT_SI);
+        } else {
+            ProcessorInterface::clear_interrupt(bus, cpu_state, PI_INTERRUPT_SI);
+        }
+    }
 }
 
 impl MmioDevice for SerialInterface {
     const BASE_ADDR: u32 = 0x0C00_6400;
 
     fn register_mmio(mmio: &mut Mmio) {
-        mmio.register_write_u32(Self::BASE_ADDR + SI_POLL, |bus, _, _, val| {
-            bus.si.poll = val.into()
-        });
+        for channel in 0..NUM_PORTS as u32 {
+            let base = Self::BASE_ADDR + channel * SI_CHANNEL_SIZE;
+
+            mmio.register_u32(
+                base + SI_CHANNEL_OUT,
+                |bus, _, addr| bus.si.channels[Self::get_channel(addr)].out,
+                |bus, _, addr, val| bus.si.channels[Self::get_channel(addr)].out = val,
+            );
+            mmio.register_read_u32(base + SI_CHANNEL_IN_HIGH, |bus, cpu_state, addr| {
+                let c = Self::get_channel(addr);
+
+                // reading the response acknowledges it
+                bus.si.status.clear_channel_flags(c, STATUS_READ);
+                Self::update_interrupts(bus, cpu_state);
+
+                bus.si.channels[c].in_high
+            });
+            mmio.register_read_u32(base + SI_CHANNEL_IN_LOW, |bus, _, addr| {
+                bus.si.channels[Self::get_channel(addr)].in_low
+            });
+        }
+
+        mmio.register_u32(
+            Self::BASE_ADDR + SI_POLL,
+            |bus, _, _| bus.si.poll.0,
+            |bus, _, _, val| bus.si.poll = val.into(),
+        );
         mmio.register_u32(
             Self::BASE_ADDR + SI_COMM_CONTROL,
             |bus, _, _| bus.si.comm_cont_status.into(),
-            |bus, _, _, val| {
-                let mut cont: CommunicationControlStatusRegister = val.into();
+            |bus, cpu_state, _, val| {
+                let new = CommunicationControlStatusRegister::from(val);
+                let csr = &mut bus.si.comm_cont_status;
 
-                if cont.rdstint() {
-                    cont.set_rdstint(false);
-                }
-                if cont.tcint() {
-                    cont.set_tcint(false);
-                }
-                bus.si.comm_cont_status = cont;
+                csr.set_channel(new.channel());
+                csr.set_input_length(new.input_length());
+                csr.set_output_length(new.output_length());
+                csr.set_rdst_interrupt_mask(new.rdst_interrupt_mask());
+                csr.set_tc_interrupt_mask(new.tc_interrupt_mask());
 
-                if cont.tstart() {
-                    warn!("FIXME tstart");
+                // write 1 to clear
+                if new.tc_interrupt() {
+                    csr.set_tc_interrupt(false);
                 }
 
-                if !cont.tstart() {
-                    warn!("Update interrupt");
+                if new.tstart() && !csr.tstart() {
+                    Self::start_transfer(bus, cpu_state);
                 }
+
+                Self::update_interrupts(bus, cpu_state);
             },
         );
         mmio.register_u32(
             Self::BASE_ADDR + SI_STATUS,
             |bus, _, _| bus.si.status.into(),
-            |bus, _, _, val| bus.si.status = val.into(),
+            |bus, _, _, val| {
+                let new = StatusRegister::from(val);
+
+                for c in 0..NUM_PORTS {
+                    // errors are write 1 to clear
+                    let errors = new.channel_flags(c) & STATUS_ERRORS;
+                    bus.si.status.clear_channel_flags(c, errors);
+                }
+
+                // copy the output buffers to the devices
+                if new.write() {
+                    for c in 0..NUM_PORTS {
+                        let command = bus.si.channels[c].out;
+                        if let Some(device) = bus.si.devices[c].as_mut() {
+                            device.send_command(command);
+                        }
+                        bus.si.status.clear_channel_flags(c, STATUS_WRITE);
+                    }
+                }
+            },
         );
         mmio.register_u32(
             Self::BASE_ADDR + SI_EXI_CLOCK_LOCK,
-            |bus, _, _| bus.si.clock_count,
-            |bus, _, _, val| bus.si.clock_count = val,
+            |bus, _, _| bus.si.exi_clock_lock.0,
+            |bus, _, _, val| {
+                let lock = ExiClockLockRegister(val);
+                bus.si.exi_clock_lock.set_lock(lock.lock());
+            },
         );
-        mmio.register_write_u32(Self::BASE_ADDR + SI_IO_BUFFER, |_bus, _, _, _val| {
-            warn!("Warning, ignoring si buffer right now")
-        });
+
+        for offset in (0..IO_BUFFER_SIZE as u32).step_by(4) {
+            mmio.register_u32(
+                Self::BASE_ADDR + SI_IO_BUFFER + offset,
+                |bus, _, addr| {
+                    let offset = (addr - (Self::BASE_ADDR + SI_IO_BUFFER)) as usize;
+                    u32::from_be_bytes(bus.si.io_buffer[offset..offset + 4].try_into().unwrap())
+                },
+                |bus, _, addr, val| {
+                    let offset = (addr - (Self::BASE_ADDR + SI_IO_BUFFER)) as usize;
+                    bus.si.io_buffer[offset..offset + 4].copy_from_slice(&val.to_be_bytes());
+                },
+            );
+        }
     }
 }
 
@@ -70,6 +435,13 @@ bitfield! {
     pub x_lines, _ : 25, 16;
 }
 
+impl PollRegister {
+    /// Channel 0 is enabled by the highest enable bit
+    fn enabled(&self, channel: usize) -> bool {
+        self.enable() & (0x8 >> channel) != 0
+    }
+}
+
 impl From<u32> for PollRegister {
     fn from(v: u32) -> Self {
         PollRegister(v)
@@ -80,17 +452,17 @@ bitfield! {
     #[derive(Copy, Clone, Default)]
     pub struct CommunicationControlStatusRegister(u32);
     impl Debug;
-    pub tstart, _ : 0;
-    pub channel, _ : 2, 1;
-    pub input_length, _ : 14, 8;
-    pub output_length, _ : 22, 16;
+    pub tstart, set_tstart : 0;
+    pub channel, set_channel : 2, 1;
+    pub input_length, set_input_length : 14, 8;
+    pub output_length, set_output_length : 22, 16;
     pub channel_enable, _ : 24;
     pub channel_number, _ : 26, 25;
-    pub rdstint, set_rdstint : 27;   // read status interrupt mask
-    pub reat_interrupt_status, _ : 28; // read status interrupt status
-    pub comm_error, _ : 29;
-    pub transfer_complete_interrupt_mask, _ : 30;
-    pub tcint, set_tcint : 31; // transfer complete interrupt status
+    pub rdst_interrupt_mask, set_rdst_interrupt_mask : 27;
+    pub rdst_interrupt, set_rdst_interrupt : 28;
+    pub comm_error, set_comm_error : 29;
+    pub tc_interrupt_mask, set_tc_interrupt_mask : 30;
+    pub tc_interrupt, set_tc_interrupt : 31;
 }
 
 impl From<u32> for CommunicationControlStatusRegister {
@@ -121,6 +493,24 @@ bitfield! {
     pub write, _ : 31;
 }
 
+impl StatusRegister {
+    fn shift(channel: usize) -> usize {
+        (NUM_PORTS - 1 - channel) * 8
+    }
+
+    fn channel_flags(&self, channel: usize) -> u32 {
+        (self.0 >> Self::shift(channel)) & 0x3F
+    }
+
+    fn set_channel_flags(&mut self, channel: usize, flags: u32) {
+        self.0 |= flags << Self::shift(channel);
+    }
+
+    fn clear_channel_flags(&mut self, channel: usize, flags: u32) {
+        self.0 &= !(flags << Self::shift(channel));
+    }
+}
+
 impl From<u32> for StatusRegister {
     fn from(v: u32) -> Self {
         StatusRegister(v)
@@ -132,3 +522,169 @@ impl From<StatusRegister> for u32 {
         s.0
     }
 }
+
+bitfield! {
+    /// Set by software to keep the EXI clock from being set to 32MHz. Only stored, EXI transfers
+    /// use the clock selected in their status register whatever the lock
+    #[derive(Copy, Clone, Default)]
+    pub struct ExiClockLockRegister(u32);
+    impl Debug;
+    pub lock, set_lock : 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::input::PAD_BUTTON_START;
+    use minifb::Key;
+
+    const BASE: u32 = SerialInterface::BASE_ADDR;
+    const PI_INTERRUPT_CAUSE: u32 = 0x0C00_3000;
+
+    struct FixedInput(PadState);
+
+    impl InputSource for FixedInput {
+        fn poll(&mut self, _keys: &[Key]) -> [PadState; NUM_PORTS] {
+            [self.0; NUM_PORTS]
+        }
+    }
+
+    #[test]
+    fn id_transfer() {
+        let mut bus = Bus::default();
+        let mut cpu_state = CpuState::default();
+
+        // id command to channel 0, 1 byte out and 3 bytes in, transfer complete interrupt
+        bus.write::<u32>(&mut cpu_state, BASE + SI_IO_BUFFER, 0);
+        bus.write::<u32>(
+            &mut cpu_state,
+            BASE + SI_COMM_CONTROL,
+            1 << 30 | 1 << 16 | 3 << 8 | 1,
+        );
+
+        assert_eq!(
+            bus.read::<u32>(&mut cpu_state, BASE + SI_IO_BUFFER) >> 8,
+            0x09_0000
+        );
+        assert_eq!(
+            bus.read::<u32>(&mut cpu_state, BASE + SI_COMM_CONTROL) & 1,
+            1
+        );
+
+        cpu_state.timers.tick(4 * TICKS_PER_BYTE as u32);
+        SerialInterface::update(&mut bus, &mut cpu_state);
+
+        let csr = bus.read::<u32>(&mut cpu_state, BASE + SI_COMM_CONTROL);
+        assert_eq!(csr & 1, 0);
+        assert!(CommunicationControlStatusRegister(csr).tc_interrupt());
+        assert_ne!(
+            bus.read::<u32>(&mut cpu_state, PI_INTERRUPT_CAUSE) & PI_INTERRUPT_SI,
+            0
+        );
+
+        // write 1 to clear
+        bus.write::<u32>(&mut cpu_state, BASE + SI_COMM_CONTROL, csr);
+        assert_eq!(
+            bus.read::<u32>(&mut cpu_state, PI_INTERRUPT_CAUSE) & PI_INTERRUPT_SI,
+            0
+        );
+
+        // no device in channel 1
+        bus.write::<u32>(
+            &mut cpu_state,
+            BASE + SI_COMM_CONTROL,
+            1 << 16 | 3 << 8 | 1 << 1 | 1,
+        );
+        let status = StatusRegister(bus.read::<u32>(&mut cpu_state, BASE + SI_STATUS));
+        assert_eq!(status.channel_flags(1), STATUS_NO_RESPONSE);
+    }
+
+    #[test]
+    fn polling() {
+        let mut bus = Bus::default();
+        let mut cpu_state = CpuState::default();
+
+        bus.si.set_input(Box::new(FixedInput(PadState {
+            buttons: PAD_BUTTON_START,
+            stick_x: 0xFF,
+            trigger_r: 0x40,
+            ..Default::default()
+        })));
+
+        // poll channels 0 and 1 every 10 lines, twice per field, read status interrupt
+        bus.write::<u32>(&mut cpu_state, BASE + SI_CHANNEL_OUT, 0x0040_0300);
+        bus.write::<u32>(&mut cpu_state, BASE + SI_POLL, 10 << 16 | 2 << 8 | 0xC << 4);
+        bus.write::<u32>(&mut cpu_state, BASE + SI_COMM_CONTROL, 1 << 27);
+
+        let polls = (0..200)
+            .filter(|half_line| {
+                SerialInterface::on_half_line(&mut bus, &mut cpu_state, *half_line);
+                let status = StatusRegister(bus.read::<u32>(&mut cpu_state, BASE + SI_STATUS));
+                let polled = status.channel_flags(0) & STATUS_READ != 0;
+
+                if polled {
+                    assert_ne!(
+                        bus.read::<u32>(&mut cpu_state, PI_INTERRUPT_CAUSE) & PI_INTERRUPT_SI,
+                        0
+                    );
+                    let high = bus.read::<u32>(&mut cpu_state, BASE + SI_CHANNEL_IN_HIGH);
+                    let low = bus.read::<u32>(&mut cpu_state, BASE + SI_CHANNEL_IN_LOW);
+                    assert_eq!(high, 0x1080_FF80);
+                    assert_eq!(low, 0x8080_0040);
+                }
+
+                polled
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(polls, [15, 35]);
+        assert_eq!(
+            bus.read::<u32>(&mut cpu_state, PI_INTERRUPT_CAUSE) & PI_INTERRUPT_SI,
+            0
+        );
+
+        // nothing in channel 1
+        let status = StatusRegister(bus.read::<u32>(&mut cpu_state, BASE + SI_STATUS));
+        assert_eq!(status.channel_flags(1), STATUS_NO_RESPONSE);
+    }
+
+    #[test]
+    fn rumble() {
+        struct RumbleInput(Rc<RefCell<Vec<(usize, Rumble)>>>);
+
+        impl InputSource for RumbleInput {
+            fn poll(&mut self, _keys: &[Key]) -> [PadState; NUM_PORTS] {
+                Default::default()
+            }
+
+            fn rumble(&mut self, port: usize, rumble: Rumble) {
+                self.0.borrow_mut().push((port, rumble));
+            }
+        }
+
+        let mut bus = Bus::default();
+        let mut cpu_state = CpuState::default();
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        bus.si.set_input(Box::new(RumbleInput(events.clone())));
+
+        // poll channel 0 once per field
+        bus.write::<u32>(&mut cpu_state, BASE + SI_POLL, 10 << 16 | 1 << 8 | 0x8 << 4);
+
+        for command in [0x0040_0301, 0x0040_0301, 0x0040_0302, 0x0040_0300] {
+            bus.write::<u32>(&mut cpu_state, BASE + SI_CHANNEL_OUT, command);
+
+            for half_line in 0..20 {
+                SerialInterface::on_half_line(&mut bus, &mut cpu_state, half_line);
+            }
+        }
+
+        assert_eq!(
+            *events.borrow(),
+            [(0, Rumble::On), (0, Rumble::HardStop), (0, Rumble::Off)]
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use super::{controller::PAD_USE_ORIGIN, DeviceController, SiDevice};
+use crate::input::{
+    PadState, PAD_BUTTON_A, PAD_BUTTON_B, PAD_BUTTON_START, PAD_BUTTON_X, PAD_BUTTON_Y,
+    PAD_TRIGGER_R,
+};
+
+/// Buttons reported by the bongos, the drums are A, B, X and Y and a clap is R
+const BONGO_BUTTONS: u16 =
+    PAD_BUTTON_A | PAD_BUTTON_B | PAD_BUTTON_X | PAD_BUTTON_Y | PAD_BUTTON_START | PAD_TRIGGER_R;
+
+/// Main stick bytes of a poll response, always centered
+const STICK_CENTERED: u32 = 0x8080;
+
+/// DK Bongos, identify as a controller and answer polls in its format without sticks
+#[derive(Default)]
+pub struct DeviceBongos {
+    controller: DeviceController,
+}
+
+impl SiDevice for DeviceBongos {
+    fn transfer(&mut self, buffer: &mut [u8], input: &PadState) -> usize {
+        self.controller.transfer(buffer, input)
+    }
+
+    fn poll(&mut self, command: u32, input: &PadState) -> Option<(u32, u32)> {
+        self.controller.poll(command, input).map(|(high, low)| {
+            let buttons = ((BONGO_BUTTONS | PAD_USE_ORIGIN) as u32) << 16;
+            (high & buttons | STICK_CENTERED, low)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::PAD_BUTTON_LEFT;
+
+    #[test]
+    fn poll() {
+        let mut bongos = DeviceBongos::default();
+        let input = PadState {
+            buttons: PAD_BUTTON_A | PAD_TRIGGER_R | PAD_BUTTON_LEFT,
+            stick_x: 0x00,
+            stick_y: 0xFF,
+            ..Default::default()
+        };
+
+        let (high, _) = bongos.poll(0x0040_0300, &input).unwrap();
+
+        assert_eq!(
+            high,
+            ((PAD_BUTTON_A | PAD_TRIGGER_R | PAD_USE_ORIGIN) as u32) << 16 | STICK_CENTERED
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use super::SiDevice;
+use crate::input::{PadState, Rumble};
+
+/// Always set in poll responses, the origin has been read
+pub(super) const PAD_USE_ORIGIN: u16 = 0x0080;
+
+const SI_GC_CONTROLLER: u32 = 0x0900_0000;
+
+const COMMAND_ID: u8 = 0x00;
+const COMMAND_DIRECT: u8 = 0x40;
+const COMMAND_ORIGIN: u8 = 0x41;
+const COMMAND_RECALIBRATE: u8 = 0x42;
+const COMMAND_RESET: u8 = 0xFF;
+
+/// Analog mode of the default poll response format
+const ANALOG_MODE_DEFAULT: u32 = 3;
+
+/// Standard controller
+#[derive(Default)]
+pub struct DeviceController {
+    rumble: Rumble,
+    /// The rumble motor changed since it was last reported
+    rumble_changed: bool,
+}
+
+impl DeviceController {
+    fn set_rumble(&mut self, rumble: Rumble) {
+        if rumble != self.rumble {
+            self.rumble = rumble;
+            self.rumble_changed = true;
+        }
+    }
+
+    /// High and low poll response words in `mode`, only the default mode 3 format is produced
+    fn response(mode: u32, pad: &PadState) -> (u32, u32) {
+        if mode != ANALOG_MODE_DEFAULT {
+            debug!("SI: analog mode {} responds in mode 3 format", mode);
+        }
+
+        let high = ((pad.buttons | PAD_USE_ORIGIN) as u32) << 16
+            | (pad.stick_x as u32) << 8
+            | pad.stick_y as u32;
+        let low = (pad.substick_x as u32) << 24
+            | (pad.substick_y as u32) << 16
+            | (pad.trigger_l as u32) << 8
+            | pad.trigger_r as u32;
+
+        (high, low)
+    }
+}
+
+impl SiDevice for DeviceController {
+    fn transfer(&mut self, buffer: &mut [u8], input: &PadState) -> usize {
+        match buffer[0] {
+            COMMAND_ID | COMMAND_RESET => {
+                buffer[..3].copy_from_slice(&SI_GC_CONTROLLER.to_be_bytes()[..3]);
+                3
+            }
+            COMMAND_DIRECT => {
+                self.set_rumble(Rumble::from_bits(buffer[2] as u32));
+
+                let mode = buffer[1] as u32 & 0x7;
+                let (high, low) = Self::response(mode, input);
+
+                buffer[..4].copy_from_slice(&high.to_be_bytes());
+                buffer[4..8].copy_from_slice(&low.to_be_bytes());
+                8
+            }
+            COMMAND_ORIGIN | COMMAND_RECALIBRATE => {
+                let origin = PadState::default();
+
+                buffer[..10].copy_from_slice(&[
+                    0,
+                    PAD_USE_ORIGIN as u8,
+                    origin.stick_x,
+                    origin.stick_y,
+                    origin.substick_x,
+                    origin.substick_y,
+                    origin.trigger_l,
+                    origin.trigger_r,
+                    0,
+                    0,
+                ]);
+                10
+            }
+            command => {
+                warn!("SI: unknown controller command {:#04x}", command);
+                0
+            }
+        }
+    }
+
+    fn poll(&mut self, command: u32, input: &PadState) -> Option<(u32, u32)> {
+        self.set_rumble(Rumble::from_bits(command));
+
+        Some(Self::response((command >> 8) & 0x7, input))
+    }
+
+    fn rumble(&mut self) -> Option<Rumble> {
+        std::mem::take(&mut self.rumble_changed).then_some(self.rumble)
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    io::{Error, ErrorKind, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    time::Duration,
+};
+
+use super::SiDevice;
+use crate::{cpu::timers::CPU_CLOCK, input::PadState};
+
+/// Base TCP ports of the link, as used by Dolphin and GBA emulators. Port 1 listens on these
+/// ports, the other ports on the following ones.
+pub const GBA_DATA_PORT: u16 = 0xD6BA;
+pub const GBA_CLOCK_PORT: u16 = 0xC10C;
+
+const COMMAND_STATUS: u8 = 0x00;
+const COMMAND_READ: u8 = 0x14;
+const COMMAND_WRITE: u8 = 0x15;
+const COMMAND_RESET: u8 = 0xFF;
+
+/// GBA cycles per second
+const GBA_CLOCK: u64 = 16_777_216;
+
+/// Cpu ticks between checks for connecting clients, about a millisecond
+const ACCEPT_TICKS: u64 = 486_000;
+
+/// How long to wait for the GBA to answer a command
+const RESPONSE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Game Boy Advance on a link cable, emulated by another program connected over TCP. Commands
+/// are forwarded on the data connection, a write with its 4 data bytes and other commands as a
+/// single byte, and the GBA answers with the response bytes. The time passed since the last
+/// command is sent on the clock connection in GBA cycles, as a big endian 32-bit value.
+pub struct DeviceGba {
+    data_listener: TcpListener,
+    clock_listener: TcpListener,
+    data: Option<TcpStream>,
+    clock: Option<TcpStream>,
+    /// Cpu ticks since the last command
+    ticks: u64,
+    /// Cpu ticks since clients were last accepted
+    accept_ticks: u64,
+    /// No command has been sent to the connected GBA yet
+    first_command: bool,
+}
+
+impl DeviceGba {
+    pub fn new(data_port: u16, clock_port: u16) -> Result<DeviceGba, Error> {
+        let data_listener = TcpListener::bind((Ipv4Addr::LOCALHOST, data_port))?;
+        data_listener.set_nonblocking(true)?;
+        let clock_listener = TcpListener::bind((Ipv4Addr::LOCALHOST, clock_port))?;
+        clock_listener.set_nonblocking(true)?;
+
+        info!(
+            "GBA link listening on {} (clock {})",
+            data_listener.local_addr()?,
+            clock_listener.local_addr()?
+        );
+
+        Ok(DeviceGba {
+            data_listener,
+            clock_listener,
+            data: None,
+            clock: None,
+            ticks: 0,
+            accept_ticks: 0,
+            first_command: true,
+        })
+    }
+
+    fn accept(&mut self) {
+        if self.data.is_none() {
+            if let Some(stream) = accept_client(&self.data_listener) {
+                self.data = Some(stream);
+                self.first_command = true;
+            }
+        }
+
+        if self.clock.is_none() {
+            self.clock = accept_client(&self.clock_listener);
+        }
+    }
+
+    fn disconnect(&mut self, e: Error) {
+        info!("GBA link: disconnected ({})", e);
+        self.data = None;
+        self.clock = None;
+    }
+
+    /// Send the GBA cycles passed since the last command
+    fn sync_clock(&mut self) {
+        let ticks = if self.first_command {
+            CPU_CLOCK / 60
+        } else {
+            self.ticks
+        };
+        self.ticks = 0;
+        self.first_command = false;
+
+        let Some(clock) = self.clock.as_mut() else {
+            return;
+        };
+
+        let cycles = (ticks * GBA_CLOCK / CPU_CLOCK) as u32;
+        if let Err(e) = clock.write_all(&cycles.to_be_bytes()) {
+            info!("GBA link: clock disconnected ({})", e);
+            self.clock = None;
+        }
+    }
+
+    /// Forward a command and wait for the GBA to fill `response`
+    fn exchange(&mut self, command: &[u8], response: &mut [u8]) -> Result<(), Error> {
+        let data = self
+            .data
+            .as_mut()
+            .ok_or_else(|| Error::from(ErrorKind::NotConnected))?;
+
+        data.write_all(command)?;
+        data.read_exact(response)
+    }
+}
+
+impl SiDevice for DeviceGba {
+    fn transfer(&mut self, buffer: &mut [u8], _input: &PadState) -> usize {
+        self.accept();
+
+        let (command_len, response_len) = match buffer[0] {
+            COMMAND_STATUS | COMMAND_RESET => (1, 3),
+            COMMAND_READ => (1, 5),
+            COMMAND_WRITE => (5, 1),
+            command => {
+                warn!("GBA link: unknown command {:#04x}", command);
+                return 0;
+            }
+        };
+
+        if self.data.is_none() {
+            return 0;
+        }
+
+        self.sync_clock();
+
+        let command = buffer[..command_len].to_vec();
+        let mut response = [0; 5];
+
+        match self.exchange(&command, &mut response[..response_len]) {
+            Ok(()) => {
+                buffer[..response_len].copy_from_slice(&response[..response_len]);
+                response_len
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                warn!("GBA link: no response to command {:#04x}", command[0]);
+                0
+            }
+            Err(e) => {
+                self.disconnect(e);
+                0
+            }
+        }
+    }
+
+    /// The GBA doesn't respond to polling
+    fn poll(&mut self, _command: u32, _input: &PadState) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn update(&mut self, ticks: u64) {
+        self.ticks += ticks;
+        self.accept_ticks += ticks;
+
+        if self.accept_ticks >= ACCEPT_TICKS {
+            self.accept_ticks = 0;
+            self.accept();
+        }
+    }
+}
+
+/// Accept a client, blocking for at most the response timeout on reads
+fn accept_client(listener: &TcpListener) -> Option<TcpStream> {
+    let (stream, addr) = match listener.accept() {
+        Ok(client) => client,
+        Err(e) if e.kind() == ErrorKind::WouldBlock => return None,
+        Err(e) => {
+            error!("GBA link: {}", e);
+            return None;
+        }
+    };
+
+    let setup = stream
+        .set_nonblocking(false)
+        .and_then(|_| stream.set_read_timeout(Some(RESPONSE_TIMEOUT)))
+        .and_then(|_| stream.set_nodelay(true));
+
+    match setup {
+        Ok(()) => {
+            info!("GBA link: {} connected", addr);
+            Some(stream)
+        }
+        Err(e) => {
+            error!("GBA link: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn link() {
+        let mut gba = DeviceGba::new(0, 0).unwrap();
+        let data_addr = gba.data_listener.local_addr().unwrap();
+        let clock_addr = gba.clock_listener.local_addr().unwrap();
+        let pad = PadState::default();
+
+        // nothing connected
+        let mut buffer = [COMMAND_STATUS, 0, 0, 0, 0];
+        assert_eq!(gba.transfer(&mut buffer, &pad), 0);
+
+        let mut clock = TcpStream::connect(clock_addr).unwrap();
+        let mut data = TcpStream::connect(data_addr).unwrap();
+
+        let client = thread::spawn(move || {
+            let mut command = [0; 1];
+            data.read_exact(&mut command).unwrap();
+            assert_eq!(command, [COMMAND_STATUS]);
+            data.write_all(&[0x00, 0x04, 0x08]).unwrap();
+
+            let mut command = [0; 5];
+            data.read_exact(&mut command).unwrap();
+            assert_eq!(command, [COMMAND_WRITE, 1, 2, 3, 4]);
+            data.write_all(&[0x02]).unwrap();
+
+            let mut cycles = [0; 8];
+            clock.read_exact(&mut cycles).unwrap();
+            cycles
+        });
+
+        // wait for both connections to be accepted
+        for _ in 0..100 {
+            gba.update(ACCEPT_TICKS);
+            if gba.data.is_some() && gba.clock.is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let mut buffer = [COMMAND_STATUS, 0, 0, 0, 0];
+        assert_eq!(gba.transfer(&mut buffer, &pad), 3);
+        assert_eq!(buffer[..3], [0x00, 0x04, 0x08]);
+
+        gba.update(CPU_CLOCK / 1000);
+
+        let mut buffer = [COMMAND_WRITE, 1, 2, 3, 4];
+        assert_eq!(gba.transfer(&mut buffer, &pad), 1);
+        assert_eq!(buffer[0], 0x02);
+
+        let cycles = client.join().unwrap();
+        assert_eq!(
+            u32::from_be_bytes(cycles[..4].try_into().unwrap()),
+            (GBA_CLOCK / 60) as u32
+        );
+        assert_eq!(
+            u32::from_be_bytes(cycles[4..].try_into().unwrap()),
+            (CPU_CLOCK / 1000 * GBA_CLOCK / CPU_CLOCK) as u32
+        );
+    }
+}
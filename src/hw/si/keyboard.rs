@@ -0,0 +1,82 @@
+use super::SiDevice;
+use crate::input::PadState;
+
+const SI_GC_KEYBOARD: u32 = 0x0820_0000;
+
+const COMMAND_ID: u8 = 0x00;
+const COMMAND_POLL: u8 = 0x54;
+const COMMAND_RESET: u8 = 0xFF;
+
+/// ASCII keyboard controller, reports up to three held keys
+#[derive(Default)]
+pub struct DeviceKeyboard {
+    /// 4-bit counter incremented on every poll
+    counter: u8,
+}
+
+impl DeviceKeyboard {
+    /// The counter, then the key codes followed by a checksum
+    fn response(&self, input: &PadState) -> (u32, u32) {
+        let [key_0, key_1, key_2] = input.keys;
+        let checksum = key_0 ^ key_1 ^ key_2 ^ self.counter;
+
+        let high = (self.counter as u32) << 24;
+        let low = u32::from_be_bytes([key_0, key_1, key_2, checksum]);
+
+        (high, low)
+    }
+}
+
+impl SiDevice for DeviceKeyboard {
+    fn transfer(&mut self, buffer: &mut [u8], input: &PadState) -> usize {
+        match buffer[0] {
+            COMMAND_ID | COMMAND_RESET => {
+                buffer[..3].copy_from_slice(&SI_GC_KEYBOARD.to_be_bytes()[..3]);
+                3
+            }
+            COMMAND_POLL => {
+                let (high, low) = self.response(input);
+
+                buffer[..4].copy_from_slice(&high.to_be_bytes());
+                buffer[4..8].copy_from_slice(&low.to_be_bytes());
+                8
+            }
+            command => {
+                warn!("SI: unknown keyboard command {:#04x}", command);
+                0
+            }
+        }
+    }
+
+    fn poll(&mut self, _command: u32, input: &PadState) -> Option<(u32, u32)> {
+        self.counter = (self.counter + 1) & 0xF;
+        Some(self.response(input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poll() {
+        let mut keyboard = DeviceKeyboard::default();
+        let input = PadState {
+            keys: [0x10, 0x61, 0],
+            ..Default::default()
+        };
+
+        let mut buffer = [COMMAND_ID, 0, 0];
+        assert_eq!(keyboard.transfer(&mut buffer, &input), 3);
+        assert_eq!(buffer, [0x08, 0x20, 0x00]);
+
+        assert_eq!(
+            keyboard.poll(0x5400_0000, &input),
+            Some((0x0100_0000, 0x1061_0070))
+        );
+        assert_eq!(
+            keyboard.poll(0x5400_0000, &PadState::default()),
+            Some((0x0200_0000, 0x0000_0002))
+        );
+    }
+}
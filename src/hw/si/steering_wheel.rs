@@ -0,0 +1,45 @@
+use super::{controller::PAD_USE_ORIGIN, DeviceController, SiDevice};
+use crate::input::PadState;
+
+const SI_GC_STEERING: u32 = 0x0800_0000;
+
+const COMMAND_ID: u8 = 0x00;
+const COMMAND_RESET: u8 = 0xFF;
+
+/// Set in poll responses when the pedals are connected
+const PEDALS_CONNECTED: u32 = 0x0800;
+
+/// Steering wheel with pedals. The wheel is the stick's X axis, the accelerator and brake
+/// pedals are the R and L triggers.
+#[derive(Default)]
+pub struct DeviceSteeringWheel {
+    controller: DeviceController,
+}
+
+impl SiDevice for DeviceSteeringWheel {
+    fn transfer(&mut self, buffer: &mut [u8], input: &PadState) -> usize {
+        match buffer[0] {
+            COMMAND_ID | COMMAND_RESET => {
+                buffer[..3].copy_from_slice(&SI_GC_STEERING.to_be_bytes()[..3]);
+                3
+            }
+            // origin and direct reads are answered as by a controller
+            _ => self.controller.transfer(buffer, input),
+        }
+    }
+
+    /// Pedals in the high bytes of the low word, followed by the triggers
+    fn poll(&mut self, _command: u32, input: &PadState) -> Option<(u32, u32)> {
+        let high = ((input.buttons | PAD_USE_ORIGIN) as u32) << 16
+            | PEDALS_CONNECTED
+            | input.stick_x as u32;
+        let low = u32::from_be_bytes([
+            input.trigger_r,
+            input.trigger_l,
+            input.trigger_l,
+            input.trigger_r,
+        ]);
+
+        Some((high, low))
+    }
+}
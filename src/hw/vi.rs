@@ -4,10 +4,12 @@ use crate::{
     hw::{
         mmio::{Mmio, MmioDevice},
         pi::{ProcessorInterface, PI_INTERRUPT_VI},
+        si::SerialInterface,
     },
     utils::Halveable,
 };
 
+use minifb::Key;
 #[cfg(not(test))]
 use minifb::{Window, WindowOptions};
 
@@ -99,8 +101,6 @@ pub struct VideoInterface {
     bfbl: u32,
     /// Burst Blanking Even Interval
     efbbi: EvenFieldBurstBlankingIntervalRegister,
-    /// Current Vertical Beam Position
-    vbp: u16,
     // Current Horizontal Beam Position
     //hbp: u16,
     /// Display Interrupts
@@ -114,21 +114,20 @@ pub struct VideoInterface {
     /// Unknown,
     unknown: u16,
     buffer: Vec<u32>,
+    /// Opened when the first frame is presented
     #[cfg(not(test))]
-    window: Window,
+    window: Option<Window>,
+    /// Keys held down in the window when events were last processed
+    keys: Vec<Key>,
+    headless: bool,
 
     cpu_ticks: u64,
     half_line_count: u32,
+    frame_count: u64,
 }
 
 impl Default for VideoInterface {
     fn default() -> Self {
-        #[cfg(not(test))]
-        let window = Window::new("Rustcube", WIDTH, HEIGHT, WindowOptions::default())
-            .unwrap_or_else(|e| {
-                panic!("{}", e);
-            });
-
         VideoInterface {
             vtr: 0.into(),
             config: 0.into(),
@@ -140,7 +139,6 @@ impl Default for VideoInterface {
             efbbi: 0.into(),
             tfbl: 0,
             bfbl: 0,
-            vbp: 1,
             //hbp: 1,
             di: Default::default(),
             hsw: Default::default(),
@@ -149,9 +147,12 @@ impl Default for VideoInterface {
             unknown: 0,
             buffer: vec![0; WIDTH * HEIGHT],
             #[cfg(not(test))]
-            window,
+            window: None,
+            keys: Vec::new(),
+            headless: false,
             cpu_ticks: 0,
             half_line_count: 0,
+            frame_count: 0,
         }
     }
 }
@@ -732,16 +733,102 @@ impl VideoInterface {
     //    CLOCK_FREQS[self.clock as usize]
     //}
 
+    /// Run without a window
+    pub fn set_headless(&mut self, headless: bool) {
+        self.headless = headless;
+    }
+
+    pub fn headless(&self) -> bool {
+        self.headless
+    }
+
+    /// Whether the window was opened and has since been closed by the user
+    pub fn window_closed(&self) -> bool {
+        #[cfg(not(test))]
+        let closed = self.window.as_ref().is_some_and(|window| !window.is_open());
+        #[cfg(test)]
+        let closed = false;
+
+        closed
+    }
+
+    /// Show the current frame buffer, also processes window events
+    pub fn present(&mut self) {
+        #[cfg(not(test))]
+        if !self.headless {
+            let window = self.window.get_or_insert_with(|| {
+                Window::new("Rustcube", WIDTH, HEIGHT, WindowOptions::default()).unwrap_or_else(
+                    |e| {
+                        panic!("{}", e);
+                    },
+                )
+            });
+
+            window
+                .update_with_buffer(&self.buffer, WIDTH, HEIGHT)
+                .unwrap();
+
+            self.keys = window.get_keys();
+        }
+    }
+
+    /// Keys held down in the window, none when running headless
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    /// Reset the registers, keeping the window and the frame count
+    pub fn reset(&mut self) {
+        let mut vi = VideoInterface {
+            keys: std::mem::take(&mut self.keys),
+            headless: self.headless,
+            frame_count: self.frame_count,
+            ..Default::default()
+        };
+
+        #[cfg(not(test))]
+        {
+            vi.window = self.window.take();
+        }
+        std::mem::swap(&mut vi.buffer, &mut self.buffer);
+
+        *self = vi;
+    }
+
+    /// Number of frames presented since power on
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
     pub fn even_field_half_lines_total(&self) -> u32 {
         // Pre-Equalization(equ) + Serration(equ) + Post-Equalization(equ) + Pre-Blanking(prb) +
         // Active Video(acv full lines) + Post-Blanking(psb)
-        (self.vtr.equ() as u32 * 3) + self.vte.prb() + (self.vtr.acv() as u32 * 2) + self.vte.prb()
+        (self.vtr.equ() as u32 * 3) + self.vte.prb() + (self.vtr.acv() as u32 * 2) + self.vte.psb()
     }
 
     pub fn odd_field_half_lines_total(&self) -> u32 {
         // Pre-Equalization(equ) + Serration(equ) + Post-Equalization(equ) + Pre-Blanking(prb) +
         // Active Video(acv full lines) + Post-Blanking(psb)
-        (self.vtr.equ() as u32 * 3) + self.vto.prb() + (self.vtr.acv() as u32 * 2) + self.vto.prb()
+        (self.vtr.equ() as u32 * 3) + self.vto.prb() + (self.vtr.acv() as u32 * 2) + self.vto.psb()
+    }
+
+    /// Convert the top field XFB to the window buffer
+    fn render_xfb(bus: &mut Bus) {
+        let mut i = bus.vi.tfbl & 0xFF_FFFF;
+        let mut j = 0;
+
+        while i < (bus.vi.tfbl & 0xFF_FFFF) + 320 * 480 * 4 {
+            let y1 = i32::from(bus.memory.read_u8(i));
+            let u = i32::from(bus.memory.read_u8(i + 1));
+            let y2 = i32::from(bus.memory.read_u8(i + 2));
+            let v = i32::from(bus.memory.read_u8(i + 3));
+
+            bus.vi.buffer[j] = yuv_to_rgb(y1, u, v);
+            bus.vi.buffer[j + 1] = yuv_to_rgb(y2, u, v);
+
+            i += 4;
+            j += 2;
+        }
     }
 
     pub fn update_interrupts(bus: &mut Bus, cpu_state: &mut CpuState) {
@@ -767,41 +854,31 @@ impl VideoInterface {
         }
 
         if bus.vi.config.enable() {
-            bus.vi.vbp += 1;
-
-            // NTSC
-            if bus.vi.config.format() == 0 && bus.vi.vbp > 525 {
-                bus.vi.vbp = 1;
-
-                let mut i = bus.vi.tfbl & 0xFF_FFFF;
-                let mut j = 0;
-
-                while i < (bus.vi.tfbl & 0xFF_FFFF) + 320 * 480 * 4 {
-                    let y1 = i32::from(bus.memory.read_u8(i));
-                    let u = i32::from(bus.memory.read_u8(i + 1));
-                    let y2 = i32::from(bus.memory.read_u8(i + 2));
-                    let v = i32::from(bus.memory.read_u8(i + 3));
+            bus.vi.half_line_count += 1;
 
-                    bus.vi.buffer[j] = yuv_to_rgb(y1, u, v);
-                    bus.vi.buffer[j + 1] = yuv_to_rgb(y2, u, v);
+            let frame_half_lines =
+                bus.vi.even_field_half_lines_total() + bus.vi.odd_field_half_lines_total();
+            if frame_half_lines != 0 && bus.vi.half_line_count >= frame_half_lines {
+                bus.vi.half_line_count = 0;
+                bus.vi.frame_count += 1;
 
-                    i += 4;
-                    j += 2;
+                // TODO: the XFB is only rendered for NTSC, other formats show the last frame
+                if bus.vi.config.format() == 0 {
+                    Self::render_xfb(bus);
                 }
 
-                #[cfg(not(test))]
-                bus.vi
-                    .window
-                    .update_with_buffer(&bus.vi.buffer, WIDTH, HEIGHT)
-                    .unwrap();
+                // window events and keys are processed every frame whatever the format
+                bus.vi.present();
             }
 
-            bus.vi.half_line_count += 1;
-            if bus.vi.half_line_count
-                == bus.vi.even_field_half_lines_total() + bus.vi.odd_field_half_lines_total()
-            {
-                bus.vi.half_line_count = 0;
-            }
+            // the second field starts after the half lines of the first
+            let first_field = bus.vi.even_field_half_lines_total();
+            let field_half_line = if first_field != 0 && bus.vi.half_line_count >= first_field {
+                bus.vi.half_line_count - first_field
+            } else {
+                bus.vi.half_line_count
+            };
+            SerialInterface::on_half_line(bus, cpu_state, field_half_line);
 
             let current_line = bus.vi.half_line_count / 2 + 1;
 
@@ -0,0 +1,100 @@
+mod keyboard;
+mod script;
+
+use minifb::Key;
+
+pub use self::{
+    keyboard::{key_from_name, KeyMap, KeyboardInput, PadInput},
+    script::InputScript,
+};
+pub use crate::hw::si::NUM_PORTS;
+
+pub const PAD_BUTTON_LEFT: u16 = 0x0001;
+pub const PAD_BUTTON_RIGHT: u16 = 0x0002;
+pub const PAD_BUTTON_DOWN: u16 = 0x0004;
+pub const PAD_BUTTON_UP: u16 = 0x0008;
+pub const PAD_TRIGGER_Z: u16 = 0x0010;
+pub const PAD_TRIGGER_R: u16 = 0x0020;
+pub const PAD_TRIGGER_L: u16 = 0x0040;
+pub const PAD_BUTTON_A: u16 = 0x0100;
+pub const PAD_BUTTON_B: u16 = 0x0200;
+pub const PAD_BUTTON_X: u16 = 0x0400;
+pub const PAD_BUTTON_Y: u16 = 0x0800;
+pub const PAD_BUTTON_START: u16 = 0x1000;
+
+/// State of the device in a controller port, as a standard controller with sticks centered at
+/// 0x80, and the keys held on an ASCII keyboard
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PadState {
+    /// `PAD_BUTTON_*` and `PAD_TRIGGER_*` bits
+    pub buttons: u16,
+    pub stick_x: u8,
+    pub stick_y: u8,
+    pub substick_x: u8,
+    pub substick_y: u8,
+    pub trigger_l: u8,
+    pub trigger_r: u8,
+    /// Keyboard key codes, 0 when fewer keys are held
+    pub keys: [u8; 3],
+}
+
+impl Default for PadState {
+    fn default() -> Self {
+        PadState {
+            buttons: 0,
+            stick_x: 0x80,
+            stick_y: 0x80,
+            substick_x: 0x80,
+            substick_y: 0x80,
+            trigger_l: 0,
+            trigger_r: 0,
+            keys: [0; 3],
+        }
+    }
+}
+
+/// State of a controller's rumble motor, set by the low bits of poll and direct commands
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rumble {
+    #[default]
+    Off,
+    On,
+    /// Stopped with the motor braked
+    HardStop,
+}
+
+impl Rumble {
+    pub(crate) fn from_bits(bits: u32) -> Rumble {
+        match bits & 0x3 {
+            1 => Rumble::On,
+            2 => Rumble::HardStop,
+            _ => Rumble::Off,
+        }
+    }
+}
+
+/// Source of controller state, asked for the state of every port each time the SI polls. This
+/// is the only way input reaches the emulated console, so recording the polls is enough to
+/// replay a session.
+pub trait InputSource {
+    /// State of the controllers in ports 1-4. `keys` are held down in the emulator window,
+    /// there are none when running headless.
+    fn poll(&mut self, keys: &[Key]) -> [PadState; NUM_PORTS];
+
+    /// VI started frame `frame`
+    fn frame(&mut self, _frame: u64) {}
+
+    /// The rumble motor of the controller in `port` (0-3) changed, to be forwarded to a host
+    /// controller
+    fn rumble(&mut self, _port: usize, _rumble: Rumble) {}
+}
+
+/// Controllers that are never touched
+#[derive(Default)]
+pub struct NoInput;
+
+impl InputSource for NoInput {
+    fn poll(&mut self, _keys: &[Key]) -> [PadState; NUM_PORTS] {
+        Default::default()
+    }
+}
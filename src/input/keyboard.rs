@@ -0,0 +1,436 @@
+use std::collections::BTreeMap;
+
+use minifb::Key;
+
+use super::{
+    InputSource, PadState, NUM_PORTS, PAD_BUTTON_A, PAD_BUTTON_B, PAD_BUTTON_DOWN, PAD_BUTTON_LEFT,
+    PAD_BUTTON_RIGHT, PAD_BUTTON_START, PAD_BUTTON_UP, PAD_BUTTON_X, PAD_BUTTON_Y, PAD_TRIGGER_L,
+    PAD_TRIGGER_R, PAD_TRIGGER_Z,
+};
+
+/// Stick and trigger values of a fully pressed key
+const STICK_MIN: u8 = 0x00;
+const STICK_MAX: u8 = 0xFF;
+const TRIGGER_MAX: u8 = 0xFF;
+
+/// Controller input a key can be bound to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PadInput {
+    A,
+    B,
+    X,
+    Y,
+    Z,
+    L,
+    R,
+    Start,
+    Up,
+    Down,
+    Left,
+    Right,
+    StickUp,
+    StickDown,
+    StickLeft,
+    StickRight,
+    CStickUp,
+    CStickDown,
+    CStickLeft,
+    CStickRight,
+}
+
+impl PadInput {
+    /// Setting name, e.g. `StickUp`
+    pub fn from_name(name: &str) -> Option<PadInput> {
+        use PadInput::*;
+
+        let input = match name.to_ascii_lowercase().as_str() {
+            "a" => A,
+            "b" => B,
+            "x" => X,
+            "y" => Y,
+            "z" => Z,
+            "l" => L,
+            "r" => R,
+            "start" => Start,
+            "up" => Up,
+            "down" => Down,
+            "left" => Left,
+            "right" => Right,
+            "stickup" => StickUp,
+            "stickdown" => StickDown,
+            "stickleft" => StickLeft,
+            "stickright" => StickRight,
+            "cstickup" => CStickUp,
+            "cstickdown" => CStickDown,
+            "cstickleft" => CStickLeft,
+            "cstickright" => CStickRight,
+            _ => return None,
+        };
+
+        Some(input)
+    }
+
+    /// Apply a held key bound to this input
+    pub(super) fn press(self, pad: &mut PadState) {
+        use PadInput::*;
+
+        match self {
+            A => pad.buttons |= PAD_BUTTON_A,
+            B => pad.buttons |= PAD_BUTTON_B,
+            X => pad.buttons |= PAD_BUTTON_X,
+            Y => pad.buttons |= PAD_BUTTON_Y,
+            Z => pad.buttons |= PAD_TRIGGER_Z,
+            L => {
+                pad.buttons |= PAD_TRIGGER_L;
+                pad.trigger_l = TRIGGER_MAX;
+            }
+            R => {
+                pad.buttons |= PAD_TRIGGER_R;
+                pad.trigger_r = TRIGGER_MAX;
+            }
+            Start => pad.buttons |= PAD_BUTTON_START,
+            Up => pad.buttons |= PAD_BUTTON_UP,
+            Down => pad.buttons |= PAD_BUTTON_DOWN,
+            Left => pad.buttons |= PAD_BUTTON_LEFT,
+            Right => pad.buttons |= PAD_BUTTON_RIGHT,
+            StickUp => pad.stick_y = STICK_MAX,
+            StickDown => pad.stick_y = STICK_MIN,
+            StickLeft => pad.stick_x = STICK_MIN,
+            StickRight => pad.stick_x = STICK_MAX,
+            CStickUp => pad.substick_y = STICK_MAX,
+            CStickDown => pad.substick_y = STICK_MIN,
+            CStickLeft => pad.substick_x = STICK_MIN,
+            CStickRight => pad.substick_x = STICK_MAX,
+        }
+    }
+}
+
+/// Keys bound to the inputs of one controller
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeyMap {
+    bindings: BTreeMap<PadInput, Key>,
+}
+
+impl KeyMap {
+    /// Bindings of the first controller when none are configured
+    pub fn default_port_1() -> KeyMap {
+        use PadInput::*;
+
+        let bindings = [
+            (A, Key::X),
+            (B, Key::Z),
+            (X, Key::C),
+            (Y, Key::S),
+            (Z, Key::D),
+            (L, Key::Q),
+            (R, Key::W),
+            (Start, Key::Enter),
+            (Up, Key::T),
+            (Down, Key::G),
+            (Left, Key::F),
+            (Right, Key::H),
+            (StickUp, Key::Up),
+            (StickDown, Key::Down),
+            (StickLeft, Key::Left),
+            (StickRight, Key::Right),
+            (CStickUp, Key::I),
+            (CStickDown, Key::K),
+            (CStickLeft, Key::J),
+            (CStickRight, Key::L),
+        ];
+
+        KeyMap {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+
+    /// Bind `key` to `input`, or unbind the input
+    pub fn bind(&mut self, input: PadInput, key: Option<Key>) {
+        match key {
+            Some(key) => self.bindings.insert(input, key),
+            None => self.bindings.remove(&input),
+        };
+    }
+
+    pub fn key(&self, input: PadInput) -> Option<Key> {
+        self.bindings.get(&input).copied()
+    }
+
+    /// Controller state with the keys in `keys` held down
+    pub fn pad(&self, keys: &[Key]) -> PadState {
+        let mut pad = PadState::default();
+
+        for (input, key) in self.bindings.iter() {
+            if keys.contains(key) {
+                input.press(&mut pad);
+            }
+        }
+
+        pad
+    }
+}
+
+/// Controllers driven by the keyboard of the emulator window
+pub struct KeyboardInput {
+    key_maps: [KeyMap; NUM_PORTS],
+}
+
+impl KeyboardInput {
+    pub fn new(key_maps: [KeyMap; NUM_PORTS]) -> KeyboardInput {
+        KeyboardInput { key_maps }
+    }
+}
+
+impl InputSource for KeyboardInput {
+    /// Held keys are also typed on keyboards in any port
+    fn poll(&mut self, keys: &[Key]) -> [PadState; NUM_PORTS] {
+        let mut codes = [0; 3];
+        for (code, key_code) in codes
+            .iter_mut()
+            .zip(keys.iter().filter_map(|key| keyboard_code(*key)))
+        {
+            *code = key_code;
+        }
+
+        std::array::from_fn(|port| PadState {
+            keys: codes,
+            ..self.key_maps[port].pad(keys)
+        })
+    }
+}
+
+/// ASCII keyboard key code of a host key
+fn keyboard_code(key: Key) -> Option<u8> {
+    if let Some(n) = KEYS_LETTERS.iter().position(|k| *k == key) {
+        return Some(0x10 + n as u8);
+    }
+
+    // 1-9 then 0
+    if let Some(n) = KEYS_DIGITS.iter().position(|k| *k == key) {
+        return Some(0x2A + (n as u8 + 9) % 10);
+    }
+
+    if let Some(n) = KEYS_FUNCTION.iter().position(|k| *k == key) {
+        return Some(0x40 + n as u8);
+    }
+
+    let code = match key {
+        Key::Home => 0x06,
+        Key::End => 0x07,
+        Key::PageUp => 0x08,
+        Key::PageDown => 0x09,
+        Key::ScrollLock => 0x0A,
+        Key::Minus => 0x34,
+        Key::Equal => 0x35,
+        Key::LeftBracket => 0x37,
+        Key::RightBracket => 0x38,
+        Key::Semicolon => 0x39,
+        Key::Apostrophe => 0x3A,
+        Key::Backslash => 0x3B,
+        Key::Comma => 0x3C,
+        Key::Period => 0x3D,
+        Key::Slash => 0x3E,
+        Key::Escape => 0x4C,
+        Key::Insert => 0x4D,
+        Key::Delete => 0x4E,
+        Key::Backquote => 0x4F,
+        Key::Backspace => 0x50,
+        Key::Tab => 0x51,
+        Key::CapsLock => 0x53,
+        Key::LeftShift => 0x54,
+        Key::RightShift => 0x55,
+        Key::LeftCtrl => 0x56,
+        Key::RightAlt => 0x57,
+        Key::LeftSuper => 0x58,
+        Key::Space => 0x59,
+        Key::RightSuper => 0x5A,
+        Key::Menu => 0x5B,
+        Key::Left => 0x5C,
+        Key::Down => 0x5D,
+        Key::Up => 0x5E,
+        Key::Right => 0x5F,
+        Key::Enter => 0x61,
+        _ => return None,
+    };
+
+    Some(code)
+}
+
+/// Key from its name in settings: letters, digits, `F1`-`F12`, arrow keys and names such as
+/// `Enter`, `Space`, `LeftShift` or `NumPad0`
+pub fn key_from_name(name: &str) -> Option<Key> {
+    let name = name.to_ascii_lowercase();
+
+    if let [c] = name.as_bytes() {
+        let key = match c {
+            b'0'..=b'9' => KEYS_DIGITS[(c - b'0') as usize],
+            b'a'..=b'z' => KEYS_LETTERS[(c - b'a') as usize],
+            _ => return None,
+        };
+        return Some(key);
+    }
+
+    if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<usize>().ok()) {
+        return KEYS_FUNCTION.get(n.checked_sub(1)?).copied();
+    }
+
+    if let Some(n) = name
+        .strip_prefix("numpad")
+        .and_then(|n| n.parse::<usize>().ok())
+    {
+        return KEYS_NUMPAD.get(n).copied();
+    }
+
+    let key = match name.as_str() {
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "enter" | "return" => Key::Enter,
+        "space" => Key::Space,
+        "tab" => Key::Tab,
+        "backspace" => Key::Backspace,
+        "escape" => Key::Escape,
+        "insert" => Key::Insert,
+        "delete" => Key::Delete,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "leftshift" | "shift" => Key::LeftShift,
+        "rightshift" => Key::RightShift,
+        "leftctrl" | "ctrl" => Key::LeftCtrl,
+        "rightctrl" => Key::RightCtrl,
+        "leftalt" | "alt" => Key::LeftAlt,
+        "rightalt" => Key::RightAlt,
+        "comma" => Key::Comma,
+        "period" => Key::Period,
+        "slash" => Key::Slash,
+        "semicolon" => Key::Semicolon,
+        "apostrophe" => Key::Apostrophe,
+        "minus" => Key::Minus,
+        "equal" => Key::Equal,
+        "leftbracket" => Key::LeftBracket,
+        "rightbracket" => Key::RightBracket,
+        "backslash" => Key::Backslash,
+        "backquote" => Key::Backquote,
+        "numpadenter" => Key::NumPadEnter,
+        "numpadplus" => Key::NumPadPlus,
+        "numpadminus" => Key::NumPadMinus,
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+const KEYS_DIGITS: [Key; 10] = [
+    Key::Key0,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+];
+
+const KEYS_LETTERS: [Key; 26] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+
+const KEYS_FUNCTION: [Key; 12] = [
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+];
+
+const KEYS_NUMPAD: [Key; 10] = [
+    Key::NumPad0,
+    Key::NumPad1,
+    Key::NumPad2,
+    Key::NumPad3,
+    Key::NumPad4,
+    Key::NumPad5,
+    Key::NumPad6,
+    Key::NumPad7,
+    Key::NumPad8,
+    Key::NumPad9,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names() {
+        assert_eq!(key_from_name("x"), Some(Key::X));
+        assert_eq!(key_from_name("7"), Some(Key::Key7));
+        assert_eq!(key_from_name("F11"), Some(Key::F11));
+        assert_eq!(key_from_name("NumPad3"), Some(Key::NumPad3));
+        assert_eq!(key_from_name("Enter"), Some(Key::Enter));
+        assert_eq!(key_from_name("F13"), None);
+        assert_eq!(key_from_name("Hyper"), None);
+    }
+
+    #[test]
+    fn keys_to_pad() {
+        let mut map = KeyMap::default_port_1();
+        map.bind(PadInput::A, Some(Key::Space));
+
+        let pad = map.pad(&[Key::Space, Key::Enter, Key::Left, Key::W]);
+
+        assert_eq!(pad.buttons, PAD_BUTTON_A | PAD_BUTTON_START | PAD_TRIGGER_R);
+        assert_eq!(pad.stick_x, STICK_MIN);
+        assert_eq!(pad.stick_y, 0x80);
+        assert_eq!(pad.trigger_r, TRIGGER_MAX);
+        assert_eq!(map.pad(&[Key::X]), PadState::default());
+    }
+
+    #[test]
+    fn typed_keys() {
+        let mut input = KeyboardInput::new(Default::default());
+
+        let pads = input.poll(&[Key::A, Key::Key1, Key::Key0, Key::Enter]);
+
+        assert_eq!(pads[0].keys, [0x10, 0x2A, 0x33]);
+        assert_eq!(pads[3].keys, [0x10, 0x2A, 0x33]);
+        assert_eq!(pads[0].buttons, 0);
+        assert_eq!(input.poll(&[Key::NumPad5])[1].keys, [0; 3]);
+    }
+}
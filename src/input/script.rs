@@ -0,0 +1,257 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use minifb::Key;
+
+use super::{InputSource, PadInput, PadState, NUM_PORTS};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Action {
+    /// Held for a number of frames
+    Press(u64),
+    /// Held until released
+    Hold,
+    Release,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Event {
+    frame: u64,
+    port: usize,
+    action: Action,
+    inputs: Vec<PadInput>,
+}
+
+/// Controllers driven by a timeline of events, applied at the start of VI frames, e.g.
+///
+/// ```text
+/// frame 120: press A for 3 frames
+/// frame 300: stick left; frame 310: pad 2 hold Start+B
+/// frame 400: pad 2 release Start
+/// ```
+///
+/// Inputs are named as in the `[Pad1]` settings, with `stick` and `cstick` directions written
+/// as `stick left`. Actions without a verb press, and a press lasts one frame unless given
+/// `for N frames`.
+#[derive(Debug, Default)]
+pub struct InputScript {
+    /// Sorted by frame
+    events: Vec<Event>,
+    next_event: usize,
+    frame: u64,
+    /// Inputs held on each port, with the frame they are released on
+    held: [BTreeMap<PadInput, Option<u64>>; NUM_PORTS],
+}
+
+impl InputScript {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<InputScript, Error> {
+        let text = fs::read_to_string(path.as_ref())?;
+
+        InputScript::parse(&text).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: {e}", path.as_ref().display()),
+            )
+        })
+    }
+
+    /// Events separated by newlines or `;`, `#` starts a comment
+    pub fn parse(text: &str) -> Result<InputScript, String> {
+        let mut events = Vec::new();
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+
+            for entry in line.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+                let event = parse_event(entry).map_err(|e| format!("line {}: {e}", n + 1))?;
+                events.push(event);
+            }
+        }
+
+        // stable, events of the same frame apply in script order
+        events.sort_by_key(|event| event.frame);
+
+        let mut script = InputScript {
+            events,
+            ..Default::default()
+        };
+        script.advance(0);
+
+        Ok(script)
+    }
+
+    fn advance(&mut self, frame: u64) {
+        self.frame = frame;
+
+        for held in self.held.iter_mut() {
+            held.retain(|_, release| release.is_none_or(|release| release > frame));
+        }
+
+        while let Some(event) = self.events.get(self.next_event) {
+            if event.frame > frame {
+                break;
+            }
+
+            let held = &mut self.held[event.port];
+            for input in event.inputs.iter().copied() {
+                match event.action {
+                    // a late press still lasts its full duration
+                    Action::Press(frames) => held.insert(input, Some(frame + frames)),
+                    Action::Hold => held.insert(input, None),
+                    Action::Release => held.remove(&input),
+                };
+            }
+
+            self.next_event += 1;
+        }
+    }
+}
+
+impl InputSource for InputScript {
+    fn poll(&mut self, _keys: &[Key]) -> [PadState; NUM_PORTS] {
+        std::array::from_fn(|port| {
+            let mut pad = PadState::default();
+            for input in self.held[port].keys() {
+                input.press(&mut pad);
+            }
+            pad
+        })
+    }
+
+    fn frame(&mut self, frame: u64) {
+        if frame > self.frame {
+            self.advance(frame);
+        }
+    }
+}
+
+/// `frame N: [pad P] [press|hold|release] INPUT[+INPUT...] [for N frames]`
+fn parse_event(entry: &str) -> Result<Event, String> {
+    let invalid = || format!("invalid event `{entry}`");
+
+    let (frame, action) = entry.split_once(':').ok_or_else(invalid)?;
+
+    let frame = match frame.split_whitespace().collect::<Vec<_>>()[..] {
+        ["frame", n] => n.parse().map_err(|_| invalid())?,
+        _ => return Err(invalid()),
+    };
+
+    let mut words: Vec<&str> = action.split_whitespace().collect();
+
+    let mut port = 0;
+    if let ["pad", n, ..] = words[..] {
+        port = match n.parse::<usize>() {
+            Ok(n @ 1..=NUM_PORTS) => n - 1,
+            _ => return Err(format!("invalid pad `{n}`")),
+        };
+        words.drain(..2);
+    }
+
+    let mut frames = 1;
+    if let [.., "for", n, "frame" | "frames"] = words[..] {
+        frames = n.parse().map_err(|_| invalid())?;
+        words.truncate(words.len() - 3);
+    }
+
+    let action = match words.first() {
+        Some(&"press") => Action::Press(frames),
+        Some(&"hold") => Action::Hold,
+        Some(&"release") => Action::Release,
+        _ => {
+            words.insert(0, "press");
+            Action::Press(frames)
+        }
+    };
+
+    let inputs = words[1..]
+        .concat()
+        .split('+')
+        .map(|name| PadInput::from_name(name).ok_or_else(|| format!("unknown input `{name}`")))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Event {
+        frame,
+        port,
+        action,
+        inputs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{PAD_BUTTON_A, PAD_BUTTON_B, PAD_BUTTON_START};
+
+    #[test]
+    fn parse() {
+        let script = InputScript::parse(
+            "
+            # title screen
+            frame 120: press A for 3 frames; frame 300: stick left
+            frame 5: pad 2 hold Start + B
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            script.events,
+            [
+                Event {
+                    frame: 5,
+                    port: 1,
+                    action: Action::Hold,
+                    inputs: vec![PadInput::Start, PadInput::B],
+                },
+                Event {
+                    frame: 120,
+                    port: 0,
+                    action: Action::Press(3),
+                    inputs: vec![PadInput::A],
+                },
+                Event {
+                    frame: 300,
+                    port: 0,
+                    action: Action::Press(1),
+                    inputs: vec![PadInput::StickLeft],
+                },
+            ]
+        );
+
+        assert!(InputScript::parse("frame 1: press Jump").is_err());
+        assert!(InputScript::parse("frame 1: pad 5 press A").is_err());
+        assert!(InputScript::parse("at 1: press A").is_err());
+    }
+
+    #[test]
+    fn timeline() {
+        let mut script = InputScript::parse(
+            "frame 0: hold B; frame 2: press A for 2 frames; frame 3: release B; frame 4: pad 4 start",
+        )
+        .unwrap();
+
+        let mut pads = Vec::new();
+        for frame in 0..6 {
+            script.frame(frame);
+            pads.push(script.poll(&[]));
+        }
+
+        let buttons = |port: usize| pads.iter().map(|p| p[port].buttons).collect::<Vec<_>>();
+
+        assert_eq!(
+            buttons(0),
+            [
+                PAD_BUTTON_B,
+                PAD_BUTTON_B,
+                PAD_BUTTON_A | PAD_BUTTON_B,
+                PAD_BUTTON_A,
+                0,
+                0
+            ]
+        );
+        assert_eq!(buttons(3), [0, 0, 0, 0, PAD_BUTTON_START, 0]);
+    }
+}
@@ -5,13 +5,24 @@ extern crate bitfield;
 extern crate log;
 
 mod bus;
+mod cheats;
+pub mod config;
 pub(crate) mod cpu;
-mod disc;
+pub mod disc;
 mod dol;
 pub mod dsp;
 mod hw;
+pub mod input;
+pub mod memcard;
+pub mod movie;
+mod patches;
 pub mod system;
 mod utils;
 mod video;
 
-pub use self::system::System;
+pub use self::{
+    config::Config,
+    cpu::assembler::{assemble, AssemblerError},
+    hw::exi::Sram,
+    system::System,
+};
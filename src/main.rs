@@ -1,24 +1,215 @@
-use std::{env, path::Path};
+mod commands;
+
+use std::{
+    env,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use env_logger::Env;
 use getopts::Options;
-use rustcube::System;
+use rustcube::{input::InputScript, movie::Movie, Config, System};
 
 pub type DynResult<T> = Result<T, Box<dyn std::error::Error>>;
 
+/// Config file loaded when `--config` is not given, if it exists
+const DEFAULT_CONFIG: &str = "rustcube.ini";
+
+/// Command line options that override a config file setting: (option, section, key)
+const CONFIG_OPTIONS: &[(&str, &str, &str)] = &[
+    ("ipl", "Core", "Ipl"),
+    ("dsp-rom", "DSP", "Rom"),
+    ("dsp-coef", "DSP", "Coef"),
+    ("slot-a", "EXI", "SlotA"),
+    ("slot-b", "EXI", "SlotB"),
+    ("usb-gecko-port", "EXI", "UsbGeckoPort"),
+    ("serial-port-1", "EXI", "SerialPort1"),
+    ("port-1", "SI", "Port1"),
+    ("port-2", "SI", "Port2"),
+    ("port-3", "SI", "Port3"),
+    ("port-4", "SI", "Port4"),
+    ("bba-backend", "BBA", "Backend"),
+    ("bba-mac", "BBA", "MacAddress"),
+    ("bba-tap", "BBA", "TapDevice"),
+    ("bba-local", "BBA", "LocalAddr"),
+    ("bba-peer", "BBA", "PeerAddr"),
+    ("memcard-a", "MemoryCard", "SlotA"),
+    ("memcard-b", "MemoryCard", "SlotB"),
+    ("memcard-size", "MemoryCard", "SizeA"),
+    ("memcard-size", "MemoryCard", "SizeB"),
+    ("sd-card-a", "SDCard", "SlotA"),
+    ("sd-card-b", "SDCard", "SlotB"),
+    ("region", "Core", "Region"),
+    ("language", "Core", "Language"),
+    ("sram", "Core", "Sram"),
+    ("rtc-time", "Core", "RtcTime"),
+    ("log", "Log", "Filter"),
+    ("frame-limit", "Core", "FrameLimit"),
+    ("frames", "Core", "Frames"),
+    ("on-exit", "Core", "OnExit"),
+];
+
 fn print_usage(program: &str, opts: &Options) {
-    let brief = format!("Usage: {program} [options] IPL_FILE");
+    let brief = format!(
+        "Usage: {program} [options] [FILE]\n       {program} memcard --help\n       {program} disc --help"
+    );
     print!("{}", opts.usage(&brief));
 }
 
 fn main() -> DynResult<()> {
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
-
     let args: Vec<String> = env::args().collect();
     let program = args[0].clone();
 
+    if args.get(1).map(String::as_str) == Some("memcard") {
+        env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+        return commands::memcard::run(&program, &args[2..]);
+    }
+
+    if args.get(1).map(String::as_str) == Some("disc") {
+        env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
+        return commands::disc::run(&program, &args[2..]);
+    }
+
     let mut opts = Options::new();
     opts.optflag("h", "help", "print this help menu");
+    opts.optmulti("c", "cheats", "load Gecko/Action Replay codes", "FILE");
+    opts.optopt("", "config", "global config file", "FILE");
+    opts.optopt(
+        "g",
+        "game-settings",
+        "directory of per-game settings and patch files",
+        "DIR",
+    );
+    opts.optflag("", "headless", "run without a window");
+    opts.optopt("", "ipl", "IPL image, booted when no game is given", "FILE");
+    opts.optopt("", "dsp-rom", "DSP instruction ROM", "FILE");
+    opts.optopt("", "dsp-coef", "DSP coefficient ROM", "FILE");
+    opts.optopt(
+        "",
+        "slot-a",
+        "device in slot A: memcard, usbgecko, sdgecko or none",
+        "DEVICE",
+    );
+    opts.optopt("", "slot-b", "device in slot B", "DEVICE");
+    opts.optopt(
+        "",
+        "usb-gecko-port",
+        "TCP port of a USB Gecko in slot A, slot B uses the next port",
+        "PORT",
+    );
+    opts.optopt(
+        "",
+        "serial-port-1",
+        "device in the serial port 1: bba or none",
+        "DEVICE",
+    );
+    opts.optopt(
+        "",
+        "port-1",
+        "device in controller port 1: controller, gba, keyboard, wheel, bongos or none",
+        "DEVICE",
+    );
+    opts.optopt("", "port-2", "device in controller port 2", "DEVICE");
+    opts.optopt("", "port-3", "device in controller port 3", "DEVICE");
+    opts.optopt("", "port-4", "device in controller port 4", "DEVICE");
+    opts.optopt(
+        "",
+        "bba-backend",
+        "Broadband Adapter backend: udp or tap",
+        "BACKEND",
+    );
+    opts.optopt("", "bba-mac", "Broadband Adapter MAC address", "MAC");
+    opts.optopt("", "bba-tap", "TAP interface of the tap backend", "NAME");
+    opts.optopt("", "bba-local", "local address of the udp backend", "ADDR");
+    opts.optopt("", "bba-peer", "peer address of the udp backend", "ADDR");
+    opts.optopt(
+        "",
+        "memcard-a",
+        "memory card image or .gci folder in slot A",
+        "PATH",
+    );
+    opts.optopt(
+        "",
+        "memcard-b",
+        "memory card image or .gci folder in slot B",
+        "PATH",
+    );
+    opts.optopt(
+        "",
+        "memcard-size",
+        "blocks of new memory cards: 59, 123, 251, 507, 1019 or 2043",
+        "BLOCKS",
+    );
+    opts.optopt(
+        "",
+        "sd-card-a",
+        "disk image of an SD Gecko in slot A",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "sd-card-b",
+        "disk image of an SD Gecko in slot B",
+        "FILE",
+    );
+    opts.optopt("", "region", "ntsc-j, ntsc-u, pal or auto", "REGION");
+    opts.optopt(
+        "",
+        "language",
+        "english, german, french, spanish, italian or dutch",
+        "LANG",
+    );
+    opts.optopt("", "sram", "file SRAM is loaded from and saved to", "FILE");
+    opts.optmulti(
+        "",
+        "sram-setting",
+        "set an SRAM setting, e.g. VideoMode=pal or ProgressiveScan=true",
+        "KEY=VALUE",
+    );
+    opts.optopt(
+        "",
+        "rtc-time",
+        "fixed RTC start time in seconds since 1970, or host",
+        "SECONDS",
+    );
+    opts.optopt(
+        "",
+        "log",
+        "log filter, e.g. info,rustcube::dsp=warn",
+        "FILTER",
+    );
+    opts.optopt(
+        "",
+        "frame-limit",
+        "frames per second, 0 for unlimited",
+        "FPS",
+    );
+    opts.optopt("", "frames", "stop after running N frames", "N");
+    opts.optmulti(
+        "",
+        "press-reset",
+        "press the reset button once N frames have run",
+        "N",
+    );
+    opts.optopt(
+        "",
+        "record-movie",
+        "record controller input from power on",
+        "FILE",
+    );
+    opts.optopt("", "play-movie", "replay recorded controller input", "FILE");
+    opts.optopt(
+        "",
+        "input-script",
+        "drive controllers from a script, e.g. `frame 120: press A`",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "on-exit",
+        "once all frames have run: quit or wait",
+        "ACTION",
+    );
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
@@ -30,30 +221,119 @@ fn main() -> DynResult<()> {
         return Ok(());
     }
 
-    let file_name = if !matches.free.is_empty() {
-        Path::new(matches.free[0].as_str())
-    } else {
-        print_usage(&program, &opts);
-        return Ok(());
+    let mut config = match matches.opt_str("config") {
+        Some(path) => Config::load(path)?,
+        None if Path::new(DEFAULT_CONFIG).is_file() => Config::load(DEFAULT_CONFIG)?,
+        None => Config::default(),
+    };
+
+    for (name, section, key) in CONFIG_OPTIONS {
+        if let Some(value) = matches.opt_str(name) {
+            config
+                .set(section, key, &value)
+                .map_err(|e| format!("--{name}: {e}"))?;
+        }
+    }
+
+    for setting in matches.opt_strs("sram-setting") {
+        let (key, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("--sram-setting: expected KEY=VALUE, got `{setting}`"))?;
+
+        config
+            .set("SRAM", key.trim(), value.trim())
+            .map_err(|e| format!("--sram-setting: {e}"))?;
+    }
+
+    if matches.opt_present("headless") {
+        config.headless = true;
+    }
+
+    let movie = match matches.opt_str("play-movie") {
+        Some(path) => {
+            let movie = Movie::open(&path).map_err(|e| format!("{path}: {e}"))?;
+            for (section, key, value) in movie.header.settings.iter() {
+                config
+                    .set(section, key, value)
+                    .map_err(|e| format!("{path}: {e}"))?;
+            }
+            Some(movie)
+        }
+        None => None,
     };
 
-    let mut sys = System::default();
-
-    match file_name.extension() {
-        Some(ext) => {
-            if ext == "dol" {
-                sys.load_dol(file_name);
-            } else if ext == "iso" || ext == "gcm" {
-                sys.load_iso(file_name);
-            } else {
-                // assume ipl
-                sys.load_ipl(file_name);
+    // replays need the RTC to start at the time the movie was recorded
+    if matches.opt_present("record-movie") && config.rtc_time.is_none() {
+        config.rtc_time = Some(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or(0),
+        );
+    }
+
+    let mut logger = env_logger::Builder::from_env(Env::default().default_filter_or("info"));
+    if let Some(filter) = config.log_filter.as_ref() {
+        logger.parse_filters(filter);
+    }
+    logger.init();
+
+    let file_name = match (matches.free.first(), config.ipl.is_some()) {
+        (Some(file_name), _) => Some(Path::new(file_name.as_str())),
+        (None, true) => None,
+        (None, false) => {
+            print_usage(&program, &opts);
+            return Ok(());
+        }
+    };
+
+    let mut sys = System::new(config);
+
+    if let Some(dir) = matches.opt_str("g") {
+        sys.set_game_settings_dir(dir);
+    }
+
+    if let Some(file_name) = file_name {
+        match file_name.extension() {
+            Some(ext) => {
+                if ext == "dol" {
+                    sys.load_dol(file_name);
+                } else if ext == "iso" || ext == "gcm" {
+                    sys.load_iso(file_name);
+                } else {
+                    // assume ipl
+                    sys.load_ipl(file_name);
+                }
             }
+            None => sys.load_ipl(file_name),
         }
-        None => sys.load_ipl(file_name),
     }
 
-    loop {
-        sys.step();
+    for frame in matches.opt_strs("press-reset") {
+        let frame = frame
+            .parse()
+            .map_err(|_| format!("--press-reset: invalid frame `{frame}`"))?;
+        sys.press_reset_at(frame);
     }
+
+    for path in matches.opt_strs("c") {
+        sys.load_cheats(path)?;
+    }
+
+    if let Some(path) = matches.opt_str("input-script") {
+        sys.set_input(Box::new(InputScript::open(path)?));
+    }
+
+    if let Some(path) = matches.opt_str("record-movie") {
+        sys.record_movie(&path)
+            .map_err(|e| format!("{path}: {e}"))?;
+    }
+
+    if let Some(movie) = movie {
+        sys.play_movie(movie)?;
+    }
+
+    sys.run();
+
+    Ok(())
 }
@@ -0,0 +1,697 @@
+mod folder;
+mod gci;
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::config::Language;
+
+pub(crate) use self::folder::GciFolder;
+pub use self::gci::{DirEntry, Gci, DIR_ENTRY_SIZE};
+
+/// Size of a card block
+pub const BLOCK_SIZE: usize = 0x2000;
+/// Header, two directory and two block allocation map copies
+pub const SYSTEM_BLOCKS: usize = 5;
+/// Card capacities in usable blocks, official cards hold 59, 251 or 1019 blocks
+pub const CARD_SIZES: [u16; 6] = [59, 123, 251, 507, 1019, 2043];
+
+/// Flash ids of slots A and B in the default SRAM
+pub const DEFAULT_FLASH_IDS: [&[u8; 12]; 2] = [b"DOLPHINSLOTA", b"DOLPHINSLOTB"];
+
+const MAX_ENTRIES: usize = 127;
+
+/// Seconds between the unix epoch and the console epoch, 2000-01-01
+pub(crate) const CONSOLE_EPOCH: u64 = 946_684_800;
+/// Time base ticks per second (bus clock / 4)
+const TIMER_FREQUENCY: u64 = 40_500_000;
+
+const HEADER_BLOCK: usize = 0;
+const DIRECTORY_BLOCKS: [usize; 2] = [1, 2];
+const BAT_BLOCKS: [usize; 2] = [3, 4];
+
+const HEADER_SERIAL: usize = 0x00;
+const HEADER_FORMAT_TIME: usize = 0x0C;
+const HEADER_SRAM_LANGUAGE: usize = 0x18;
+const HEADER_SIZE_MBITS: usize = 0x22;
+const HEADER_ENCODING: usize = 0x24;
+const HEADER_CHECKSUM: usize = 0x1FC;
+
+const DIRECTORY_UPDATE_COUNTER: usize = 0x1FFA;
+const DIRECTORY_CHECKSUM: usize = 0x1FFC;
+
+const BAT_CHECKSUM: usize = 0x00;
+const BAT_UPDATE_COUNTER: usize = 0x04;
+const BAT_FREE_BLOCKS: usize = 0x06;
+const BAT_LAST_ALLOCATED: usize = 0x08;
+const BAT_MAP: usize = 0x0A;
+
+const BAT_FREE: u16 = 0x0000;
+const BAT_LAST: u16 = 0xFFFF;
+
+/// Raw memory card image size in bytes
+pub fn card_size(blocks: u16) -> usize {
+    (blocks as usize + SYSTEM_BLOCKS) * BLOCK_SIZE
+}
+
+/// Current time in time base ticks since the console epoch, stored when formatting
+pub fn format_time_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs().saturating_sub(CONSOLE_EPOCH) * TIMER_FREQUENCY)
+        .unwrap_or(0)
+}
+
+/// 16-bit additive checksum and inverted checksum used by the card filesystem
+fn checksums(data: &[u8]) -> (u16, u16) {
+    let mut checksum: u16 = 0;
+    let mut checksum_inv: u16 = 0;
+
+    for word in data.chunks_exact(2) {
+        let word = BigEndian::read_u16(word);
+
+        checksum = checksum.wrapping_add(word);
+        checksum_inv = checksum_inv.wrapping_add(!word);
+    }
+
+    // 0xFFFF is reserved for erased flash
+    if checksum == 0xFFFF {
+        checksum = 0;
+    }
+    if checksum_inv == 0xFFFF {
+        checksum_inv = 0;
+    }
+
+    (checksum, checksum_inv)
+}
+
+fn invalid_data(message: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, message.into())
+}
+
+/// Memory card image with the card filesystem: a header block, followed by two copies of the
+/// directory and two copies of the block allocation map (BAT). The copy with the higher update
+/// counter is active, modifications are written to the other copy.
+pub struct Card {
+    data: Vec<u8>,
+}
+
+impl Card {
+    pub fn new(data: Vec<u8>) -> Result<Card, Error> {
+        if !CARD_SIZES
+            .iter()
+            .any(|blocks| card_size(*blocks) == data.len())
+        {
+            return Err(invalid_data("invalid memory card size"));
+        }
+
+        Ok(Card { data })
+    }
+
+    /// Read a raw card image
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Card, Error> {
+        Card::new(fs::read(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, &self.data)
+    }
+
+    /// Create a formatted card. `flash_id` is the SRAM flash id of the slot, the serial
+    /// number is derived from it so the card is accepted as formatted on this console.
+    pub fn format(blocks: u16, flash_id: &[u8; 12], format_time: u64, language: Language) -> Card {
+        let mut data = vec![0xFF; card_size(blocks)];
+        let size_mbits = (data.len() * 8 / (1024 * 1024)) as u16;
+
+        let header = &mut data[..BLOCK_SIZE];
+        header[..0x26].fill(0);
+
+        let mut rand = format_time;
+        for (i, id) in flash_id.iter().enumerate() {
+            rand = (rand.wrapping_mul(0x41C6_4E6D).wrapping_add(0x3039)) >> 16;
+            header[HEADER_SERIAL + i] = id.wrapping_add(rand as u8);
+            rand = (rand.wrapping_mul(0x41C6_4E6D).wrapping_add(0x3039)) >> 16;
+            rand &= 0x7FFF;
+        }
+
+        BigEndian::write_u64(&mut header[HEADER_FORMAT_TIME..], format_time);
+        BigEndian::write_u32(&mut header[HEADER_SRAM_LANGUAGE..], language as u32);
+        BigEndian::write_u16(&mut header[HEADER_SIZE_MBITS..], size_mbits);
+        BigEndian::write_u16(&mut header[HEADER_ENCODING..], 0);
+
+        let mut card = Card { data };
+        card.fix_header_checksum();
+
+        for block in DIRECTORY_BLOCKS {
+            let directory = card.block_mut(block);
+            directory.fill(0xFF);
+            BigEndian::write_u16(&mut directory[DIRECTORY_UPDATE_COUNTER..], 0);
+            card.fix_directory_checksum(block);
+        }
+
+        let total = card.total_blocks();
+
+        for block in BAT_BLOCKS {
+            let bat = card.block_mut(block);
+            bat.fill(0);
+            BigEndian::write_u16(&mut bat[BAT_FREE_BLOCKS..], total - SYSTEM_BLOCKS as u16);
+            BigEndian::write_u16(&mut bat[BAT_LAST_ALLOCATED..], SYSTEM_BLOCKS as u16 - 1);
+            card.fix_bat_checksum(block);
+        }
+
+        card
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Number of blocks available for files
+    pub fn blocks(&self) -> u16 {
+        self.total_blocks() - SYSTEM_BLOCKS as u16
+    }
+
+    pub fn free_blocks(&self) -> u16 {
+        BigEndian::read_u16(&self.bat()[BAT_FREE_BLOCKS..])
+    }
+
+    /// Directory entries in use, with their directory index
+    pub fn entries(&self) -> Vec<(usize, DirEntry)> {
+        let directory = self.directory();
+
+        (0..MAX_ENTRIES)
+            .map(|i| (i, DirEntry::from_bytes(&directory[i * DIR_ENTRY_SIZE..])))
+            .filter(|(_, entry)| !entry.is_empty())
+            .collect()
+    }
+
+    pub fn export(&self, index: usize) -> Result<Gci, Error> {
+        let entry = self.entry(index)?;
+
+        let mut data = Vec::with_capacity(entry.block_count() as usize * BLOCK_SIZE);
+
+        for block in self.chain(entry.first_block(), entry.block_count())? {
+            data.extend_from_slice(self.block(block as usize));
+        }
+
+        Ok(Gci { entry, data })
+    }
+
+    pub fn import(&mut self, gci: &Gci) -> Result<(), Error> {
+        let count = gci.entry.block_count();
+
+        if gci.data.len() != count as usize * BLOCK_SIZE {
+            return Err(invalid_data(
+                "save data size does not match its block count",
+            ));
+        }
+
+        let entries = self.entries();
+
+        if entries.iter().any(|(_, entry)| entry.same_file(&gci.entry)) {
+            return Err(invalid_data(format!("{} already exists", gci.entry)));
+        }
+
+        let Some(index) = (0..MAX_ENTRIES).find(|i| entries.iter().all(|(n, _)| n != i)) else {
+            return Err(invalid_data("no free directory entries"));
+        };
+
+        if count == 0 || count > self.free_blocks() {
+            return Err(invalid_data(format!(
+                "not enough free blocks for {} ({} needed, {} free)",
+                gci.entry,
+                count,
+                self.free_blocks()
+            )));
+        }
+
+        let mut bat = self.bat().to_vec();
+        let total = self.total_blocks();
+        let mut last = BigEndian::read_u16(&bat[BAT_LAST_ALLOCATED..]);
+        let mut blocks = Vec::with_capacity(count as usize);
+
+        // the free block count can't be trusted, look at each block at most once
+        for _ in SYSTEM_BLOCKS as u16..total {
+            if blocks.len() == count as usize {
+                break;
+            }
+
+            last = last.saturating_add(1);
+            if last >= total || last < SYSTEM_BLOCKS as u16 {
+                last = SYSTEM_BLOCKS as u16;
+            }
+
+            if bat_entry(&bat, last) == BAT_FREE {
+                blocks.push(last);
+            }
+        }
+
+        if blocks.len() < count as usize {
+            return Err(invalid_data("block allocation map is inconsistent"));
+        }
+
+        for (i, block) in blocks.iter().enumerate() {
+            let next = blocks.get(i + 1).copied().unwrap_or(BAT_LAST);
+            set_bat_entry(&mut bat, *block, next);
+
+            let data = &gci.data[i * BLOCK_SIZE..(i + 1) * BLOCK_SIZE];
+            self.block_mut(*block as usize).copy_from_slice(data);
+        }
+
+        BigEndian::write_u16(&mut bat[BAT_LAST_ALLOCATED..], last);
+        BigEndian::write_u16(&mut bat[BAT_FREE_BLOCKS..], self.free_blocks() - count);
+
+        let mut entry = gci.entry.clone();
+        entry.set_first_block(blocks[0]);
+
+        let mut directory = self.directory().to_vec();
+        directory[index * DIR_ENTRY_SIZE..(index + 1) * DIR_ENTRY_SIZE]
+            .copy_from_slice(entry.as_bytes());
+
+        self.commit(directory, bat);
+
+        Ok(())
+    }
+
+    pub fn delete(&mut self, index: usize) -> Result<(), Error> {
+        let entry = self.entry(index)?;
+        let blocks = self.chain(entry.first_block(), entry.block_count())?;
+
+        let mut bat = self.bat().to_vec();
+
+        for block in blocks.iter() {
+            set_bat_entry(&mut bat, *block, BAT_FREE);
+        }
+
+        let free = self.free_blocks() + blocks.len() as u16;
+        BigEndian::write_u16(&mut bat[BAT_FREE_BLOCKS..], free);
+
+        let mut directory = self.directory().to_vec();
+        directory[index * DIR_ENTRY_SIZE..(index + 1) * DIR_ENTRY_SIZE].fill(0xFF);
+
+        self.commit(directory, bat);
+
+        Ok(())
+    }
+
+    /// Check the filesystem, returning the problems found
+    pub fn verify(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if !self.header_valid() {
+            problems.push("header checksum is invalid".to_string());
+        }
+
+        for (n, block) in DIRECTORY_BLOCKS.into_iter().enumerate() {
+            if !self.directory_valid(block) {
+                problems.push(format!("directory copy {} checksum is invalid", n + 1));
+            }
+        }
+
+        for (n, block) in BAT_BLOCKS.into_iter().enumerate() {
+            if !self.bat_valid(block) {
+                problems.push(format!(
+                    "block allocation map copy {} checksum is invalid",
+                    n + 1
+                ));
+            }
+        }
+
+        let (_, used, mut file_problems) = self.check_files();
+        problems.append(&mut file_problems);
+
+        let bat = self.bat();
+        let free = (SYSTEM_BLOCKS as u16..self.total_blocks())
+            .filter(|block| bat_entry(bat, *block) == BAT_FREE)
+            .count() as u16;
+
+        if free != self.free_blocks() {
+            problems.push(format!(
+                "free block count is {}, {} blocks are free",
+                self.free_blocks(),
+                free
+            ));
+        }
+
+        let lost = (SYSTEM_BLOCKS as u16..self.total_blocks())
+            .filter(|block| bat_entry(bat, *block) != BAT_FREE && !used.contains(block))
+            .count();
+
+        if lost != 0 {
+            problems.push(format!(
+                "{lost} blocks are allocated but not used by any file"
+            ));
+        }
+
+        problems
+    }
+
+    /// Rebuild the directory and BAT: files with broken block chains are removed, unused blocks
+    /// are freed and all checksums are recomputed. Returns the problems found and files removed.
+    pub fn repair(&mut self) -> Vec<String> {
+        let mut repairs = self.verify();
+
+        if !self.header_valid() {
+            self.fix_header_checksum();
+        }
+
+        let (files, _, _) = self.check_files();
+
+        let mut directory = self.directory().to_vec();
+        let mut bat = self.bat().to_vec();
+        let old_bat = bat.clone();
+
+        for (index, entry) in self.entries() {
+            if !files.iter().any(|(i, _)| *i == index) {
+                repairs.push(format!("{entry} removed"));
+                directory[index * DIR_ENTRY_SIZE..(index + 1) * DIR_ENTRY_SIZE].fill(0xFF);
+            }
+        }
+
+        bat[BAT_MAP..].fill(0);
+        for (_, blocks) in files.iter() {
+            for block in blocks.iter() {
+                set_bat_entry(&mut bat, *block, bat_entry(&old_bat, *block));
+            }
+        }
+
+        let free = (SYSTEM_BLOCKS as u16..self.total_blocks())
+            .filter(|block| bat_entry(&bat, *block) == BAT_FREE)
+            .count() as u16;
+        BigEndian::write_u16(&mut bat[BAT_FREE_BLOCKS..], free);
+
+        let last = BigEndian::read_u16(&bat[BAT_LAST_ALLOCATED..]);
+        if last < SYSTEM_BLOCKS as u16 - 1 || last >= self.total_blocks() {
+            BigEndian::write_u16(&mut bat[BAT_LAST_ALLOCATED..], SYSTEM_BLOCKS as u16 - 1);
+        }
+
+        // write both copies so the backup is valid too
+        self.commit(directory.clone(), bat.clone());
+        self.commit(directory, bat);
+
+        repairs
+    }
+
+    /// Files with valid block chains, with the blocks they use, and the problems of the others
+    #[allow(clippy::type_complexity)]
+    fn check_files(&self) -> (Vec<(usize, Vec<u16>)>, BTreeSet<u16>, Vec<String>) {
+        let mut files = Vec::new();
+        let mut used = BTreeSet::new();
+        let mut problems = Vec::new();
+
+        for (index, entry) in self.entries() {
+            match self.chain(entry.first_block(), entry.block_count()) {
+                Ok(blocks) if blocks.iter().any(|block| used.contains(block)) => {
+                    problems.push(format!("{entry}: blocks are shared with another file"));
+                }
+                Ok(blocks) => {
+                    used.extend(blocks.iter().copied());
+                    files.push((index, blocks));
+                }
+                Err(e) => problems.push(format!("{entry}: {e}")),
+            }
+        }
+
+        (files, used, problems)
+    }
+
+    fn entry(&self, index: usize) -> Result<DirEntry, Error> {
+        self.entries()
+            .into_iter()
+            .find(|(i, _)| *i == index)
+            .map(|(_, entry)| entry)
+            .ok_or_else(|| invalid_data(format!("no file at directory index {index}")))
+    }
+
+    /// Blocks of a file, following the BAT from its first block
+    fn chain(&self, first: u16, count: u16) -> Result<Vec<u16>, Error> {
+        let bat = self.bat();
+        let total = self.total_blocks();
+        let mut blocks = Vec::with_capacity(count as usize);
+        let mut block = first;
+
+        for i in 0..count {
+            if block < SYSTEM_BLOCKS as u16 || block >= total || blocks.contains(&block) {
+                return Err(invalid_data(format!("broken block chain at block {block}")));
+            }
+
+            blocks.push(block);
+
+            let next = bat_entry(bat, block);
+            if (next == BAT_LAST) != (i == count - 1) {
+                return Err(invalid_data(format!("broken block chain at block {block}")));
+            }
+            block = next;
+        }
+
+        Ok(blocks)
+    }
+
+    fn total_blocks(&self) -> u16 {
+        (self.data.len() / BLOCK_SIZE) as u16
+    }
+
+    fn block(&self, block: usize) -> &[u8] {
+        &self.data[block * BLOCK_SIZE..(block + 1) * BLOCK_SIZE]
+    }
+
+    fn block_mut(&mut self, block: usize) -> &mut [u8] {
+        &mut self.data[block * BLOCK_SIZE..(block + 1) * BLOCK_SIZE]
+    }
+
+    /// Active copy of a directory or BAT, the valid copy with the higher update counter
+    fn active(&self, blocks: [usize; 2], counter: usize, valid: fn(&Card, usize) -> bool) -> usize {
+        let [a, b] = blocks;
+
+        match (valid(self, a), valid(self, b)) {
+            (true, false) => a,
+            (false, true) => b,
+            _ => {
+                let count_a = BigEndian::read_u16(&self.block(a)[counter..]);
+                let count_b = BigEndian::read_u16(&self.block(b)[counter..]);
+
+                if (count_b.wrapping_sub(count_a) as i16) > 0 {
+                    b
+                } else {
+                    a
+                }
+            }
+        }
+    }
+
+    fn directory_block(&self) -> usize {
+        self.active(
+            DIRECTORY_BLOCKS,
+            DIRECTORY_UPDATE_COUNTER,
+            Card::directory_valid,
+        )
+    }
+
+    fn bat_block(&self) -> usize {
+        self.active(BAT_BLOCKS, BAT_UPDATE_COUNTER, Card::bat_valid)
+    }
+
+    fn directory(&self) -> &[u8] {
+        self.block(self.directory_block())
+    }
+
+    fn bat(&self) -> &[u8] {
+        self.block(self.bat_block())
+    }
+
+    /// Write a modified directory and BAT to the inactive copies, making them active
+    fn commit(&mut self, mut directory: Vec<u8>, mut bat: Vec<u8>) {
+        let directory_block =
+            DIRECTORY_BLOCKS[(self.directory_block() == DIRECTORY_BLOCKS[0]) as usize];
+        let bat_block = BAT_BLOCKS[(self.bat_block() == BAT_BLOCKS[0]) as usize];
+
+        let counter = BigEndian::read_u16(&directory[DIRECTORY_UPDATE_COUNTER..]);
+        BigEndian::write_u16(
+            &mut directory[DIRECTORY_UPDATE_COUNTER..],
+            counter.wrapping_add(1),
+        );
+
+        let counter = BigEndian::read_u16(&bat[BAT_UPDATE_COUNTER..]);
+        BigEndian::write_u16(&mut bat[BAT_UPDATE_COUNTER..], counter.wrapping_add(1));
+
+        self.block_mut(directory_block).copy_from_slice(&directory);
+        self.fix_directory_checksum(directory_block);
+
+        self.block_mut(bat_block).copy_from_slice(&bat);
+        self.fix_bat_checksum(bat_block);
+    }
+
+    /// Header and active directory and BAT checksums are valid
+    pub(crate) fn is_valid(&self) -> bool {
+        self.header_valid()
+            && self.directory_valid(self.directory_block())
+            && self.bat_valid(self.bat_block())
+    }
+
+    fn header_valid(&self) -> bool {
+        let header = self.block(HEADER_BLOCK);
+        checksums(&header[..HEADER_CHECKSUM]) == read_checksums(&header[HEADER_CHECKSUM..])
+    }
+
+    fn directory_valid(&self, block: usize) -> bool {
+        let directory = self.block(block);
+        checksums(&directory[..DIRECTORY_CHECKSUM])
+            == read_checksums(&directory[DIRECTORY_CHECKSUM..])
+    }
+
+    fn bat_valid(&self, block: usize) -> bool {
+        let bat = self.block(block);
+        checksums(&bat[BAT_UPDATE_COUNTER..]) == read_checksums(&bat[BAT_CHECKSUM..])
+    }
+
+    fn fix_header_checksum(&mut self) {
+        let header = self.block_mut(HEADER_BLOCK);
+        let sums = checksums(&header[..HEADER_CHECKSUM]);
+        write_checksums(&mut header[HEADER_CHECKSUM..], sums);
+    }
+
+    fn fix_directory_checksum(&mut self, block: usize) {
+        let directory = self.block_mut(block);
+        let sums = checksums(&directory[..DIRECTORY_CHECKSUM]);
+        write_checksums(&mut directory[DIRECTORY_CHECKSUM..], sums);
+    }
+
+    fn fix_bat_checksum(&mut self, block: usize) {
+        let bat = self.block_mut(block);
+        let sums = checksums(&bat[BAT_UPDATE_COUNTER..]);
+        write_checksums(&mut bat[BAT_CHECKSUM..], sums);
+    }
+}
+
+fn read_checksums(data: &[u8]) -> (u16, u16) {
+    (BigEndian::read_u16(data), BigEndian::read_u16(&data[2..]))
+}
+
+fn write_checksums(data: &mut [u8], (checksum, checksum_inv): (u16, u16)) {
+    BigEndian::write_u16(data, checksum);
+    BigEndian::write_u16(&mut data[2..], checksum_inv);
+}
+
+fn bat_entry(bat: &[u8], block: u16) -> u16 {
+    BigEndian::read_u16(&bat[BAT_MAP + (block as usize - SYSTEM_BLOCKS) * 2..])
+}
+
+fn set_bat_entry(bat: &mut [u8], block: u16, value: u16) {
+    BigEndian::write_u16(
+        &mut bat[BAT_MAP + (block as usize - SYSTEM_BLOCKS) * 2..],
+        value,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gci(name: &str, blocks: u16) -> Gci {
+        let mut entry = DirEntry::new(*b"GALE", *b"01", name);
+        entry.set_block_count(blocks);
+
+        Gci {
+            entry,
+            data: (0..blocks as usize * BLOCK_SIZE).map(|i| i as u8).collect(),
+        }
+    }
+
+    #[test]
+    fn format() {
+        let card = Card::format(59, b"DOLPHINSLOTA", 0x1234_5678, Language::English);
+
+        assert_eq!(card.data().len(), 0x8_0000);
+        assert!(card.header_valid());
+        assert!(card.directory_valid(1) && card.directory_valid(2));
+        assert!(card.bat_valid(3) && card.bat_valid(4));
+        assert_eq!(card.free_blocks(), 59);
+        assert!(card.entries().is_empty());
+    }
+
+    #[test]
+    fn verify_and_repair() {
+        let mut card = Card::format(59, b"DOLPHINSLOTA", 0, Language::English);
+
+        card.import(&gci("save a", 3)).unwrap();
+        card.import(&gci("save b", 2)).unwrap();
+        assert!(card.verify().is_empty());
+
+        // corrupt the backup directory and break the chain of the first file
+        card.data[2 * BLOCK_SIZE] ^= 1;
+        let bat_block = card.bat_block();
+        set_bat_entry(card.block_mut(bat_block), 6, BAT_LAST);
+        card.fix_bat_checksum(bat_block);
+
+        let problems = card.verify();
+        assert_eq!(problems.len(), 3);
+
+        let repairs = card.repair();
+        assert_eq!(repairs.last().unwrap(), "GALE01 save a removed");
+        assert!(card.verify().is_empty());
+        assert_eq!(card.entries().len(), 1);
+        assert_eq!(card.free_blocks(), 57);
+    }
+
+    #[test]
+    fn import_export_delete() {
+        let mut card = Card::format(59, b"DOLPHINSLOTA", 0, Language::English);
+
+        let a = gci("save a", 3);
+        let b = gci("save b", 2);
+
+        card.import(&a).unwrap();
+        card.import(&b).unwrap();
+
+        assert!(card.import(&a).is_err());
+        assert!(card.import(&gci("too big", 55)).is_err());
+        assert_eq!(card.free_blocks(), 54);
+
+        let entries = card.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].1.file_name(), "save b");
+        assert_eq!(entries[1].1.first_block(), 8);
+
+        let exported = card.export(entries[0].0).unwrap();
+        assert_eq!(exported.data, a.data);
+
+        card.delete(entries[0].0).unwrap();
+
+        assert_eq!(card.entries().len(), 1);
+        assert_eq!(card.free_blocks(), 57);
+        assert!(card.directory_valid(card.directory_block()));
+        assert!(card.bat_valid(card.bat_block()));
+
+        // freed blocks are reused once the allocator wraps around
+        card.import(&gci("big", 57)).unwrap();
+        assert_eq!(card.free_blocks(), 0);
+        // the freed directory entry is reused too
+        let (index, entry) = &card.entries()[0];
+        assert_eq!(entry.file_name(), "big");
+        assert!(card.export(*index).unwrap().data == gci("big", 57).data);
+    }
+
+    #[test]
+    fn import_inflated_free_count() {
+        let mut card = Card::format(59, b"DOLPHINSLOTA", 0, Language::English);
+        card.import(&gci("save a", 58)).unwrap();
+
+        // claim more free blocks than the allocation map has
+        let bat_block = card.bat_block();
+        BigEndian::write_u16(&mut card.block_mut(bat_block)[BAT_FREE_BLOCKS..], 10);
+        card.fix_bat_checksum(bat_block);
+
+        assert!(card.import(&gci("save b", 2)).is_err());
+    }
+}
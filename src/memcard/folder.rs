@@ -0,0 +1,172 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+use super::{Card, DirEntry, Gci};
+use crate::config::Language;
+
+/// Directory of `.gci` saves presented as a memory card. The card filesystem is built from the
+/// saves when the card is inserted, saves are written back out after the card is modified.
+pub(crate) struct GciFolder {
+    path: PathBuf,
+    /// Contents of the `.gci` files this folder manages, by file name
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl GciFolder {
+    /// Read the saves in `path` onto a freshly formatted card
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        blocks: u16,
+        flash_id: &[u8; 12],
+        language: Language,
+    ) -> Result<(GciFolder, Card), Error> {
+        let path = path.as_ref();
+
+        let mut card = Card::format(blocks, flash_id, super::format_time_now(), language);
+        let mut files = BTreeMap::new();
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("gci"))
+            })
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let result = fs::read(&path).and_then(|data| {
+                let gci = Gci::from_bytes(&data)?;
+                card.import(&gci)?;
+                Ok(gci)
+            });
+
+            match result {
+                Ok(gci) => {
+                    info!("Memory card folder: loaded {}", gci.entry);
+
+                    let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                    files.insert(name, gci.to_bytes());
+                }
+                Err(e) => warn!("Memory card folder: skipping {}: {}", path.display(), e),
+            }
+        }
+
+        let folder = GciFolder {
+            path: path.to_path_buf(),
+            files,
+        };
+
+        Ok((folder, card))
+    }
+
+    /// Write changed saves back to the folder and remove the files of deleted saves
+    pub fn write_back(&mut self, data: &[u8]) {
+        let card = match Card::new(data.to_vec()) {
+            Ok(card) if card.is_valid() => card,
+            _ => {
+                warn!("Memory card folder: card filesystem is invalid, not writing saves");
+                return;
+            }
+        };
+
+        let mut files = BTreeMap::new();
+
+        for (index, entry) in card.entries() {
+            match card.export(index) {
+                Ok(gci) => {
+                    let data = gci.to_bytes();
+
+                    // keep the name of files loaded from the folder
+                    let name = self
+                        .files
+                        .iter()
+                        .find(|(_, file)| gci.entry.same_file(&DirEntry::from_bytes(file)))
+                        .map(|(name, _)| name.clone())
+                        .unwrap_or_else(|| entry.gci_file_name());
+
+                    files.insert(name, data);
+                }
+                Err(e) => {
+                    warn!("Memory card folder: unable to export {}: {}", entry, e);
+                    return;
+                }
+            }
+        }
+
+        for (name, data) in files.iter() {
+            if self.files.get(name) == Some(data) {
+                continue;
+            }
+
+            let path = self.path.join(name);
+
+            info!("Memory card folder: writing {}", path.display());
+
+            if let Err(e) = fs::write(&path, data) {
+                error!("Unable to write {}: {}", path.display(), e);
+            }
+        }
+
+        for name in self.files.keys().filter(|name| !files.contains_key(*name)) {
+            let path = self.path.join(name);
+
+            info!("Memory card folder: removing {}", path.display());
+
+            if let Err(e) = fs::remove_file(&path) {
+                error!("Unable to remove {}: {}", path.display(), e);
+            }
+        }
+
+        self.files = files;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memcard::BLOCK_SIZE;
+
+    #[test]
+    fn load_and_write_back() {
+        let dir = std::env::temp_dir().join(format!("rustcube-gci-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut entry = DirEntry::new(*b"GALE", *b"01", "settings");
+        entry.set_block_count(1);
+        let gci = Gci {
+            entry,
+            data: vec![0x12; BLOCK_SIZE],
+        };
+        fs::write(dir.join("settings.gci"), gci.to_bytes()).unwrap();
+
+        let (mut folder, mut card) =
+            GciFolder::open(&dir, 59, b"DOLPHINSLOTA", Language::English).unwrap();
+        assert_eq!(card.entries().len(), 1);
+
+        // game creates a new save and deletes the old one
+        let mut entry = DirEntry::new(*b"GALE", *b"01", "new save");
+        entry.set_block_count(2);
+        card.import(&Gci {
+            entry: entry.clone(),
+            data: vec![0x34; 2 * BLOCK_SIZE],
+        })
+        .unwrap();
+        card.delete(card.entries()[0].0).unwrap();
+
+        folder.write_back(card.data());
+
+        assert!(!dir.join("settings.gci").exists());
+        let written = fs::read(dir.join(entry.gci_file_name())).unwrap();
+        assert_eq!(
+            Gci::from_bytes(&written).unwrap().data,
+            vec![0x34; 2 * BLOCK_SIZE]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
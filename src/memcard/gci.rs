@@ -0,0 +1,167 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind},
+};
+
+use byteorder::{BigEndian, ByteOrder};
+
+use super::BLOCK_SIZE;
+
+/// Size of a directory entry, also the header of a `.gci` file
+pub const DIR_ENTRY_SIZE: usize = 0x40;
+
+const ENTRY_GAME_CODE: usize = 0x00;
+const ENTRY_MAKER_CODE: usize = 0x04;
+const ENTRY_FILE_NAME: usize = 0x08;
+const ENTRY_FILE_NAME_SIZE: usize = 0x20;
+const ENTRY_MODIFIED_TIME: usize = 0x28;
+const ENTRY_PERMISSIONS: usize = 0x34;
+const ENTRY_FIRST_BLOCK: usize = 0x36;
+const ENTRY_BLOCK_COUNT: usize = 0x38;
+
+/// Directory entry of a file on the card
+#[derive(Clone, PartialEq)]
+pub struct DirEntry([u8; DIR_ENTRY_SIZE]);
+
+impl DirEntry {
+    pub fn new(game_code: [u8; 4], maker_code: [u8; 2], file_name: &str) -> DirEntry {
+        let mut data = [0; DIR_ENTRY_SIZE];
+
+        data[ENTRY_GAME_CODE..ENTRY_GAME_CODE + 4].copy_from_slice(&game_code);
+        data[ENTRY_MAKER_CODE..ENTRY_MAKER_CODE + 2].copy_from_slice(&maker_code);
+        data[0x06] = 0xFF;
+
+        let name = file_name.as_bytes();
+        let len = name.len().min(ENTRY_FILE_NAME_SIZE);
+        data[ENTRY_FILE_NAME..ENTRY_FILE_NAME + len].copy_from_slice(&name[..len]);
+
+        // public
+        data[ENTRY_PERMISSIONS] = 0x04;
+        data[0x3A..].fill(0xFF);
+
+        DirEntry(data)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> DirEntry {
+        let mut entry = [0; DIR_ENTRY_SIZE];
+        entry.copy_from_slice(&data[..DIR_ENTRY_SIZE]);
+        DirEntry(entry)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Unused entries are erased
+    pub fn is_empty(&self) -> bool {
+        self.0[ENTRY_GAME_CODE..ENTRY_GAME_CODE + 4] == [0xFF; 4]
+    }
+
+    pub fn game_code(&self) -> String {
+        text(&self.0[ENTRY_GAME_CODE..ENTRY_GAME_CODE + 4])
+    }
+
+    pub fn maker_code(&self) -> String {
+        text(&self.0[ENTRY_MAKER_CODE..ENTRY_MAKER_CODE + 2])
+    }
+
+    pub fn file_name(&self) -> String {
+        text(&self.0[ENTRY_FILE_NAME..ENTRY_FILE_NAME + ENTRY_FILE_NAME_SIZE])
+    }
+
+    /// Seconds since 2000-01-01
+    pub fn modified_time(&self) -> u32 {
+        BigEndian::read_u32(&self.0[ENTRY_MODIFIED_TIME..])
+    }
+
+    pub fn first_block(&self) -> u16 {
+        BigEndian::read_u16(&self.0[ENTRY_FIRST_BLOCK..])
+    }
+
+    pub fn set_first_block(&mut self, block: u16) {
+        BigEndian::write_u16(&mut self.0[ENTRY_FIRST_BLOCK..], block);
+    }
+
+    pub fn block_count(&self) -> u16 {
+        BigEndian::read_u16(&self.0[ENTRY_BLOCK_COUNT..])
+    }
+
+    pub fn set_block_count(&mut self, count: u16) {
+        BigEndian::write_u16(&mut self.0[ENTRY_BLOCK_COUNT..], count);
+    }
+
+    /// Files are identified by game code, maker code and file name
+    pub fn same_file(&self, other: &DirEntry) -> bool {
+        self.0[..ENTRY_FILE_NAME + ENTRY_FILE_NAME_SIZE]
+            == other.0[..ENTRY_FILE_NAME + ENTRY_FILE_NAME_SIZE]
+    }
+
+    /// Host file name of the exported save, e.g. `01-GALE-SuperSmashBros0110290334.gci`
+    pub fn gci_file_name(&self) -> String {
+        let name: String = self
+            .file_name()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "-_. ".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        format!("{}-{}-{}.gci", self.maker_code(), self.game_code(), name)
+    }
+}
+
+impl fmt::Display for DirEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{} {}",
+            self.game_code(),
+            self.maker_code(),
+            self.file_name()
+        )
+    }
+}
+
+fn text(data: &[u8]) -> String {
+    let end = data.iter().position(|c| *c == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+/// Save file in `.gci` format: the directory entry followed by the file's blocks
+pub struct Gci {
+    pub entry: DirEntry,
+    pub data: Vec<u8>,
+}
+
+impl Gci {
+    pub fn from_bytes(data: &[u8]) -> Result<Gci, Error> {
+        if data.len() < DIR_ENTRY_SIZE {
+            return Err(Error::new(ErrorKind::InvalidData, "gci file too short"));
+        }
+
+        let entry = DirEntry::from_bytes(data);
+
+        if data.len() - DIR_ENTRY_SIZE != entry.block_count() as usize * BLOCK_SIZE {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "gci size does not match its block count",
+            ));
+        }
+
+        Ok(Gci {
+            entry,
+            data: data[DIR_ENTRY_SIZE..].to_vec(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(DIR_ENTRY_SIZE + self.data.len());
+        data.extend_from_slice(self.entry.as_bytes());
+        data.extend_from_slice(&self.data);
+        data
+    }
+}
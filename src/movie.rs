@@ -0,0 +1,391 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Error, ErrorKind, Read, Write},
+    path::Path,
+};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use minifb::Key;
+
+use crate::input::{InputSource, PadState, Rumble, NUM_PORTS};
+
+const MAGIC: &[u8; 4] = b"RCM\x1A";
+const VERSION: u16 = 1;
+
+const START_COLD_BOOT: u8 = 0;
+const START_SAVE_STATE: u8 = 1;
+
+/// Bytes of a recorded controller state
+const PAD_SIZE: usize = 11;
+
+/// State the emulator is in when a movie starts
+#[derive(Clone, Debug, PartialEq)]
+pub enum MovieStart {
+    /// Power on, with no state carried over
+    ColdBoot,
+    /// Loaded from the named save state
+    SaveState(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MovieHeader {
+    pub start: MovieStart,
+    /// Game id of the disc (e.g. `GALE01`), empty when booting a DOL or the IPL
+    pub game_id: String,
+    /// SHA-1 of the IPL image, if one was loaded
+    pub ipl_hash: Option<[u8; 20]>,
+    /// SHA-1 of the memory cards in slots A and B at power on
+    pub memory_cards: [Option<[u8; 20]>; 2],
+    /// Settings recorded with, as `(section, key, value)`
+    pub settings: Vec<(String, String, String)>,
+}
+
+impl MovieHeader {
+    fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_all(MAGIC)?;
+        writer.write_u16::<BigEndian>(VERSION)?;
+
+        match &self.start {
+            MovieStart::ColdBoot => writer.write_u8(START_COLD_BOOT)?,
+            MovieStart::SaveState(name) => {
+                writer.write_u8(START_SAVE_STATE)?;
+                write_string(writer, name)?;
+            }
+        }
+
+        write_string(writer, &self.game_id)?;
+
+        write_hash(writer, &self.ipl_hash)?;
+        for hash in self.memory_cards.iter() {
+            write_hash(writer, hash)?;
+        }
+
+        writer.write_u16::<BigEndian>(self.settings.len() as u16)?;
+        for (section, key, value) in self.settings.iter() {
+            write_string(writer, section)?;
+            write_string(writer, key)?;
+            write_string(writer, value)?;
+        }
+
+        Ok(())
+    }
+
+    fn read<R: Read>(reader: &mut R) -> Result<MovieHeader, Error> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a movie file"));
+        }
+
+        let version = reader.read_u16::<BigEndian>()?;
+        if version != VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported movie version {version}"),
+            ));
+        }
+
+        let start = match reader.read_u8()? {
+            START_COLD_BOOT => MovieStart::ColdBoot,
+            START_SAVE_STATE => MovieStart::SaveState(read_string(reader)?),
+            start => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid movie start {start}"),
+                ))
+            }
+        };
+
+        let game_id = read_string(reader)?;
+
+        let ipl_hash = read_hash(reader)?;
+        let memory_cards = [read_hash(reader)?, read_hash(reader)?];
+
+        let count = reader.read_u16::<BigEndian>()?;
+        let mut settings = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            settings.push((
+                read_string(reader)?,
+                read_string(reader)?,
+                read_string(reader)?,
+            ));
+        }
+
+        Ok(MovieHeader {
+            start,
+            game_id,
+            ipl_hash,
+            memory_cards,
+            settings,
+        })
+    }
+}
+
+/// Controller state of all ports for every SI poll of a session
+#[derive(Clone, Debug, PartialEq)]
+pub struct Movie {
+    pub header: MovieHeader,
+    pub polls: Vec<[PadState; NUM_PORTS]>,
+}
+
+impl Movie {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Movie, Error> {
+        let data = fs::read(path)?;
+        Movie::read(&mut data.as_slice())
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> Result<Movie, Error> {
+        let header = MovieHeader::read(reader)?;
+
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        let record_size = PAD_SIZE * NUM_PORTS;
+        if data.len() % record_size != 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "truncated movie"));
+        }
+
+        let polls = data
+            .chunks_exact(record_size)
+            .map(|record| std::array::from_fn(|port| read_pad(&record[port * PAD_SIZE..])))
+            .collect();
+
+        Ok(Movie { header, polls })
+    }
+}
+
+/// Records the polls answered by another input source
+pub struct MovieRecorder {
+    input: Box<dyn InputSource>,
+    writer: BufWriter<File>,
+    polls: u64,
+    failed: bool,
+}
+
+impl MovieRecorder {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        header: &MovieHeader,
+        input: Box<dyn InputSource>,
+    ) -> Result<MovieRecorder, Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        header.write(&mut writer)?;
+
+        Ok(MovieRecorder {
+            input,
+            writer,
+            polls: 0,
+            failed: false,
+        })
+    }
+}
+
+impl InputSource for MovieRecorder {
+    fn poll(&mut self, keys: &[Key]) -> [PadState; NUM_PORTS] {
+        let pads = self.input.poll(keys);
+
+        if !self.failed {
+            let mut record = [0; PAD_SIZE * NUM_PORTS];
+            for (port, pad) in pads.iter().enumerate() {
+                write_pad(&mut record[port * PAD_SIZE..], pad);
+            }
+
+            if let Err(e) = self.writer.write_all(&record) {
+                error!("Movie: recording stopped after {} polls: {}", self.polls, e);
+                self.failed = true;
+            } else {
+                self.polls += 1;
+            }
+        }
+
+        pads
+    }
+
+    fn frame(&mut self, frame: u64) {
+        self.input.frame(frame);
+    }
+
+    fn rumble(&mut self, port: usize, rumble: Rumble) {
+        self.input.rumble(port, rumble);
+    }
+}
+
+impl Drop for MovieRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.writer.flush() {
+            error!("Movie: unable to save recording: {}", e);
+        }
+    }
+}
+
+/// Answers polls from a movie, controllers are released once it has been played
+pub struct MoviePlayer {
+    polls: std::vec::IntoIter<[PadState; NUM_PORTS]>,
+    finished: bool,
+}
+
+impl MoviePlayer {
+    pub fn new(movie: Movie) -> MoviePlayer {
+        MoviePlayer {
+            polls: movie.polls.into_iter(),
+            finished: false,
+        }
+    }
+}
+
+impl InputSource for MoviePlayer {
+    fn poll(&mut self, _keys: &[Key]) -> [PadState; NUM_PORTS] {
+        match self.polls.next() {
+            Some(pads) => pads,
+            None => {
+                if !self.finished {
+                    info!("Movie: playback finished");
+                    self.finished = true;
+                }
+                Default::default()
+            }
+        }
+    }
+}
+
+fn write_pad(buffer: &mut [u8], pad: &PadState) {
+    buffer[..2].copy_from_slice(&pad.buttons.to_be_bytes());
+    buffer[2..PAD_SIZE].copy_from_slice(&[
+        pad.stick_x,
+        pad.stick_y,
+        pad.substick_x,
+        pad.substick_y,
+        pad.trigger_l,
+        pad.trigger_r,
+        pad.keys[0],
+        pad.keys[1],
+        pad.keys[2],
+    ]);
+}
+
+fn read_pad(buffer: &[u8]) -> PadState {
+    PadState {
+        buttons: u16::from_be_bytes([buffer[0], buffer[1]]),
+        stick_x: buffer[2],
+        stick_y: buffer[3],
+        substick_x: buffer[4],
+        substick_y: buffer[5],
+        trigger_l: buffer[6],
+        trigger_r: buffer[7],
+        keys: [buffer[8], buffer[9], buffer[10]],
+    }
+}
+
+fn write_hash<W: Write>(writer: &mut W, hash: &Option<[u8; 20]>) -> Result<(), Error> {
+    match hash {
+        Some(hash) => {
+            writer.write_u8(1)?;
+            writer.write_all(hash)
+        }
+        None => writer.write_u8(0),
+    }
+}
+
+fn read_hash<R: Read>(reader: &mut R) -> Result<Option<[u8; 20]>, Error> {
+    match reader.read_u8()? {
+        0 => Ok(None),
+        _ => {
+            let mut hash = [0; 20];
+            reader.read_exact(&mut hash)?;
+            Ok(Some(hash))
+        }
+    }
+}
+
+fn write_string<W: Write>(writer: &mut W, value: &str) -> Result<(), Error> {
+    writer.write_u16::<BigEndian>(value.len() as u16)?;
+    writer.write_all(value.as_bytes())
+}
+
+fn read_string<R: Read>(reader: &mut R) -> Result<String, Error> {
+    let len = reader.read_u16::<BigEndian>()?;
+    let mut data = vec![0; len as usize];
+    reader.read_exact(&mut data)?;
+
+    String::from_utf8(data).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{PAD_BUTTON_A, PAD_BUTTON_START};
+
+    struct Sequence(u8);
+
+    impl InputSource for Sequence {
+        fn poll(&mut self, _keys: &[Key]) -> [PadState; NUM_PORTS] {
+            self.0 += 1;
+
+            std::array::from_fn(|port| PadState {
+                buttons: if port == 0 {
+                    PAD_BUTTON_A
+                } else {
+                    PAD_BUTTON_START
+                },
+                stick_x: self.0,
+                trigger_r: port as u8,
+                keys: [0x10, self.0, 0],
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    fn record_and_play() {
+        let path = std::env::temp_dir().join(format!("rustcube-movie-{}.rcm", std::process::id()));
+
+        let header = MovieHeader {
+            start: MovieStart::ColdBoot,
+            game_id: "GALE01".to_string(),
+            ipl_hash: Some([0x5A; 20]),
+            memory_cards: [None, Some([0xA5; 20])],
+            settings: vec![(
+                "Core".to_string(),
+                "RtcTime".to_string(),
+                "946684800".to_string(),
+            )],
+        };
+
+        let mut recorded = Vec::new();
+        {
+            let mut recorder =
+                MovieRecorder::create(&path, &header, Box::new(Sequence(0))).unwrap();
+            for _ in 0..3 {
+                recorded.push(recorder.poll(&[]));
+            }
+        }
+
+        let movie = Movie::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(movie.header, header);
+        assert_eq!(movie.polls, recorded);
+
+        let mut player = MoviePlayer::new(movie);
+        for pads in recorded.iter() {
+            assert_eq!(player.poll(&[]), *pads);
+        }
+        assert_eq!(player.poll(&[]), [PadState::default(); NUM_PORTS]);
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            Movie::read(&mut &b"RCM\x1A\x00\x02"[..])
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidData
+        );
+        assert_eq!(
+            Movie::read(&mut &b"DTM\x1A\x00\x01"[..])
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidData
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use crate::{
+    bus::Bus,
+    cheats::is_ram,
+    config::game_files,
+    cpu::{assembler::assemble, Cpu},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Size {
+    Byte,
+    Word,
+    Dword,
+}
+
+#[derive(Debug, PartialEq)]
+struct Entry {
+    addr: u32,
+    size: Size,
+    value: u32,
+    /// Only patch when memory holds this value
+    compare: Option<u32>,
+}
+
+struct Patch {
+    name: String,
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    OnLoad,
+    OnLoadEnabled,
+    OnFrame,
+    OnFrameEnabled,
+    Other,
+}
+
+/// Per-game memory patches in Dolphin's `[OnLoad]`/`[OnFrame]` ini format. Each patch starts
+/// with a `$Name` line followed by `ADDRESS:byte|word|dword:VALUE[:COMPARE]` lines,
+/// instruction replacements are dword patches. `ADDRESS:asm:SOURCE` lines are assembled into
+/// dword patches, instructions are separated by `|`.
+#[derive(Default)]
+pub struct Patches {
+    on_load: Vec<Patch>,
+    on_frame: Vec<Patch>,
+}
+
+impl Patches {
+    /// Load patches for a game from `dir`, `<game code>.ini` is applied before the more
+    /// specific `<game id>.ini`. Missing files are not an error.
+    pub fn for_game<P: AsRef<Path>>(dir: P, game_id: &str) -> Result<Patches, Error> {
+        let mut patches = Patches::default();
+
+        for path in game_files(dir, game_id) {
+            patches.load_text(&fs::read_to_string(&path)?)?;
+        }
+
+        Ok(patches)
+    }
+
+    /// Patches in a section are kept only if listed in the matching `_Enabled` section, when
+    /// one exists
+    pub fn load_text(&mut self, text: &str) -> Result<(), Error> {
+        let mut section = Section::Other;
+        let mut on_load: Vec<Patch> = Vec::new();
+        let mut on_frame: Vec<Patch> = Vec::new();
+        let mut on_load_enabled: Option<HashSet<String>> = None;
+        let mut on_frame_enabled: Option<HashSet<String>> = None;
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('*') || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = match &line[1..line.len() - 1] {
+                    "OnLoad" => Section::OnLoad,
+                    "OnLoad_Enabled" => Section::OnLoadEnabled,
+                    "OnFrame" => Section::OnFrame,
+                    "OnFrame_Enabled" => Section::OnFrameEnabled,
+                    _ => Section::Other,
+                };
+                continue;
+            }
+
+            let patches = match section {
+                Section::OnLoad => &mut on_load,
+                Section::OnFrame => &mut on_frame,
+                Section::OnLoadEnabled | Section::OnFrameEnabled => {
+                    let enabled = if section == Section::OnLoadEnabled {
+                        &mut on_load_enabled
+                    } else {
+                        &mut on_frame_enabled
+                    };
+
+                    if let Some(name) = line.strip_prefix('$') {
+                        enabled
+                            .get_or_insert_with(HashSet::new)
+                            .insert(name.trim().to_string());
+                    }
+                    continue;
+                }
+                Section::Other => continue,
+            };
+
+            if let Some(name) = line.strip_prefix('$') {
+                patches.push(Patch {
+                    name: name.trim().to_string(),
+                    entries: Vec::new(),
+                });
+                continue;
+            }
+
+            let entries = parse_entries(line)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, format!("line {}: {e}", n + 1)))?;
+
+            if patches.is_empty() {
+                patches.push(Patch {
+                    name: String::new(),
+                    entries: Vec::new(),
+                });
+            }
+
+            patches.last_mut().unwrap().entries.extend(entries);
+        }
+
+        let is_enabled = |enabled: &Option<HashSet<String>>, patch: &Patch| {
+            enabled
+                .as_ref()
+                .is_none_or(|enabled| enabled.contains(&patch.name))
+        };
+
+        self.on_load.extend(
+            on_load
+                .into_iter()
+                .filter(|patch| is_enabled(&on_load_enabled, patch)),
+        );
+        self.on_frame.extend(
+            on_frame
+                .into_iter()
+                .filter(|patch| is_enabled(&on_frame_enabled, patch)),
+        );
+
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.on_load.is_empty() && self.on_frame.is_empty()
+    }
+
+    /// Apply all patches once, called after the apploader has finished
+    pub fn apply_on_load(&self, cpu: &mut Cpu, bus: &mut Bus) {
+        for patch in self.on_load.iter().chain(self.on_frame.iter()) {
+            info!("Applying patch: {}", patch.name);
+            apply(patch, cpu, bus);
+        }
+    }
+
+    /// Reapply `[OnFrame]` patches, called on every VI frame
+    pub fn apply_on_frame(&self, cpu: &mut Cpu, bus: &mut Bus) {
+        for patch in self.on_frame.iter() {
+            apply(patch, cpu, bus);
+        }
+    }
+}
+
+fn apply(patch: &Patch, cpu: &mut Cpu, bus: &mut Bus) {
+    for entry in patch.entries.iter() {
+        let current = match entry.size {
+            Size::Byte => cpu.read::<u8>(bus, entry.addr).map(u32::from),
+            Size::Word => cpu.read::<u16>(bus, entry.addr).map(u32::from),
+            Size::Dword => cpu.read::<u32>(bus, entry.addr),
+        };
+
+        let Some(current) = current else {
+            continue;
+        };
+
+        if entry.compare.is_some_and(|compare| compare != current) || current == entry.value {
+            continue;
+        }
+
+        match entry.size {
+            Size::Byte => cpu.write::<u8>(bus, entry.addr, entry.value as u8),
+            Size::Word => cpu.write::<u16>(bus, entry.addr, entry.value as u16),
+            Size::Dword => cpu.write::<u32>(bus, entry.addr, entry.value),
+        };
+    }
+}
+
+/// Addresses are checked here as patches are reapplied on every frame
+fn parse_entries(line: &str) -> Result<Vec<Entry>, String> {
+    let entries = parse_entries_unchecked(line)?;
+
+    if let Some(entry) = entries.iter().find(|entry| !is_ram(entry.addr)) {
+        return Err(format!("`{line}`: invalid address {:#010x}", entry.addr));
+    }
+
+    Ok(entries)
+}
+
+fn parse_entries_unchecked(line: &str) -> Result<Vec<Entry>, String> {
+    let invalid = || format!("invalid patch `{line}`");
+
+    let mut fields = line.splitn(3, ':').map(str::trim);
+    let addr = fields.next().and_then(parse_hex).ok_or_else(invalid)?;
+
+    if !fields
+        .next()
+        .is_some_and(|size| size.eq_ignore_ascii_case("asm"))
+    {
+        return parse_entry(line)
+            .map(|entry| vec![entry])
+            .ok_or_else(invalid);
+    }
+
+    let source = fields.next().unwrap_or_default().replace('|', "\n");
+    let code = assemble(addr, &source).map_err(|e| format!("`{line}`: {}", e.message))?;
+
+    Ok(code
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| Entry {
+            addr: addr.wrapping_add(4 * i as u32),
+            size: Size::Dword,
+            value,
+            compare: None,
+        })
+        .collect())
+}
+
+fn parse_entry(line: &str) -> Option<Entry> {
+    let mut fields = line.split(':').map(str::trim);
+
+    let addr = parse_hex(fields.next()?)?;
+    let size = match fields.next()?.to_ascii_lowercase().as_str() {
+        "byte" => Size::Byte,
+        "word" => Size::Word,
+        "dword" => Size::Dword,
+        _ => return None,
+    };
+    let value = parse_hex(fields.next()?)?;
+    let compare = match fields.next() {
+        Some(compare) => Some(parse_hex(compare)?),
+        None => None,
+    };
+
+    if fields.next().is_some() {
+        return None;
+    }
+
+    Some(Entry {
+        addr,
+        size,
+        value,
+        compare,
+    })
+}
+
+fn parse_hex(value: &str) -> Option<u32> {
+    let value = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+
+    u32::from_str_radix(value, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_text() {
+        let mut patches = Patches::default();
+
+        let text = "
+            [Core]
+            CPUThread = False
+            [OnLoad]
+            $Skip Intro
+            0x80003100:dword:0x60000000
+            [OnFrame]
+            $Fix Timing
+            0x80004000:word:0x1234:0x5678
+            $Disabled
+            0x80004004:byte:0x01
+            [OnFrame_Enabled]
+            $Fix Timing
+        ";
+
+        patches.load_text(text).unwrap();
+
+        assert_eq!(patches.on_load.len(), 1);
+        assert_eq!(
+            patches.on_load[0].entries,
+            vec![Entry {
+                addr: 0x8000_3100,
+                size: Size::Dword,
+                value: 0x6000_0000,
+                compare: None,
+            }]
+        );
+        assert_eq!(patches.on_frame.len(), 1);
+        assert_eq!(patches.on_frame[0].name, "Fix Timing");
+        assert_eq!(patches.on_frame[0].entries[0].compare, Some(0x5678));
+
+        assert!(patches.load_text("[OnFrame]\n0x80003100:qword:0").is_err());
+        assert!(patches
+            .load_text("[OnFrame]\n0x80003100:asm:foo r3")
+            .is_err());
+        assert!(patches.load_text("[OnFrame]\n0xCC003000:word:0").is_err());
+    }
+
+    #[test]
+    fn apply_patches() {
+        let mut cpu = Cpu::default();
+        let mut bus = Bus::default();
+        cpu.emulate_bs2(&mut bus);
+
+        cpu.write::<u16>(&mut bus, 0x8000_4002, 0x1111);
+
+        let mut patches = Patches::default();
+
+        patches
+            .load_text(
+                "
+                [OnLoad]
+                0x80003100:dword:0x60000000
+                $Return One
+                0x80003200:asm:li r3,1 | blr
+                [OnFrame]
+                0x80004000:word:0xAAAA:0x5678
+                0x80004002:word:0xBBBB:0x1111
+                ",
+            )
+            .unwrap();
+
+        patches.apply_on_load(&mut cpu, &mut bus);
+
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8000_3100), Some(0x6000_0000));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8000_3200), Some(0x3860_0001));
+        assert_eq!(cpu.read::<u32>(&mut bus, 0x8000_3204), Some(0x4E80_0020));
+        assert_eq!(cpu.read::<u16>(&mut bus, 0x8000_4000), Some(0));
+        assert_eq!(cpu.read::<u16>(&mut bus, 0x8000_4002), Some(0xBBBB));
+    }
+}
@@ -1,26 +1,126 @@
-use std::path::Path;
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use crate::{
     bus::Bus,
+    cheats::Cheats,
+    config::{BbaBackend, Config, ExitBehavior, PortDevice, Region, SerialPortDevice, SlotDevice},
     cpu::Cpu,
     disc::Disc,
     dol::Dol,
     dsp::DspInterface,
-    hw::{ai::AudioInterface, vi::VideoInterface},
+    hw::{
+        ai::AudioInterface,
+        exi::{
+            Device, DeviceBba, DeviceMemoryCard, DeviceSdGecko, DeviceUsbGecko, ExternalInterface,
+            PacketBackend, Sram, UdpBackend,
+        },
+        pi::ProcessorInterface,
+        si::{
+            DeviceBongos, DeviceController, DeviceGba, DeviceKeyboard, DeviceSteeringWheel,
+            SerialInterface, SiDevice, GBA_CLOCK_PORT, GBA_DATA_PORT,
+        },
+        vi::VideoInterface,
+    },
+    input::{InputSource, KeyboardInput},
+    movie::{Movie, MovieHeader, MoviePlayer, MovieRecorder, MovieStart},
+    patches::Patches,
+    utils::sha1,
 };
 
-#[derive(Default)]
+/// Default directory searched for per-game settings and patch files
+const GAME_SETTINGS_DIR: &str = "games";
+
 pub struct System {
     cpu: Cpu,
     bus: Bus,
+    cheats: Cheats,
+    patches: Patches,
+    /// Global settings, per-game settings are merged over these when a disc is loaded
+    config: Config,
+    game_settings_dir: Option<PathBuf>,
+    /// Id of the loaded disc, empty for a DOL or the IPL
+    game_id: String,
+    /// SHA-1 of the loaded IPL image
+    ipl_hash: Option<[u8; 20]>,
+    frame: u64,
+    /// Frames the reset button is pressed on
+    reset_frames: BTreeSet<u64>,
+    /// Time the last frame was presented, for the frame limiter
+    frame_time: Instant,
+    stopped: bool,
+}
+
+impl Default for System {
+    fn default() -> Self {
+        System::new(Config::default())
+    }
 }
 
 impl System {
+    pub fn new(config: Config) -> System {
+        let mut system = System {
+            cpu: Default::default(),
+            bus: Default::default(),
+            cheats: Default::default(),
+            patches: Default::default(),
+            config,
+            game_settings_dir: None,
+            game_id: String::new(),
+            ipl_hash: None,
+            frame: 0,
+            reset_frames: BTreeSet::new(),
+            frame_time: Instant::now(),
+            stopped: false,
+        };
+
+        let config = system.config.clone();
+
+        system.bus.dsp.load_roms(&config.dsp_rom, &config.dsp_coef);
+        if let Some(path) = config.sram.as_ref() {
+            if let Err(e) = system.bus.exi.load_sram(path) {
+                error!("Unable to load SRAM {}: {}", path.display(), e);
+            }
+        }
+
+        system.apply_sram_settings(&config);
+
+        let rtc_time = config.rtc_time.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or(0)
+        });
+        system.bus.exi.set_rtc(rtc_time);
+        system.bus.vi.set_headless(config.headless);
+        system
+            .bus
+            .si
+            .set_input(Box::new(KeyboardInput::new(config.key_maps.clone())));
+
+        system.insert_slot_devices(&config);
+        system.insert_serial_port_1(&config);
+        system.insert_port_devices(&config);
+
+        if let Some(ipl) = config.ipl {
+            system.load_ipl(ipl);
+        }
+
+        system
+    }
+
     pub fn load_dol<P: AsRef<Path>>(&mut self, path: P) {
         let dol = Dol::open(path).unwrap();
 
         self.cpu.emulate_bs2(&mut self.bus);
 
+        self.apply_config(&self.config.clone(), None);
+
         dol.load(&mut self.cpu, &mut self.bus);
 
         self.cpu.cia = dol.get_entry_point();
@@ -29,22 +129,609 @@ impl System {
     pub fn load_iso<P: AsRef<Path>>(&mut self, path: P) {
         let mut disc = Disc::open(path).unwrap();
 
+        let game_id = disc.game_id();
+        let dir = self
+            .game_settings_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(GAME_SETTINGS_DIR));
+
+        let config = self.config.for_game(&dir, &game_id).unwrap_or_else(|e| {
+            warn!("Unable to load settings for {}: {}", game_id, e);
+            self.config.clone()
+        });
+
+        self.patches = Patches::for_game(&dir, &game_id).unwrap_or_else(|e| {
+            warn!("Unable to load patches for {}: {}", game_id, e);
+            Patches::default()
+        });
+
         self.cpu.emulate_bs2(&mut self.bus);
 
-        disc.load(&mut self.cpu, &mut self.bus).unwrap(); // fix this and don't be lazy
+        self.apply_config(&config, Region::from_game_code(disc.game_code()));
+
+        disc.load(&mut self.cpu, &mut self.bus, &self.patches)
+            .unwrap(); // fix this and don't be lazy
 
         self.bus.di.set_disc(Some(disc));
+        self.game_id = game_id;
     }
 
     pub fn load_ipl<P: AsRef<Path>>(&mut self, path: P) {
+        match fs::read(path.as_ref()) {
+            Ok(data) => self.ipl_hash = Some(sha1(&data)),
+            Err(e) => warn!("Unable to hash IPL {}: {}", path.as_ref().display(), e),
+        }
+
         self.bus.bootrom.load_ipl(path);
     }
 
+    /// Directory searched for `<game id>.ini` settings and patch files, must be set before
+    /// loading a disc
+    pub fn set_game_settings_dir<P: AsRef<Path>>(&mut self, path: P) {
+        self.game_settings_dir = Some(path.as_ref().to_path_buf());
+    }
+
+    /// Load Gecko/Action Replay codes, applied on every frame
+    pub fn load_cheats<P: AsRef<Path>>(&mut self, path: P) -> Result<(), io::Error> {
+        self.cheats.load(path)
+    }
+
+    /// Run until the configured number of frames have been emulated or the window is closed
+    pub fn run(&mut self) {
+        while !self.stopped {
+            self.step();
+        }
+
+        if self.config.on_exit == ExitBehavior::Wait && !self.config.headless {
+            while !self.bus.vi.window_closed() {
+                self.bus.vi.present();
+                thread::sleep(Duration::from_millis(16));
+            }
+        }
+    }
+
     pub fn step(&mut self) {
-        VideoInterface::update(&mut self.bus, &mut self.cpu.state);
+        self.update_video();
+
         DspInterface::update(&mut self.bus, &mut self.cpu.state);
+        ExternalInterface::update(&mut self.bus, &mut self.cpu.state);
+        SerialInterface::update(&mut self.bus, &mut self.cpu.state);
         AudioInterface::update(&mut self.bus, &mut self.cpu.state);
+        ProcessorInterface::update(&mut self.bus, &mut self.cpu.state);
 
         self.cpu.step(&mut self.bus);
+
+        if let Some(clear_memory) = self.bus.pi.take_system_reset() {
+            self.system_reset(clear_memory);
+        }
+    }
+
+    /// Run the VI, frame work is done when a new frame starts
+    fn update_video(&mut self) {
+        VideoInterface::update(&mut self.bus, &mut self.cpu.state);
+
+        if self.bus.vi.frame_count() != self.frame {
+            self.frame = self.bus.vi.frame_count();
+            self.on_frame();
+        }
+    }
+
+    /// Tap the reset button
+    pub fn press_reset(&mut self) {
+        ProcessorInterface::press_reset_button(&mut self.bus, &mut self.cpu.state);
+    }
+
+    /// Tap the reset button once frame `frame` starts
+    pub fn press_reset_at(&mut self, frame: u64) {
+        self.reset_frames.insert(frame);
+    }
+
+    /// Reset requested by software through the PI, the IPL boots again
+    fn system_reset(&mut self, clear_memory: bool) {
+        info!("System reset");
+
+        if self.ipl_hash.is_none() {
+            warn!("System reset without an IPL loaded, stopping");
+            self.stopped = true;
+            return;
+        }
+
+        self.cpu.reset();
+        self.bus.reset();
+        if clear_memory {
+            self.bus.memory.clear();
+        }
+    }
+
+    /// `region` is the disc region, used unless the config overrides it
+    fn apply_config(&mut self, config: &Config, region: Option<Region>) {
+        self.cpu.set_timing(config.cpu_timing);
+        self.bus.dsp.set_mode(config.dsp_mode);
+
+        if config.slot_a != self.config.slot_a
+            || config.slot_b != self.config.slot_b
+            || config.memcard_a != self.config.memcard_a
+            || config.memcard_b != self.config.memcard_b
+            || config.sd_card_a != self.config.sd_card_a
+            || config.sd_card_b != self.config.sd_card_b
+        {
+            self.insert_slot_devices(config);
+        }
+
+        if config.serial_port_1 != self.config.serial_port_1 || config.bba != self.config.bba {
+            self.insert_serial_port_1(config);
+        }
+
+        if config.ports != self.config.ports {
+            self.insert_port_devices(config);
+        }
+
+        // Video mode set by the IPL (0 - NTSC, 1 - PAL)
+        if config.region.or(region) == Some(Region::Pal) {
+            self.cpu.write::<u32>(&mut self.bus, 0x8000_00CC, 1);
+        }
+
+        // hacks are read by the code that owns them, none exist yet
+        for hack in config.hacks.iter() {
+            warn!("Hack {} is enabled but not implemented", hack);
+        }
+    }
+
+    /// Source of controller state, replaces the keyboard input of the emulator window
+    pub fn set_input(&mut self, input: Box<dyn InputSource>) {
+        self.bus.si.set_input(input);
+    }
+
+    /// Record the controller state of every poll to `path`, from power on
+    pub fn record_movie<P: AsRef<Path>>(&mut self, path: P) -> Result<(), io::Error> {
+        if self.bus.vi.frame_count() != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "movies can only be recorded from power on",
+            ));
+        }
+
+        let header = MovieHeader {
+            start: MovieStart::ColdBoot,
+            game_id: self.game_id.clone(),
+            ipl_hash: self.ipl_hash,
+            memory_cards: self.start_movie(),
+            settings: self.config.movie_settings(),
+        };
+
+        let input = self.bus.si.take_input();
+        let recorder = MovieRecorder::create(path, &header, input)?;
+        self.bus.si.set_input(Box::new(recorder));
+
+        Ok(())
+    }
+
+    /// Replace the controller input with a recorded movie, from power on. The settings of the
+    /// movie should have been applied to the config the system was created with.
+    pub fn play_movie(&mut self, movie: Movie) -> Result<(), io::Error> {
+        if let MovieStart::SaveState(name) = &movie.header.start {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("movie starts from save state {name}, save states are not supported"),
+            ));
+        }
+
+        if self.bus.vi.frame_count() != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "movies can only be played from power on",
+            ));
+        }
+
+        if movie.header.game_id != self.game_id {
+            warn!(
+                "Movie: recorded with game {:?}, playing {:?}",
+                movie.header.game_id, self.game_id
+            );
+        }
+
+        if movie.header.ipl_hash != self.ipl_hash {
+            warn!("Movie: recorded with a different IPL");
+        }
+
+        let memory_cards = self.start_movie();
+        for (slot, hash) in memory_cards.iter().enumerate() {
+            if movie.header.memory_cards[slot] != *hash {
+                warn!(
+                    "Movie: recorded with a different memory card in slot {}",
+                    if slot == 0 { 'A' } else { 'B' }
+                );
+            }
+        }
+
+        let settings = self.config.movie_settings();
+        for setting in movie.header.settings.iter() {
+            if !settings.contains(setting) {
+                warn!(
+                    "Movie: recorded with {}.{} = {}",
+                    setting.0, setting.1, setting.2
+                );
+            }
+        }
+
+        info!("Movie: playing {} polls", movie.polls.len());
+        self.bus.si.set_input(Box::new(MoviePlayer::new(movie)));
+
+        Ok(())
+    }
+
+    /// Typed view of SRAM, as it is persisted and read by the IPL and games
+    pub fn sram(&self) -> Sram {
+        self.bus.exi.sram()
+    }
+
+    pub fn set_sram(&mut self, sram: &Sram) {
+        self.bus.exi.set_sram(sram);
+    }
+
+    /// Put SRAM and the memory cards in the same state for every recording and playback of a
+    /// movie: SRAM starts from its defaults with the configured settings and neither it nor the
+    /// cards are written back. Returns the SHA-1 of the cards in slots A and B.
+    fn start_movie(&mut self) -> [Option<[u8; 20]>; 2] {
+        let config = self.config.clone();
+
+        if config.sram.is_some() {
+            info!("Movie: SRAM is not loaded from or saved to its file");
+        }
+        self.bus.exi.reset_sram();
+        self.apply_sram_settings(&config);
+
+        let mut hashes = [None; 2];
+
+        for (slot, kind) in [config.slot_a, config.slot_b].into_iter().enumerate() {
+            if kind != SlotDevice::MemoryCard {
+                continue;
+            }
+
+            // reopened so folder cards are built for the reset SRAM
+            self.bus.exi.insert_device(slot, None);
+
+            let card = self.open_memory_card(slot, &config).map(|mut card| {
+                card.detach();
+                hashes[slot] = Some(sha1(card.data()));
+                Box::new(card) as Box<dyn Device>
+            });
+
+            self.bus.exi.insert_device(slot, card);
+        }
+
+        if hashes.iter().any(Option::is_some) {
+            info!("Movie: memory cards are not written back");
+        }
+
+        hashes
+    }
+
+    fn apply_sram_settings(&mut self, config: &Config) {
+        let settings = &config.sram_settings;
+        let mut sram = self.bus.exi.sram();
+
+        if let Some(language) = config.language {
+            sram.set_language(language);
+        }
+        if let Some(offset) = settings.display_offset {
+            sram.set_display_offset(offset);
+        }
+        if let Some(mode) = settings.sound_mode {
+            sram.set_sound_mode(mode);
+        }
+        if let Some(mode) = settings.video_mode {
+            sram.set_video_mode(mode);
+        }
+        if let Some(enabled) = settings.progressive_scan {
+            sram.set_progressive_scan(enabled);
+        }
+        if let Some(enabled) = settings.pal60 {
+            sram.set_pal60(enabled);
+        }
+        for (pad, id) in settings.wireless_pad_ids.iter().enumerate() {
+            if let Some(id) = id {
+                sram.set_wireless_pad_id(pad, *id);
+            }
+        }
+        if let Some(error) = settings.last_dvd_error {
+            sram.set_last_dvd_error(error);
+        }
+
+        self.bus.exi.set_sram(&sram);
+    }
+
+    fn insert_slot_devices(&mut self, config: &Config) {
+        for (slot, kind) in [config.slot_a, config.slot_b].into_iter().enumerate() {
+            // the old device releases its file or port first
+            self.bus.exi.insert_device(slot, None);
+
+            let device: Option<Box<dyn Device>> = match kind {
+                SlotDevice::None => None,
+                SlotDevice::MemoryCard => self
+                    .open_memory_card(slot, config)
+                    .map(|card| Box::new(card) as Box<dyn Device>),
+                SlotDevice::UsbGecko => DeviceUsbGecko::new(config.usb_gecko_port + slot as u16)
+                    .map_err(|e| error!("Unable to start USB Gecko: {}", e))
+                    .ok()
+                    .map(|gecko| Box::new(gecko) as Box<dyn Device>),
+                SlotDevice::SdGecko => self
+                    .open_sd_card(slot, config)
+                    .map(|sd| Box::new(sd) as Box<dyn Device>),
+            };
+
+            self.bus.exi.insert_device(slot, device);
+        }
+    }
+
+    fn insert_port_devices(&mut self, config: &Config) {
+        for (port, kind) in config.ports.into_iter().enumerate() {
+            // the old device releases its ports first
+            self.bus.si.set_device(port, None);
+
+            let device: Option<Box<dyn SiDevice>> = match kind {
+                PortDevice::None => None,
+                PortDevice::Controller => Some(Box::new(DeviceController::default())),
+                PortDevice::Gba => {
+                    DeviceGba::new(GBA_DATA_PORT + port as u16, GBA_CLOCK_PORT + port as u16)
+                        .map_err(|e| error!("Unable to start GBA link: {}", e))
+                        .ok()
+                        .map(|gba| Box::new(gba) as Box<dyn SiDevice>)
+                }
+                PortDevice::Keyboard => Some(Box::new(DeviceKeyboard::default())),
+                PortDevice::SteeringWheel => Some(Box::new(DeviceSteeringWheel::default())),
+                PortDevice::Bongos => Some(Box::new(DeviceBongos::default())),
+            };
+
+            self.bus.si.set_device(port, device);
+        }
+    }
+
+    fn insert_serial_port_1(&mut self, config: &Config) {
+        // the old adapter releases its socket or interface first
+        self.bus.exi.insert_serial_port_1(None);
+
+        if config.serial_port_1 == SerialPortDevice::None {
+            return;
+        }
+
+        let bba = &config.bba;
+        let backend: io::Result<Box<dyn PacketBackend>> = match bba.backend {
+            BbaBackend::Udp => UdpBackend::new(bba.local_addr, bba.peer_addr)
+                .map(|backend| Box::new(backend) as Box<dyn PacketBackend>),
+            #[cfg(target_os = "linux")]
+            BbaBackend::Tap => crate::hw::exi::TapBackend::new(&bba.tap_device)
+                .map(|backend| Box::new(backend) as Box<dyn PacketBackend>),
+            #[cfg(not(target_os = "linux"))]
+            BbaBackend::Tap => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "TAP interfaces are only supported on Linux",
+            )),
+        };
+
+        match backend {
+            Ok(backend) => self
+                .bus
+                .exi
+                .insert_serial_port_1(Some(Box::new(DeviceBba::new(backend, bba.mac)))),
+            Err(e) => error!("Unable to start the Broadband Adapter: {}", e),
+        }
+    }
+
+    fn open_memory_card(&self, slot: usize, config: &Config) -> Option<DeviceMemoryCard> {
+        let (path, blocks) = match slot {
+            0 => (config.memcard_a.as_ref()?, config.memcard_a_size),
+            _ => (config.memcard_b.as_ref()?, config.memcard_b_size),
+        };
+
+        if path.is_dir() {
+            let sram = self.bus.exi.sram();
+            let flash_id = sram.flash_id(slot);
+            DeviceMemoryCard::open_folder(path, blocks, &flash_id, sram.language())
+        } else {
+            DeviceMemoryCard::open(path, blocks)
+        }
+        .map_err(|e| error!("Unable to open memory card {}: {}", path.display(), e))
+        .ok()
+    }
+
+    fn open_sd_card(&self, slot: usize, config: &Config) -> Option<DeviceSdGecko> {
+        let path = match slot {
+            0 => config.sd_card_a.as_ref(),
+            _ => config.sd_card_b.as_ref(),
+        };
+
+        let Some(path) = path else {
+            error!("SD Gecko in slot {} has no SD card image", ['A', 'B'][slot]);
+            return None;
+        };
+
+        DeviceSdGecko::open(path)
+            .map_err(|e| error!("Unable to open SD card {}: {}", path.display(), e))
+            .ok()
+    }
+
+    fn on_frame(&mut self) {
+        if self.bus.vi.window_closed()
+            || (self.config.frames != 0 && self.frame >= self.config.frames)
+        {
+            self.stopped = true;
+        }
+
+        if self.config.frame_limit != 0 {
+            let frame_duration = Duration::from_secs(1) / self.config.frame_limit;
+            let elapsed = self.frame_time.elapsed();
+
+            if elapsed < frame_duration {
+                thread::sleep(frame_duration - elapsed);
+            }
+
+            self.frame_time = Instant::now();
+        }
+
+        self.bus.si.on_frame(self.frame);
+
+        if self.reset_frames.remove(&self.frame) {
+            self.press_reset();
+        }
+
+        if !self.patches.is_empty() {
+            self.patches.apply_on_frame(&mut self.cpu, &mut self.bus);
+        }
+
+        if !self.cheats.is_empty() {
+            self.cheats.apply(&mut self.cpu, &mut self.bus);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VideoMode;
+
+    const VI_BASE: u32 = 0x0C00_2000;
+    const PI_RESET: u32 = 0x0C00_3024;
+
+    #[test]
+    fn pal_frames() {
+        let mut system = System::new(Config {
+            headless: true,
+            frame_limit: 0,
+            frames: 2,
+            ..Default::default()
+        });
+        let cpu_state = &mut system.cpu.state;
+
+        // PAL 50Hz timing as set up by the SDK, 625 lines per frame
+        system.bus.write::<u16>(cpu_state, VI_BASE, 287 << 4 | 5);
+        system
+            .bus
+            .write::<u32>(cpu_state, VI_BASE + 0x0C, 0x0001_0023);
+        system
+            .bus
+            .write::<u32>(cpu_state, VI_BASE + 0x10, 0x0000_0024);
+        // enabled, PAL format
+        system.bus.write::<u16>(cpu_state, VI_BASE + 0x02, 0x0101);
+
+        for _ in 0..1250 * 2 {
+            assert!(!system.stopped);
+            system.cpu.state.timers.tick(601);
+            system.update_video();
+        }
+
+        assert_eq!(system.frame, 2);
+        assert!(system.stopped);
+    }
+
+    #[test]
+    fn system_reset() {
+        let config = Config {
+            headless: true,
+            ..Default::default()
+        };
+
+        // registers of the VI, MI and DSP, and of the PI, SI, EXI and AI
+        let registers_u16 = [0x0C00_2002, 0x0C00_401C, 0x0C00_500A];
+        let registers_u32 = [0x0C00_3004, 0x0C00_6430, 0x0C00_6808, 0x0C00_6C00];
+
+        let read_registers = |system: &mut System| {
+            let cpu_state = &mut system.cpu.state;
+            let mut values: Vec<u32> = registers_u16
+                .iter()
+                .map(|&addr| system.bus.read::<u16>(cpu_state, addr) as u32)
+                .collect();
+            values.extend(
+                registers_u32
+                    .iter()
+                    .map(|&addr| system.bus.read::<u32>(cpu_state, addr)),
+            );
+            values
+        };
+
+        let power_on_values = read_registers(&mut System::new(config.clone()));
+
+        let mut system = System::new(config);
+        system.ipl_hash = Some([0; 20]);
+        system.cpu.cia = 0x8000_3100;
+        system.cpu.gpr[3] = 0x1234;
+
+        let cpu_state = &mut system.cpu.state;
+        system.bus.write::<u32>(cpu_state, 0x100, 0xCAFE_F00D);
+        system.bus.write::<u32>(cpu_state, 0x0C00_3004, 0xFF);
+        system.bus.write::<u16>(cpu_state, 0x0C00_2002, 0x0101);
+        system.bus.write::<u16>(cpu_state, 0x0C00_401C, 0x1F);
+        system.bus.write::<u16>(cpu_state, 0x0C00_500A, 0x0010);
+        system.bus.write::<u32>(cpu_state, 0x0C00_6430, 0x00F0_0200);
+        system.bus.write::<u32>(cpu_state, 0x0C00_6808, 0x20);
+        system.bus.write::<u32>(cpu_state, 0x0C00_6C00, 0x1);
+
+        // hot reset with reset code 1, keeping memory
+        system
+            .bus
+            .write::<u32>(&mut system.cpu.state, PI_RESET, 1 << 3 | 0x6);
+        let clear_memory = system.bus.pi.take_system_reset().unwrap();
+        system.system_reset(clear_memory);
+
+        assert!(!system.stopped);
+        assert_eq!(system.cpu.cia, Cpu::default().cia);
+        assert_eq!(system.cpu.gpr[3], 0);
+
+        assert_eq!(read_registers(&mut system), power_on_values);
+
+        let cpu_state = &mut system.cpu.state;
+        assert_eq!(system.bus.read::<u32>(cpu_state, PI_RESET) >> 3, 1);
+        assert_eq!(system.bus.read::<u32>(cpu_state, 0x100), 0xCAFE_F00D);
+    }
+
+    #[test]
+    fn movie_start() {
+        let dir = std::env::temp_dir().join(format!("rustcube-movie-start-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let sram_path = dir.join("sram.raw");
+        let card_path = dir.join("card_a.raw");
+        let movie_path = dir.join("movie.rcm");
+
+        let mut config = Config {
+            headless: true,
+            sram: Some(sram_path.clone()),
+            memcard_a: Some(card_path.clone()),
+            slot_a: SlotDevice::MemoryCard,
+            ..Default::default()
+        };
+        config.sram_settings.video_mode = Some(VideoMode::Pal);
+
+        // SRAM changed by an earlier run
+        let mut sram = Sram::default();
+        sram.set_display_offset(-8);
+        let mut data = vec![0; 4];
+        data.extend_from_slice(sram.as_bytes());
+        fs::write(&sram_path, &data).unwrap();
+
+        let mut system = System::new(config);
+        assert_eq!(system.bus.exi.sram().display_offset(), -8);
+
+        system.record_movie(&movie_path).unwrap();
+
+        let sram = system.bus.exi.sram();
+        assert_eq!(sram.display_offset(), 0);
+        assert_eq!(sram.video_mode(), VideoMode::Pal);
+        drop(system);
+
+        let movie = Movie::open(&movie_path).unwrap();
+        assert_eq!(
+            movie.header.memory_cards,
+            [Some(sha1(&fs::read(&card_path).unwrap())), None]
+        );
+        assert!(movie.header.settings.contains(&(
+            "SRAM".to_string(),
+            "VideoMode".to_string(),
+            "pal".to_string()
+        )));
+        // the SRAM file is left as it was
+        assert_eq!(fs::read(&sram_path).unwrap(), data);
+
+        fs::remove_dir_all(&dir).unwrap();
     }
 }